@@ -0,0 +1,132 @@
+//! Deserialization of wide and deep documents
+//!
+//! `untagged` is the former implementation, which reads the second item of a tag through an
+//! untagged enum and so buffers the whole first child before deserializing it again.
+//! Run with `cargo bench --bench deserialize`.
+//!
+//! Median times with Rust 1.95 on an Intel Xeon virtual machine with one core:
+//!
+//! | Document                | `untagged` | `Element` | `ElementRef` |
+//! |-------------------------|-----------:|----------:|-------------:|
+//! | `wide`, 10,000 children |    10.0 ms |   6.97 ms |      4.70 ms |
+//! | `deep`, 25 levels       |    22.6 µs |   3.78 µs |      3.22 µs |
+//! | `deep`, 50 levels       |    82.1 µs |   7.86 µs |      6.53 µs |
+//! | `deep`, 100 levels      |     341 µs |   16.7 µs |      14.2 µs |
+//!
+//! Each level of a deep document is buffered once more by `untagged`, so doubling the depth
+//! quadruples its time, while it doubles that of `Element`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jsonml::{Element, ElementRef};
+
+/// Root with many children, each with attributes and text
+fn wide(children: usize) -> String {
+    let children: Vec<_> = (0..children)
+        .map(|i| format!(r#"["li", {{"class": "item", "id": "i{i}"}}, "Item {i}"]"#))
+        .collect();
+    format!(r#"["ul", {}]"#, children.join(", "))
+}
+
+/// Tags nested without attributes, so that the second item of each tag is a child
+fn deep(depth: usize) -> String {
+    let mut json = r#""leaf""#.to_string();
+    for _ in 0..depth {
+        json = format!(r#"["div", {json}, "text"]"#);
+    }
+    json
+}
+
+mod untagged {
+    use std::fmt;
+
+    use jsonml::Attributes;
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        Deserialize, Deserializer,
+    };
+
+    #[allow(dead_code)]
+    pub enum Node {
+        Tag(String, Attributes, Vec<Node>),
+        String(String),
+    }
+
+    impl<'de> Deserialize<'de> for Node {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_any(NodeVisitor)
+        }
+    }
+
+    struct NodeVisitor;
+
+    impl<'de> Visitor<'de> for NodeVisitor {
+        type Value = Node;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("JsonML element")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Node::String(v.to_string()))
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            #[derive(Deserialize)]
+            #[serde(untagged)]
+            enum AttributesOrElement {
+                Attributes(Attributes),
+                Element(Node),
+            }
+
+            let name: String = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::missing_field("name"))?;
+            let mut attributes = Attributes::default();
+            let mut element_list = vec![];
+            if let Some(attributes_or_element) = seq.next_element()? {
+                match attributes_or_element {
+                    AttributesOrElement::Attributes(attrs) => attributes = attrs,
+                    AttributesOrElement::Element(element) => element_list.push(element),
+                }
+            }
+            while let Some(element) = seq.next_element()? {
+                element_list.push(element);
+            }
+            Ok(Node::Tag(name, attributes, element_list))
+        }
+    }
+}
+
+fn bench_documents(c: &mut Criterion) {
+    // serde_json limits nesting to 128 arrays.
+    for (name, json) in [
+        ("wide", wide(10_000)),
+        ("deep-25", deep(25)),
+        ("deep-50", deep(50)),
+        ("deep-100", deep(100)),
+    ] {
+        let mut group = c.benchmark_group(name);
+        group.bench_function("untagged", |b| {
+            b.iter(|| serde_json::from_str::<untagged::Node>(black_box(&json)).unwrap())
+        });
+        group.bench_function("Element", |b| {
+            b.iter(|| serde_json::from_str::<Element>(black_box(&json)).unwrap())
+        });
+        group.bench_function("ElementRef", |b| {
+            b.iter(|| serde_json::from_str::<ElementRef>(black_box(&json)).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_documents);
+criterion_main!(benches);
@@ -0,0 +1,508 @@
+//! `ToJsonML` and `FromJsonML` derives
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Ident, LitStr, PathArguments,
+    Type,
+};
+
+use crate::markup;
+
+/// Type and its tags converted from, either a struct or an enum
+struct Container {
+    ident: Ident,
+    body: Body,
+}
+
+enum Body {
+    Struct(Shape),
+    Enum(Vec<Variant>),
+}
+
+struct Variant {
+    ident: Ident,
+    kind: VariantKind,
+}
+
+enum VariantKind {
+    Shape(Shape),
+    /// Variant with a single unnamed field, converted as that field
+    Newtype(Type),
+}
+
+/// Tag with fields
+struct Shape {
+    name: String,
+    fields: Vec<Field>,
+}
+
+struct Field {
+    ident: Ident,
+    ty: Type,
+    mapping: Mapping,
+}
+
+enum Mapping {
+    Attribute(String),
+    Child,
+    Text,
+    Skip,
+}
+
+/// Field type as a child
+enum ChildKind {
+    Required,
+    Optional,
+    Repeated,
+}
+
+pub(crate) fn derive_to_jsonml(input: DeriveInput) -> Result<TokenStream, Error> {
+    let container = Container::parse(&input)?;
+    let ident = &container.ident;
+    let body = match &container.body {
+        Body::Struct(shape) => {
+            let bindings = shape.bindings();
+            let tag = shape.expand_to_tag();
+            quote! {
+                let #ident { #(#bindings),* } = self;
+                #tag
+            }
+        }
+        Body::Enum(variants) => {
+            let arms = variants.iter().map(|variant| {
+                let variant_ident = &variant.ident;
+                match &variant.kind {
+                    VariantKind::Shape(shape) => {
+                        let bindings = shape.bindings();
+                        let tag = shape.expand_to_tag();
+                        quote!(#ident::#variant_ident { #(#bindings),* } => { #tag })
+                    }
+                    VariantKind::Newtype(_) => quote! {
+                        #ident::#variant_ident(value) => ::jsonml::ToJsonML::to_jsonml(value),
+                    },
+                }
+            });
+            if variants.is_empty() {
+                quote!(match *self {})
+            } else {
+                quote!(match self { #(#arms)* })
+            }
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::jsonml::ToJsonML for #ident #ty_generics #where_clause {
+            fn to_jsonml(&self) -> ::jsonml::Element {
+                #body
+            }
+        }
+    })
+}
+
+pub(crate) fn derive_from_jsonml(input: DeriveInput) -> Result<TokenStream, Error> {
+    let container = Container::parse(&input)?;
+    let ident = &container.ident;
+    let (from_jsonml, matches) = match &container.body {
+        Body::Struct(shape) => {
+            let name = &shape.name;
+            let from_tag = shape.expand_from_tag(quote!(#ident));
+            (
+                quote! {
+                    let tag = ::jsonml::convert::expect_tag(element, &[#name])?;
+                    #from_tag
+                },
+                quote!(::jsonml::convert::is_tag(element, &[#name])),
+            )
+        }
+        Body::Enum(variants) => {
+            let names: Vec<&String> = variants
+                .iter()
+                .filter_map(|variant| match &variant.kind {
+                    VariantKind::Shape(shape) => Some(&shape.name),
+                    VariantKind::Newtype(_) => None,
+                })
+                .collect();
+            let candidates = variants.iter().map(|variant| {
+                let variant_ident = &variant.ident;
+                match &variant.kind {
+                    VariantKind::Shape(shape) => {
+                        let name = &shape.name;
+                        let from_tag = shape.expand_from_tag(quote!(#ident::#variant_ident));
+                        quote! {
+                            if let ::jsonml::Element::Tag(tag) = element {
+                                if tag.name() == #name {
+                                    #from_tag
+                                }
+                            }
+                        }
+                    }
+                    VariantKind::Newtype(ty) => quote! {
+                        if <#ty as ::jsonml::FromJsonML>::matches(element) {
+                            return ::std::result::Result::map(
+                                <#ty as ::jsonml::FromJsonML>::from_jsonml(element),
+                                #ident::#variant_ident,
+                            );
+                        }
+                    },
+                }
+            });
+            let matches = variants.iter().map(|variant| match &variant.kind {
+                VariantKind::Shape(shape) => {
+                    let name = &shape.name;
+                    quote!(::jsonml::convert::is_tag(element, &[#name]))
+                }
+                VariantKind::Newtype(ty) => {
+                    quote!(<#ty as ::jsonml::FromJsonML>::matches(element))
+                }
+            });
+            // The tags accepted by variants converted as their fields are not known here.
+            let error = if names.is_empty() || names.len() < variants.len() {
+                let type_name = ident.to_string();
+                quote! {
+                    ::jsonml::convert::FromJsonMLError::new(
+                        ::jsonml::convert::FromJsonMLErrorKind::NoMatchingVariant {
+                            type_name: ::std::string::ToString::to_string(#type_name),
+                        },
+                    )
+                }
+            } else {
+                quote! {
+                    ::jsonml::convert::expect_tag(element, &[#(#names),*])
+                        .err()
+                        .unwrap_or_else(|| ::jsonml::convert::FromJsonMLError::new(
+                            ::jsonml::convert::FromJsonMLErrorKind::UnexpectedChild,
+                        ))
+                }
+            };
+            (
+                quote! {
+                    #(#candidates)*
+                    ::std::result::Result::Err(#error)
+                },
+                quote!(false #(|| #matches)*),
+            )
+        }
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::jsonml::FromJsonML for #ident #ty_generics #where_clause {
+            fn from_jsonml(
+                element: &::jsonml::Element,
+            ) -> ::std::result::Result<Self, ::jsonml::convert::FromJsonMLError> {
+                #from_jsonml
+            }
+
+            fn matches(element: &::jsonml::Element) -> bool {
+                #matches
+            }
+        }
+    })
+}
+
+impl Container {
+    fn parse(input: &DeriveInput) -> Result<Self, Error> {
+        let body = match &input.data {
+            Data::Struct(data) => {
+                let name = parse_tag_name(&input.attrs, &input.ident)?;
+                Body::Struct(Shape::parse(name, &data.fields, &input.ident)?)
+            }
+            Data::Enum(data) => {
+                reject_tag_name(&input.attrs)?;
+                let variants = data
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let kind = match &variant.fields {
+                            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                                reject_tag_name(&variant.attrs)?;
+                                VariantKind::Newtype(fields.unnamed[0].ty.clone())
+                            }
+                            fields => {
+                                let name = parse_tag_name(&variant.attrs, &variant.ident)?;
+                                VariantKind::Shape(Shape::parse(name, fields, &variant.ident)?)
+                            }
+                        };
+                        Ok(Variant {
+                            ident: variant.ident.clone(),
+                            kind,
+                        })
+                    })
+                    .collect::<Result<Vec<Variant>, Error>>()?;
+                for (index, variant) in variants.iter().enumerate() {
+                    let name = match &variant.kind {
+                        VariantKind::Shape(shape) => &shape.name,
+                        VariantKind::Newtype(_) => continue,
+                    };
+                    let previous = variants[..index].iter().find(|previous| {
+                        matches!(&previous.kind, VariantKind::Shape(shape) if &shape.name == name)
+                    });
+                    if let Some(previous) = previous {
+                        return Err(Error::new(
+                            variant.ident.span(),
+                            format!(
+                                "tag name {name:?} is already used by variant `{}`",
+                                previous.ident
+                            ),
+                        ));
+                    }
+                }
+                Body::Enum(variants)
+            }
+            Data::Union(data) => {
+                return Err(Error::new(
+                    data.union_token.span,
+                    "unions cannot be converted to elements",
+                ))
+            }
+        };
+        Ok(Container {
+            ident: input.ident.clone(),
+            body,
+        })
+    }
+}
+
+impl Shape {
+    fn parse(name: String, fields: &Fields, ident: &Ident) -> Result<Self, Error> {
+        let fields = match fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| {
+                    let ident = field.ident.clone().expect("named field has an ident");
+                    Ok(Field {
+                        mapping: parse_mapping(&field.attrs, &ident)?,
+                        ident,
+                        ty: field.ty.clone(),
+                    })
+                })
+                .collect::<Result<_, Error>>()?,
+            Fields::Unnamed(fields) => {
+                return Err(Error::new_spanned(
+                    fields,
+                    format!(
+                        "unnamed fields of `{ident}` are not supported, \
+                         except a single field of an enum variant"
+                    ),
+                ))
+            }
+            Fields::Unit => Vec::new(),
+        };
+        Ok(Shape { name, fields })
+    }
+
+    /// Field patterns binding each field to a variable of the same name
+    fn bindings(&self) -> Vec<TokenStream> {
+        let mut bindings: Vec<TokenStream> = self
+            .fields
+            .iter()
+            .filter(|field| !matches!(field.mapping, Mapping::Skip))
+            .map(|field| {
+                let ident = &field.ident;
+                quote!(#ident)
+            })
+            .collect();
+        if bindings.len() < self.fields.len() {
+            bindings.push(quote!(..));
+        }
+        bindings
+    }
+
+    /// Build the tag from the fields bound by [`Shape::bindings`].
+    fn expand_to_tag(&self) -> TokenStream {
+        let name = &self.name;
+        let fields = self.fields.iter().map(|field| {
+            let ident = &field.ident;
+            match &field.mapping {
+                Mapping::Attribute(key) => quote! {
+                    if let ::std::option::Option::Some(value) =
+                        ::jsonml::convert::ToAttributeValue::to_attribute_value(#ident)
+                    {
+                        attributes.insert(::std::string::String::from(#key), value);
+                    }
+                },
+                Mapping::Child => match child_kind(&field.ty) {
+                    ChildKind::Required => quote! {
+                        element_list.push(::jsonml::ToJsonML::to_jsonml(#ident));
+                    },
+                    ChildKind::Optional | ChildKind::Repeated => quote! {
+                        element_list.extend(
+                            ::std::iter::IntoIterator::into_iter(#ident)
+                                .map(::jsonml::ToJsonML::to_jsonml),
+                        );
+                    },
+                },
+                Mapping::Text => quote! {
+                    let text = ::std::string::ToString::to_string(#ident);
+                    if !text.is_empty() {
+                        element_list.push(::jsonml::Element::String(text));
+                    }
+                },
+                Mapping::Skip => quote!(),
+            }
+        });
+        quote! {
+            #[allow(unused_mut)]
+            let mut attributes = ::jsonml::Attributes::new();
+            #[allow(unused_mut)]
+            let mut element_list = ::std::vec::Vec::new();
+            #(#fields)*
+            ::jsonml::Element::Tag(
+                ::jsonml::Tag::from_parts(#name, attributes, element_list)
+                    .expect("names are checked by the derive"),
+            )
+        }
+    }
+
+    /// Return the value constructed with the path from `tag`.
+    fn expand_from_tag(&self, path: TokenStream) -> TokenStream {
+        let fields = self.fields.iter().map(|field| {
+            let ident = &field.ident;
+            let field_name = ident.to_string();
+            let value = match &field.mapping {
+                Mapping::Attribute(key) => quote!(::jsonml::convert::attribute(tag, #key)?),
+                Mapping::Child => match child_kind(&field.ty) {
+                    ChildKind::Required => quote!(children.child(#field_name)?),
+                    ChildKind::Optional => quote!(children.optional_child()?),
+                    ChildKind::Repeated => quote!(children.children()?),
+                },
+                Mapping::Text => quote!(children.text(#field_name)?),
+                Mapping::Skip => quote!(::std::default::Default::default()),
+            };
+            quote!(#ident: #value)
+        });
+        quote! {
+            #[allow(unused_mut)]
+            let mut children = ::jsonml::convert::Children::new(tag);
+            let value = #path { #(#fields),* };
+            children.finish()?;
+            return ::std::result::Result::Ok(value);
+        }
+    }
+}
+
+/// Parse `#[jsonml(tag = "name")]`, defaulting to the lowercase ident.
+fn parse_tag_name(attributes: &[Attribute], ident: &Ident) -> Result<String, Error> {
+    let mut name = None;
+    for attribute in jsonml_attributes(attributes) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") {
+                let literal: LitStr = meta.value()?.parse()?;
+                let value = literal.value();
+                if let Err(reason) = markup::validate_tag_name(&value) {
+                    return Err(Error::new(
+                        literal.span(),
+                        format!("invalid tag name {value:?}: {reason}"),
+                    ));
+                }
+                name = Some(value);
+                Ok(())
+            } else {
+                Err(meta.error("expected `tag`"))
+            }
+        })?;
+    }
+    match name {
+        Some(name) => Ok(name),
+        None => {
+            let name = ident
+                .to_string()
+                .trim_start_matches("r#")
+                .to_ascii_lowercase();
+            match markup::validate_tag_name(&name) {
+                Ok(()) => Ok(name),
+                Err(reason) => Err(Error::new(
+                    ident.span(),
+                    format!(
+                        "invalid tag name {name:?}: {reason}, \
+                         specify one with `#[jsonml(tag = \"name\")]`"
+                    ),
+                )),
+            }
+        }
+    }
+}
+
+/// Reject `#[jsonml(...)]` where no tag is converted.
+fn reject_tag_name(attributes: &[Attribute]) -> Result<(), Error> {
+    match jsonml_attributes(attributes).next() {
+        Some(attribute) => Err(Error::new_spanned(
+            attribute,
+            "tag name cannot be specified on an enum or a variant converted as its field",
+        )),
+        None => Ok(()),
+    }
+}
+
+fn parse_mapping(attributes: &[Attribute], ident: &Ident) -> Result<Mapping, Error> {
+    let mut mapping = None;
+    for attribute in jsonml_attributes(attributes) {
+        attribute.parse_nested_meta(|meta| {
+            if mapping.is_some() {
+                return Err(meta.error("field is mapped more than once"));
+            }
+            mapping = Some(if meta.path.is_ident("attribute") {
+                let key = if meta.input.peek(syn::Token![=]) {
+                    let literal: LitStr = meta.value()?.parse()?;
+                    let key = literal.value();
+                    if let Err(reason) = markup::validate_attribute_name(&key) {
+                        return Err(Error::new(
+                            literal.span(),
+                            format!("invalid attribute name {key:?}: {reason}"),
+                        ));
+                    }
+                    key
+                } else {
+                    ident.to_string().trim_start_matches("r#").to_string()
+                };
+                Mapping::Attribute(key)
+            } else if meta.path.is_ident("child") {
+                Mapping::Child
+            } else if meta.path.is_ident("text") {
+                Mapping::Text
+            } else if meta.path.is_ident("skip") {
+                Mapping::Skip
+            } else {
+                return Err(meta.error("expected `attribute`, `child`, `text` or `skip`"));
+            });
+            Ok(())
+        })?;
+    }
+    Ok(mapping.unwrap_or_else(|| {
+        Mapping::Attribute(ident.to_string().trim_start_matches("r#").to_string())
+    }))
+}
+
+fn jsonml_attributes(attributes: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attributes
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("jsonml"))
+}
+
+/// Classify the type by its name, as the derive cannot resolve types.
+fn child_kind(ty: &Type) -> ChildKind {
+    let segment = match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+        _ => None,
+    };
+    let has_one_type_argument = |arguments: &PathArguments| match arguments {
+        PathArguments::AngleBracketed(arguments) => {
+            arguments.args.len() == 1
+                && matches!(arguments.args.first(), Some(GenericArgument::Type(_)))
+        }
+        _ => false,
+    };
+    match segment {
+        Some(segment) if has_one_type_argument(&segment.arguments) => {
+            if segment.ident == "Option" {
+                ChildKind::Optional
+            } else if segment.ident == "Vec" {
+                ChildKind::Repeated
+            } else {
+                ChildKind::Required
+            }
+        }
+        _ => ChildKind::Required,
+    }
+}
@@ -0,0 +1,144 @@
+//! Procedural macros for the [`jsonml`](https://docs.rs/jsonml) crate
+//!
+//! The macros are re-exported from `jsonml` with its `macros` feature.
+
+use std::ops::Range;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput, LitStr};
+
+mod derive;
+mod markup;
+
+/// Build an `Element` from HTML markup at compile time
+///
+/// The markup is a string literal with a single root element, which may be surrounded by
+/// whitespace and comments.
+/// Tag and attribute names are kept as written and checked with the same rules as rendering an
+/// `Element` to HTML.
+/// Attribute values and text are decoded from character references, except in raw text elements
+/// such as `script`.
+/// Attributes without a value become empty strings, as with `html::parse_fragment`.
+/// Void elements have no end tag, and any element may be closed with `/>`.
+///
+/// ```
+/// use jsonml::Element;
+/// use jsonml_macros::html;
+///
+/// let element = html!(r#"<p class="greeting">Hello, <em>world</em> &amp; all!</p>"#);
+/// assert_eq!(
+///     element,
+///     serde_json::from_str::<Element>(
+///         r#"["p", {"class": "greeting"}, "Hello, ", ["em", "world"], " & all!"]"#
+///     )
+///     .unwrap()
+/// );
+/// ```
+///
+/// Invalid markup fails to compile:
+///
+/// ```compile_fail
+/// let element = jsonml_macros::html!("<my_tag></my_tag>");
+/// ```
+///
+/// The error message gives the line and column of the problem in the markup.
+/// Only nightly compilers can point into the literal; on stable the error spans the whole
+/// literal.
+#[proc_macro]
+pub fn html(input: TokenStream) -> TokenStream {
+    let literal = parse_macro_input!(input as LitStr);
+    let markup = literal.value();
+    match markup::parse(&markup) {
+        Ok(tag) => expand_tag(&tag).into(),
+        Err(error) => {
+            let (line, column) = line_column(&markup, error.range.start);
+            syn::Error::new(
+                error_span(&literal, error.range),
+                format!("{} at line {line}, column {column}", error.kind),
+            )
+            .to_compile_error()
+            .into()
+        }
+    }
+}
+
+/// Derive `ToJsonML`, converting into a tag
+///
+/// See the `jsonml::convert` module for the mapping and the `#[jsonml(...)]` attributes.
+#[proc_macro_derive(ToJsonML, attributes(jsonml))]
+pub fn derive_to_jsonml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive::derive_to_jsonml(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `FromJsonML`, converting from a tag
+///
+/// See the `jsonml::convert` module for the mapping and the `#[jsonml(...)]` attributes.
+#[proc_macro_derive(FromJsonML, attributes(jsonml))]
+pub fn derive_from_jsonml(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive::derive_from_jsonml(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_tag(tag: &markup::Tag) -> TokenStream2 {
+    let name = &tag.name;
+    let attributes = if tag.attributes.is_empty() {
+        quote!(::jsonml::Attributes::new())
+    } else {
+        let attributes = tag.attributes.iter().map(|(key, value)| {
+            quote! {
+                (
+                    ::std::string::String::from(#key),
+                    ::jsonml::AttributeValue::String(::std::string::String::from(#value)),
+                )
+            }
+        });
+        quote!(::jsonml::Attributes::from([#(#attributes),*]))
+    };
+    let element_list = tag.children.iter().map(|child| match child {
+        markup::Node::Tag(tag) => expand_tag(tag),
+        markup::Node::Text(text) => {
+            quote!(::jsonml::Element::String(::std::string::String::from(#text)))
+        }
+    });
+    quote! {
+        ::jsonml::Element::Tag(
+            ::jsonml::Tag::from_parts(#name, #attributes, ::std::vec![#(#element_list),*])
+                .expect("names are checked by html!"),
+        )
+    }
+}
+
+/// 1-based line and column in characters of the byte offset
+fn line_column(markup: &str, offset: usize) -> (usize, usize) {
+    let before = &markup[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Span of the range in the literal value, or of the whole literal where the compiler cannot
+/// point into it, as `Literal::subspan` always does on stable.
+fn error_span(literal: &LitStr, range: Range<usize>) -> Span {
+    let token = literal.token();
+    let source = token.to_string();
+    // Offsets in the value match those in the source only without escapes.
+    let prefix = match source.strip_prefix('r') {
+        Some(raw) => 1 + raw.find('"').unwrap_or(0) + 1,
+        None if !source.contains('\\') => 1,
+        None => return literal.span(),
+    };
+    // Point at a character even for an empty range, such as the closing quote at the end.
+    let end = range.end.max(range.start + 1);
+    token
+        .subspan(prefix + range.start..prefix + end)
+        .unwrap_or_else(|| literal.span())
+}
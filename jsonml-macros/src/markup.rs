@@ -0,0 +1,605 @@
+//! Strict parser of the HTML markup accepted by `html!`
+//!
+//! Unlike the HTML tokenizer, which recovers from any input, this parser rejects markup that
+//! would not round-trip through [`Element`](https://docs.rs/jsonml/latest/jsonml/enum.Element.html)
+//! rendering, so that mistakes in templates are reported at compile time.
+
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use html_escape::decode_html_entities;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Node {
+    Tag(Tag),
+    Text(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Tag {
+    pub(crate) name: String,
+    /// Attributes in source order
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<Node>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Error {
+    pub(crate) kind: ErrorKind,
+    /// Byte range of the offending markup
+    pub(crate) range: Range<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    ExpectedTagName,
+    ExpectedAttributeValue,
+    InvalidTagName {
+        name: String,
+        reason: InvalidNameReason,
+    },
+    InvalidAttributeName {
+        name: String,
+        reason: InvalidNameReason,
+    },
+    DuplicateAttribute(String),
+    UnclosedElement(String),
+    UnclosedComment,
+    MismatchedEndTag {
+        expected: String,
+        found: String,
+    },
+    EndTagOfVoidElement(String),
+    UnexpectedEndTag(String),
+    TextOutsideRoot,
+    MissingRoot,
+    MultipleRoots,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of markup"),
+            ErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            ErrorKind::ExpectedTagName => {
+                write!(
+                    f,
+                    "expected tag name after '<', use \"&lt;\" for a literal '<'"
+                )
+            }
+            ErrorKind::ExpectedAttributeValue => write!(f, "expected attribute value after '='"),
+            ErrorKind::InvalidTagName { name, reason } => {
+                write!(f, "invalid tag name {name:?}: {reason}")
+            }
+            ErrorKind::InvalidAttributeName { name, reason } => {
+                write!(f, "invalid attribute name {name:?}: {reason}")
+            }
+            ErrorKind::DuplicateAttribute(name) => write!(f, "duplicate attribute {name:?}"),
+            ErrorKind::UnclosedElement(name) => write!(f, "element {name:?} is not closed"),
+            ErrorKind::UnclosedComment => write!(f, "comment is not closed"),
+            ErrorKind::MismatchedEndTag { expected, found } => {
+                write!(
+                    f,
+                    "end tag </{found}> does not match start tag <{expected}>"
+                )
+            }
+            ErrorKind::EndTagOfVoidElement(name) => {
+                write!(f, "void element {name:?} cannot have an end tag")
+            }
+            ErrorKind::UnexpectedEndTag(name) => write!(f, "unexpected end tag </{name}>"),
+            ErrorKind::TextOutsideRoot => write!(f, "text outside the root element"),
+            ErrorKind::MissingRoot => write!(f, "expected a root element"),
+            ErrorKind::MultipleRoots => write!(f, "expected a single root element"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum InvalidNameReason {
+    Empty,
+    InvalidChar(char),
+}
+
+impl Display for InvalidNameReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidNameReason::Empty => write!(f, "name is empty"),
+            InvalidNameReason::InvalidChar(c) => write!(f, "{c:?} is not allowed"),
+        }
+    }
+}
+
+/// Parse markup consisting of a single root element, optionally surrounded by whitespace and
+/// comments.
+pub(crate) fn parse(input: &str) -> Result<Tag, Error> {
+    let mut parser = Parser { input, position: 0 };
+    let mut root = None;
+    loop {
+        parser.skip_whitespace();
+        let start = parser.position;
+        if parser.rest().is_empty() {
+            break;
+        } else if parser.rest().starts_with("<!--") {
+            parser.skip_comment()?;
+        } else if parser.rest().starts_with("</") {
+            let name = parser.parse_end_tag()?;
+            return Err(parser.error(ErrorKind::UnexpectedEndTag(name), start));
+        } else if parser.rest().starts_with('<') {
+            let tag = parser.parse_element()?;
+            if root.is_some() {
+                return Err(parser.error(ErrorKind::MultipleRoots, start));
+            }
+            root = Some(tag);
+        } else {
+            parser.take_while(|c| c != '<');
+            return Err(parser.error(ErrorKind::TextOutsideRoot, start));
+        }
+    }
+    root.ok_or(Error {
+        kind: ErrorKind::MissingRoot,
+        range: 0..input.len(),
+    })
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !predicate(c)).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(is_html_whitespace);
+    }
+
+    fn error(&self, kind: ErrorKind, start: usize) -> Error {
+        Error {
+            kind,
+            range: start..self.position,
+        }
+    }
+
+    /// Error on the character at the current position
+    fn unexpected(&self) -> Error {
+        match self.peek() {
+            Some(c) => Error {
+                kind: ErrorKind::UnexpectedChar(c),
+                range: self.position..self.position + c.len_utf8(),
+            },
+            None => self.error(ErrorKind::UnexpectedEof, self.position),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), Error> {
+        let start = self.position;
+        match self.rest()[4..].find("-->") {
+            Some(end) => {
+                self.position += 4 + end + 3;
+                Ok(())
+            }
+            None => {
+                self.position += 4;
+                Err(self.error(ErrorKind::UnclosedComment, start))
+            }
+        }
+    }
+
+    fn parse_tag_name(&mut self) -> Result<String, Error> {
+        let start = self.position;
+        let name = self.take_while(|c| !is_html_whitespace(c) && c != '/' && c != '>');
+        if name.is_empty() {
+            return Err(match self.peek() {
+                Some(c) => Error {
+                    kind: ErrorKind::ExpectedTagName,
+                    range: start..start + c.len_utf8(),
+                },
+                None => self.error(ErrorKind::UnexpectedEof, start),
+            });
+        }
+        if let Err(reason) = validate_tag_name(name) {
+            return Err(self.error(
+                ErrorKind::InvalidTagName {
+                    name: name.to_string(),
+                    reason,
+                },
+                start,
+            ));
+        }
+        Ok(name.to_string())
+    }
+
+    /// Parse an end tag, returning its name.
+    fn parse_end_tag(&mut self) -> Result<String, Error> {
+        self.position += 2;
+        let name = self.parse_tag_name()?;
+        self.skip_whitespace();
+        self.expect('>')?;
+        Ok(name)
+    }
+
+    fn parse_element(&mut self) -> Result<Tag, Error> {
+        let start = self.position;
+        self.position += 1;
+        let name = self.parse_tag_name()?;
+        let name_end = self.position;
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        let self_closing = loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None => return Err(self.unexpected()),
+                Some('>') => {
+                    self.position += 1;
+                    break false;
+                }
+                Some('/') => {
+                    self.position += 1;
+                    self.expect('>')?;
+                    break true;
+                }
+                Some(_) => {
+                    let attribute_start = self.position;
+                    let (key, value) = self.parse_attribute()?;
+                    if attributes.iter().any(|(other, _)| *other == key) {
+                        self.position = attribute_start + key.len();
+                        return Err(self.error(ErrorKind::DuplicateAttribute(key), attribute_start));
+                    }
+                    attributes.push((key, value));
+                }
+            }
+        };
+        let mut tag = Tag {
+            name,
+            attributes,
+            children: Vec::new(),
+        };
+        if self_closing || is_void_element(&tag.name) {
+            return Ok(tag);
+        }
+
+        let unclosed = |tag: &Tag| Error {
+            kind: ErrorKind::UnclosedElement(tag.name.clone()),
+            range: start..name_end,
+        };
+        if is_raw_text_element(&tag.name) {
+            let len = find_end_tag(self.rest(), &tag.name).ok_or_else(|| unclosed(&tag))?;
+            if len > 0 {
+                tag.children
+                    .push(Node::Text(self.rest()[..len].to_string()));
+            }
+            self.position += len;
+            self.parse_end_tag()?;
+            return Ok(tag);
+        }
+
+        loop {
+            let child_start = self.position;
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(unclosed(&tag));
+            } else if rest.starts_with("<!--") {
+                self.skip_comment()?;
+            } else if rest.starts_with("</") {
+                let found = self.parse_end_tag()?;
+                if found.eq_ignore_ascii_case(&tag.name) {
+                    return Ok(tag);
+                }
+                let kind = if is_void_element(&found) {
+                    ErrorKind::EndTagOfVoidElement(found)
+                } else {
+                    ErrorKind::MismatchedEndTag {
+                        expected: tag.name,
+                        found,
+                    }
+                };
+                return Err(self.error(kind, child_start));
+            } else if rest.starts_with('<') {
+                let child = self.parse_element()?;
+                tag.children.push(Node::Tag(child));
+            } else {
+                let text = decode_html_entities(self.take_while(|c| c != '<'));
+                // Text split by a comment is merged.
+                match tag.children.last_mut() {
+                    Some(Node::Text(previous)) => previous.push_str(&text),
+                    _ => tag.children.push(Node::Text(text.into_owned())),
+                }
+            }
+        }
+    }
+
+    fn parse_attribute(&mut self) -> Result<(String, String), Error> {
+        let start = self.position;
+        let key = self.take_while(|c| !is_html_whitespace(c) && !matches!(c, '=' | '>' | '/'));
+        let invalid = |reason| ErrorKind::InvalidAttributeName {
+            name: key.to_string(),
+            reason,
+        };
+        if let Err(reason) = validate_attribute_name(key) {
+            if key.is_empty() {
+                // Only reachable at '='.
+                self.position += 1;
+            }
+            return Err(self.error(invalid(reason), start));
+        }
+
+        self.skip_whitespace();
+        if self.peek() != Some('=') {
+            return Ok((key.to_string(), String::new()));
+        }
+        self.position += 1;
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some(quote @ ('"' | '\'')) => {
+                self.position += 1;
+                let value = self.take_while(|c| c != quote);
+                self.expect(quote)?;
+                value
+            }
+            Some('>') => {
+                return Err(Error {
+                    kind: ErrorKind::ExpectedAttributeValue,
+                    range: self.position..self.position + 1,
+                })
+            }
+            Some(_) => self.take_while(|c| !is_html_whitespace(c) && c != '>'),
+            None => return Err(self.unexpected()),
+        };
+        Ok((key.to_string(), decode_html_entities(value).into_owned()))
+    }
+}
+
+/// Check the tag name with the same rule as the rendering of `Tag`.
+///
+/// This crate cannot depend on `jsonml`, so the rules are repeated here;
+/// `test_names_agree_with_jsonml` checks them against `jsonml` for every character.
+// https://html.spec.whatwg.org/multipage/syntax.html#syntax-tag-name
+pub(crate) fn validate_tag_name(name: &str) -> Result<(), InvalidNameReason> {
+    if name.is_empty() {
+        return Err(InvalidNameReason::Empty);
+    }
+    match name.chars().find(|c| !c.is_ascii_alphanumeric()) {
+        Some(c) => Err(InvalidNameReason::InvalidChar(c)),
+        None => Ok(()),
+    }
+}
+
+/// Check the attribute name with the same rule as the rendering of `Tag`, as for
+/// [`validate_tag_name`].
+// https://html.spec.whatwg.org/multipage/syntax.html#attributes-2
+pub(crate) fn validate_attribute_name(name: &str) -> Result<(), InvalidNameReason> {
+    if name.is_empty() {
+        return Err(InvalidNameReason::Empty);
+    }
+    match name.chars().find(|&c| !is_attribute_name_char(c)) {
+        Some(c) => Err(InvalidNameReason::InvalidChar(c)),
+        None => Ok(()),
+    }
+}
+
+/// Find the end tag of a raw text element, case-insensitively.
+fn find_end_tag(text: &str, name: &str) -> Option<usize> {
+    text.match_indices("</").map(|(i, _)| i).find(|&i| {
+        text.as_bytes()[i + 2..]
+            .get(..name.len())
+            .map_or(false, |candidate| {
+                candidate.eq_ignore_ascii_case(name.as_bytes())
+            })
+    })
+}
+
+fn is_attribute_name_char(c: char) -> bool {
+    !(c.is_control()
+        || matches!(c, ' ' | '"' | '\'' | '>' | '/' | '=' | '\u{FDD0}'..='\u{FDEF}')
+        // Noncharacters at the end of each plane
+        || c as u32 & 0xFFFE == 0xFFFE)
+}
+
+// https://infra.spec.whatwg.org/#ascii-whitespace
+fn is_html_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+fn is_void_element(name: &str) -> bool {
+    [
+        "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+        "keygen", "link", "meta", "param", "source", "track", "wbr",
+    ]
+    .iter()
+    .any(|void| name.eq_ignore_ascii_case(void))
+}
+
+fn is_raw_text_element(name: &str) -> bool {
+    [
+        "style",
+        "script",
+        "xmp",
+        "iframe",
+        "noembed",
+        "noframes",
+        "plaintext",
+    ]
+    .iter()
+    .any(|raw| name.eq_ignore_ascii_case(raw))
+}
+
+#[cfg(test)]
+fn tag(name: &str, attributes: &[(&str, &str)], children: Vec<Node>) -> Tag {
+    Tag {
+        name: name.to_string(),
+        attributes: attributes
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        children,
+    }
+}
+
+#[cfg(test)]
+fn text(text: &str) -> Node {
+    Node::Text(text.to_string())
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        parse(
+            r#"
+            <!-- list -->
+            <ul class="a &amp; b" hidden data-x = 'y' id=z>
+              <li>A &lt; <!-- c -->B</li><br><img src="i.png"/>
+              <script>if (a < b) {}</script>
+            </ul>
+            "#
+        ),
+        Ok(tag(
+            "ul",
+            &[
+                ("class", "a & b"),
+                ("hidden", ""),
+                ("data-x", "y"),
+                ("id", "z")
+            ],
+            vec![
+                text("\n              "),
+                Node::Tag(tag("li", &[], vec![text("A < B")])),
+                Node::Tag(tag("br", &[], vec![])),
+                Node::Tag(tag("img", &[("src", "i.png")], vec![])),
+                text("\n              "),
+                Node::Tag(tag("script", &[], vec![text("if (a < b) {}")])),
+                text("\n            "),
+            ]
+        ))
+    );
+    assert_eq!(parse("<p/>"), Ok(tag("p", &[], vec![])));
+    assert_eq!(parse("<P>a</p >"), Ok(tag("P", &[], vec![text("a")])));
+}
+
+#[test]
+fn test_names_agree_with_jsonml() {
+    let reason = |error: jsonml::InvalidNameError| {
+        let reason = match error {
+            jsonml::InvalidNameError::InvalidTagName { reason, .. }
+            | jsonml::InvalidNameError::InvalidAttributeName { reason, .. } => reason,
+            error => panic!("unexpected error {error}"),
+        };
+        match reason {
+            jsonml::html::InvalidNameReason::Empty => InvalidNameReason::Empty,
+            jsonml::html::InvalidNameReason::InvalidChar(c) => InvalidNameReason::InvalidChar(c),
+            reason => panic!("unexpected reason {reason}"),
+        }
+    };
+    let mut tag = jsonml::Tag::new("p").unwrap();
+    for name in ["".to_string()]
+        .into_iter()
+        .chain((0..=0x10FFFF).filter_map(char::from_u32).map(String::from))
+    {
+        assert_eq!(
+            validate_tag_name(&name),
+            jsonml::Tag::new(name.as_str()).map(drop).map_err(reason),
+            "{name:?}"
+        );
+        assert_eq!(
+            validate_attribute_name(&name),
+            tag.set_attribute(name.as_str(), true)
+                .map(drop)
+                .map_err(reason),
+            "{name:?}"
+        );
+        tag.remove_attribute(&name);
+    }
+}
+
+#[test]
+fn test_parse_error() {
+    let error = |input: &str, kind, range| {
+        assert_eq!(parse(input), Err(Error { kind, range }), "{input}");
+    };
+    error(
+        "<my_tag></my_tag>",
+        ErrorKind::InvalidTagName {
+            name: "my_tag".to_string(),
+            reason: InvalidNameReason::InvalidChar('_'),
+        },
+        1..7,
+    );
+    error(
+        "<p a\"b=c></p>",
+        ErrorKind::InvalidAttributeName {
+            name: "a\"b".to_string(),
+            reason: InvalidNameReason::InvalidChar('"'),
+        },
+        3..6,
+    );
+    error(
+        "<p =c></p>",
+        ErrorKind::InvalidAttributeName {
+            name: "".to_string(),
+            reason: InvalidNameReason::Empty,
+        },
+        3..4,
+    );
+    error(
+        "<p a=1 a=2></p>",
+        ErrorKind::DuplicateAttribute("a".to_string()),
+        7..8,
+    );
+    error("<p>1 < 2</p>", ErrorKind::ExpectedTagName, 6..7);
+    error("<p a=>", ErrorKind::ExpectedAttributeValue, 5..6);
+    error("<p a='b>", ErrorKind::UnexpectedEof, 8..8);
+    error(
+        "<div><p></div>",
+        ErrorKind::MismatchedEndTag {
+            expected: "p".to_string(),
+            found: "div".to_string(),
+        },
+        8..14,
+    );
+    error(
+        "<p><br></br></p>",
+        ErrorKind::EndTagOfVoidElement("br".to_string()),
+        7..12,
+    );
+    error(
+        "<div><p>",
+        ErrorKind::UnclosedElement("p".to_string()),
+        5..7,
+    );
+    error(
+        "<script>a",
+        ErrorKind::UnclosedElement("script".to_string()),
+        0..7,
+    );
+    error("<p><!-- a</p>", ErrorKind::UnclosedComment, 3..7);
+    error(
+        "<p></p></p>",
+        ErrorKind::UnexpectedEndTag("p".to_string()),
+        7..11,
+    );
+    error("a<p></p>", ErrorKind::TextOutsideRoot, 0..1);
+    error("<p></p><p></p>", ErrorKind::MultipleRoots, 7..14);
+    error(" <!-- a --> ", ErrorKind::MissingRoot, 0..12);
+}
@@ -0,0 +1,179 @@
+use jsonml::convert::{FromJsonMLError, FromJsonMLErrorKind};
+use jsonml::{Element, FromJsonML as _, ToJsonML as _};
+use jsonml_macros::{FromJsonML, ToJsonML};
+
+#[derive(Debug, PartialEq, ToJsonML, FromJsonML)]
+struct Product {
+    #[jsonml(attribute = "data-sku")]
+    sku: String,
+    r#type: Option<String>,
+    #[jsonml(attribute)]
+    featured: bool,
+    #[jsonml(child)]
+    title: Title,
+    #[jsonml(child)]
+    price: Option<Price>,
+    #[jsonml(child)]
+    tags: Vec<Badge>,
+    #[jsonml(skip)]
+    cached: Option<String>,
+}
+
+#[derive(Debug, PartialEq, ToJsonML, FromJsonML)]
+#[jsonml(tag = "h2")]
+struct Title {
+    #[jsonml(text)]
+    text: String,
+}
+
+#[derive(Debug, PartialEq, ToJsonML, FromJsonML)]
+#[jsonml(tag = "data")]
+struct Price {
+    currency: String,
+    #[jsonml(text)]
+    amount: u32,
+}
+
+#[derive(Debug, PartialEq, ToJsonML, FromJsonML)]
+enum Badge {
+    #[jsonml(tag = "strong")]
+    New,
+    #[jsonml(tag = "em")]
+    Sale {
+        #[jsonml(text)]
+        percent: u8,
+    },
+    Custom(Title),
+}
+
+#[derive(Debug, PartialEq, FromJsonML)]
+enum Mark {
+    #[jsonml(tag = "strong")]
+    Strong,
+    #[jsonml(tag = "b")]
+    Bold,
+}
+
+#[derive(Debug, PartialEq, FromJsonML)]
+enum Heading {
+    Title(Title),
+}
+
+fn element(json: &str) -> Element {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn test_derive() {
+    let product = Product {
+        sku: "A-1".to_string(),
+        r#type: None,
+        featured: true,
+        title: Title {
+            text: "Lamp".to_string(),
+        },
+        price: Some(Price {
+            currency: "EUR".to_string(),
+            amount: 30,
+        }),
+        tags: vec![Badge::New, Badge::Sale { percent: 10 }],
+        cached: None,
+    };
+    assert_eq!(
+        product.to_jsonml(),
+        element(
+            r#"["product", {"data-sku": "A-1", "featured": true},
+                ["h2", "Lamp"],
+                ["data", {"currency": "EUR"}, "30"],
+                ["strong"],
+                ["em", "10"]]"#
+        )
+    );
+    assert_eq!(Product::from_jsonml(&product.to_jsonml()), Ok(product));
+
+    // As parsed from HTML
+    assert_eq!(
+        Product::from_jsonml(&element(
+            r#"["product", {"data-sku": "B", "type": "x", "featured": "", "class": "c"},
+                "\n  ", ["h2", "Desk"], "\n  ", ["h2", "Old"], "\n"]"#
+        )),
+        Ok(Product {
+            sku: "B".to_string(),
+            r#type: Some("x".to_string()),
+            featured: true,
+            title: Title {
+                text: "Desk".to_string(),
+            },
+            price: None,
+            tags: vec![Badge::Custom(Title {
+                text: "Old".to_string()
+            })],
+            cached: None,
+        })
+    );
+}
+
+#[test]
+fn test_derive_error() {
+    let error = |json: &str| Product::from_jsonml(&element(json)).unwrap_err();
+    assert_eq!(
+        error(r#"["item"]"#),
+        FromJsonMLError::new(FromJsonMLErrorKind::UnexpectedTag {
+            expected: vec!["product".to_string()],
+            found: "item".to_string()
+        })
+    );
+    assert_eq!(
+        error(r#"["product", ["h2"]]"#).kind(),
+        &FromJsonMLErrorKind::MissingAttribute {
+            name: "data-sku".to_string()
+        }
+    );
+    assert_eq!(
+        error(r#"["product", {"data-sku": "A", "featured": "yes"}]"#).kind(),
+        &FromJsonMLErrorKind::InvalidAttribute {
+            name: "featured".to_string(),
+            value: jsonml::AttributeValue::String("yes".to_string())
+        }
+    );
+    assert_eq!(
+        error(r#"["product", {"data-sku": "A"}]"#).kind(),
+        &FromJsonMLErrorKind::MissingChild {
+            field: "title".to_string()
+        }
+    );
+    let invalid_price = error(
+        r#"["product", {"data-sku": "A"}, ["h2", "T"], ["data", {"currency": "EUR"}, "free"]]"#,
+    );
+    assert_eq!(invalid_price.path(), &[1, 0]);
+    assert_eq!(
+        invalid_price.to_string(),
+        "invalid text \"free\" for field \"amount\" at path [1, 0]"
+    );
+    let unexpected = error(r#"["product", {"data-sku": "A"}, ["h2", "T"], ["p"]]"#);
+    assert_eq!(unexpected.kind(), &FromJsonMLErrorKind::UnexpectedChild);
+    assert_eq!(unexpected.path(), &[1]);
+    assert_eq!(
+        Badge::from_jsonml(&element(r#""new""#))
+            .unwrap_err()
+            .to_string(),
+        "no variant of Badge matches at path []"
+    );
+    assert_eq!(
+        Mark::from_jsonml(&element(r#"["em"]"#))
+            .unwrap_err()
+            .to_string(),
+        "expected <strong> or <b>, found <em> at path []"
+    );
+    assert_eq!(
+        Heading::from_jsonml(&element(r#""x""#)).unwrap_err().kind(),
+        &FromJsonMLErrorKind::NoMatchingVariant {
+            type_name: "Heading".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_derive_compile_error() {
+    trybuild::TestCases::new().compile_fail("tests/ui/derive/*.rs");
+}
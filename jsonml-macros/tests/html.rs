@@ -0,0 +1,32 @@
+use jsonml::Element;
+use jsonml_macros::html;
+
+#[test]
+fn test_html() {
+    let element = html!(
+        r#"
+        <form action="/search" method=get>
+          <input name="q" required>
+          <!-- submit -->
+          <button type="submit" disabled>Go &rarr;</button>
+        </form>
+        "#
+    );
+    assert_eq!(
+        element,
+        serde_json::from_str::<Element>(
+            r#"["form", {"action": "/search", "method": "get"},
+                "\n          ",
+                ["input", {"name": "q", "required": ""}],
+                "\n          \n          ",
+                ["button", {"type": "submit", "disabled": ""}, "Go →"],
+                "\n        "]"#
+        )
+        .unwrap()
+    );
+}
+
+#[test]
+fn test_html_compile_error() {
+    trybuild::TestCases::new().compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,47 @@
+use jsonml_macros::{FromJsonML, ToJsonML};
+
+#[derive(ToJsonML)]
+#[jsonml(tag = "my-item")]
+struct Item;
+
+#[derive(ToJsonML)]
+struct Entity {
+    #[jsonml(attribute = "a b")]
+    name: String,
+}
+
+#[derive(FromJsonML)]
+struct Pair(String, String);
+
+#[derive(ToJsonML)]
+struct Entry {
+    #[jsonml(child, text)]
+    value: String,
+}
+
+#[derive(FromJsonML)]
+struct Node_;
+
+#[derive(FromJsonML)]
+#[jsonml(tag = "node")]
+struct Tree {
+    #[jsonml(children)]
+    children: Vec<Tree>,
+}
+
+#[derive(ToJsonML)]
+enum Choice {
+    #[jsonml(tag = "a")]
+    A(String),
+}
+
+#[derive(FromJsonML)]
+enum Emphasis {
+    #[jsonml(tag = "em")]
+    Em,
+    I,
+    #[jsonml(tag = "em")]
+    Italic,
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use jsonml_macros::html;
+
+fn main() {
+    html!("<my_tag></my_tag>");
+    html!(r#"<p a"b=c></p>"#);
+    html!("<div><p>text</div>");
+    html!("<p></p><p></p>");
+    html!(
+        "<ul>
+           <li>1 < 2</li>
+         </ul>"
+    );
+    html!(concat!("<p>", "</p>"));
+}
@@ -0,0 +1,830 @@
+//! Compact binary encoding of elements with lazy decoding
+//!
+//! Tag names and attribute names are stored once in a string table and referred to by index,
+//! so repeated names such as `div` and `class` take a byte or two each.
+//! A [`Document`] reads the string table only, and tags are decoded as they are visited, with
+//! constant-time access to any child.
+//!
+//! ```
+//! use jsonml::{binary, Element};
+//!
+//! let element: Element =
+//!     serde_json::from_str(r#"["ul", ["li", {"class": "item"}, "a"], ["li", {"class": "item"}, "b"]]"#)
+//!         .unwrap();
+//! let bytes = binary::to_vec(&element);
+//!
+//! let document = binary::Document::new(&bytes).unwrap();
+//! if let binary::Node::Tag(tag) = document.root().unwrap() {
+//!     assert_eq!(tag.name(), "ul");
+//!     assert_eq!(tag.len(), 2);
+//!     let second = tag.child(1).unwrap().unwrap().to_element().unwrap();
+//!     assert_eq!(second, serde_json::from_str(r#"["li", {"class": "item"}, "b"]"#).unwrap());
+//! }
+//!
+//! assert_eq!(binary::from_slice(&bytes).unwrap(), element);
+//! ```
+//!
+//! # Format
+//!
+//! Lengths, counts and indices are unsigned LEB128 varints.
+//!
+//! * The magic `JML` and the version, 1
+//! * The string table: the count, and the length and UTF-8 bytes of each string
+//! * The root element
+//!
+//! An element starts with its type:
+//!
+//! * 0, text: the length and UTF-8 bytes
+//! * 1, tag: the index of the name, the count of attributes, each attribute, the count of
+//!   children, and the children.
+//!   With more than one child, the count is followed by the width of offsets, which is 1, 2, 4
+//!   or 8, and the offset of each child but the first as a little-endian integer of the width from
+//!   the start of the first child.
+//!
+//! An attribute is the index of its name followed by the type of the value:
+//!
+//! * 0, string: the length and UTF-8 bytes
+//! * 1, number: a 4-byte little-endian `f32`
+//! * 2, boolean: a byte, 0 or 1
+//! * 3, null
+
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{self, Display},
+};
+
+use indexmap::IndexSet;
+
+use crate::{
+    borrowed::{AttributeValueRef, TagRef},
+    AttributeValue, Element, ElementRef,
+};
+
+const MAGIC: &[u8] = b"JML";
+const VERSION: u8 = 1;
+
+const TEXT: u8 = 0;
+const TAG: u8 = 1;
+
+const STRING: u8 = 0;
+const NUMBER: u8 = 1;
+const BOOL: u8 = 2;
+const NULL: u8 = 3;
+
+/// Levels of nested tags which decoding an element entirely accepts
+const MAX_DEPTH: usize = 512;
+
+/// Error on decoding with the offset of the offending byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    kind: DecodeErrorKind,
+    offset: usize,
+}
+
+impl DecodeError {
+    pub fn kind(&self) -> &DecodeErrorKind {
+        &self.kind
+    }
+
+    /// Offset in bytes from the start of the input
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.kind, self.offset)
+    }
+}
+
+impl Error for DecodeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeErrorKind {
+    InvalidMagic,
+    UnsupportedVersion(u8),
+    UnexpectedEof,
+    InvalidVarint,
+    InvalidUtf8,
+    InvalidStringIndex(usize),
+    InvalidElementType(u8),
+    InvalidValueType(u8),
+    InvalidBool(u8),
+    InvalidOffsetWidth(u8),
+    InvalidOffset,
+    DepthLimitExceeded,
+    TrailingBytes,
+}
+
+impl Display for DecodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeErrorKind::InvalidMagic => write!(f, "invalid magic"),
+            DecodeErrorKind::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {version}")
+            }
+            DecodeErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            DecodeErrorKind::InvalidVarint => write!(f, "invalid varint"),
+            DecodeErrorKind::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            DecodeErrorKind::InvalidStringIndex(index) => {
+                write!(f, "string index {index} out of the string table")
+            }
+            DecodeErrorKind::InvalidElementType(t) => write!(f, "invalid element type {t}"),
+            DecodeErrorKind::InvalidValueType(t) => write!(f, "invalid attribute value type {t}"),
+            DecodeErrorKind::InvalidBool(b) => write!(f, "invalid boolean {b}"),
+            DecodeErrorKind::InvalidOffsetWidth(width) => {
+                write!(f, "invalid width of offsets {width}")
+            }
+            DecodeErrorKind::InvalidOffset => {
+                write!(f, "offset of a child not at the end of the previous child")
+            }
+            DecodeErrorKind::DepthLimitExceeded => {
+                write!(f, "elements nested more than {MAX_DEPTH} levels deep")
+            }
+            DecodeErrorKind::TrailingBytes => write!(f, "trailing bytes after the root element"),
+        }
+    }
+}
+
+/// Encode an element
+///
+/// Encoding recurses into children, so the depth of the element is limited by the stack as for
+/// serialization with serde.
+/// Elements nested more than 512 levels deep are encoded but cannot be decoded entirely.
+pub fn to_vec(element: &Element) -> Vec<u8> {
+    let mut strings = IndexSet::new();
+    for tag in element.tags() {
+        strings.insert(tag.name());
+        strings.extend(tag.attributes().keys().map(String::as_str));
+    }
+
+    let mut encoder = Encoder {
+        output: MAGIC.to_vec(),
+        strings,
+        lens: vec![],
+        next: 0,
+    };
+    encoder.output.push(VERSION);
+    write_varint(&mut encoder.output, encoder.strings.len());
+    for string in &encoder.strings {
+        write_str(&mut encoder.output, string);
+    }
+    encoder.measure(element);
+    encoder.write_element(element);
+    encoder.output
+}
+
+/// Decode an element entirely
+///
+/// Fails on tags nested more than 512 levels deep, and on children which do not follow one
+/// another without gaps, so that malformed input is neither decoded twice nor exhausts the stack.
+pub fn from_slice(bytes: &[u8]) -> Result<Element, DecodeError> {
+    let document = Document::new(bytes)?;
+    let (element, end) = document.decode(document.root()?, 0)?;
+    if end != bytes.len() {
+        return Err(DecodeError {
+            kind: DecodeErrorKind::TrailingBytes,
+            offset: end,
+        });
+    }
+    Ok(element.into_owned())
+}
+
+struct Encoder<'e> {
+    output: Vec<u8>,
+    strings: IndexSet<&'e str>,
+    /// Encoded length and number of elements of each element in pre-order, to write offsets
+    /// before the children
+    lens: Vec<(usize, usize)>,
+    /// Pre-order index of the next element to write
+    next: usize,
+}
+
+impl<'e> Encoder<'e> {
+    /// Compute the encoded lengths of the element and its descendants.
+    fn measure(&mut self, element: &Element) -> usize {
+        let index = self.lens.len();
+        self.lens.push((0, 0));
+        let len = match element {
+            Element::Tag(tag) => {
+                let mut len = 1 + varint_len(string_index(&self.strings, tag.name()));
+                len += varint_len(tag.attributes().len());
+                for (name, value) in tag.attributes() {
+                    len += varint_len(string_index(&self.strings, name)) + 1;
+                    len += match value {
+                        AttributeValue::String(s) => varint_len(s.len()) + s.len(),
+                        AttributeValue::Number(_) => 4,
+                        AttributeValue::Bool(_) => 1,
+                        AttributeValue::Null => 0,
+                    };
+                }
+                let child_lens: Vec<_> = tag
+                    .element_list()
+                    .iter()
+                    .map(|child| self.measure(child))
+                    .collect();
+                let width = offset_width(&child_lens);
+                len += varint_len(child_lens.len());
+                if child_lens.len() > 1 {
+                    len += 1 + width * (child_lens.len() - 1);
+                }
+                len + child_lens.iter().sum::<usize>()
+            }
+            Element::String(s) => 1 + varint_len(s.len()) + s.len(),
+        };
+        self.lens[index] = (len, self.lens.len() - index);
+        len
+    }
+
+    fn write_element(&mut self, element: &Element) {
+        self.next += 1;
+        let output = &mut self.output;
+        match element {
+            Element::Tag(tag) => {
+                output.push(TAG);
+                write_varint(output, string_index(&self.strings, tag.name()));
+                write_varint(output, tag.attributes().len());
+                for (name, value) in tag.attributes() {
+                    write_varint(output, string_index(&self.strings, name));
+                    match value {
+                        AttributeValue::String(s) => {
+                            output.push(STRING);
+                            write_str(output, s);
+                        }
+                        AttributeValue::Number(n) => {
+                            output.push(NUMBER);
+                            output.extend_from_slice(&n.to_le_bytes());
+                        }
+                        AttributeValue::Bool(b) => {
+                            output.push(BOOL);
+                            output.push(u8::from(*b));
+                        }
+                        AttributeValue::Null => output.push(NULL),
+                    }
+                }
+
+                let element_list = tag.element_list();
+                write_varint(output, element_list.len());
+                let mut child_lens = Vec::with_capacity(element_list.len());
+                let mut index = self.next;
+                for _ in element_list {
+                    let (len, count) = self.lens[index];
+                    child_lens.push(len);
+                    index += count;
+                }
+                if child_lens.len() > 1 {
+                    let width = offset_width(&child_lens);
+                    output.push(width as u8);
+                    let mut offset = 0;
+                    for len in &child_lens[..child_lens.len() - 1] {
+                        offset += len;
+                        output.extend_from_slice(&(offset as u64).to_le_bytes()[..width]);
+                    }
+                }
+                for child in element_list {
+                    self.write_element(child);
+                }
+            }
+            Element::String(s) => {
+                output.push(TEXT);
+                write_str(output, s);
+            }
+        }
+    }
+}
+
+/// Width in bytes of the offsets of children with the lengths
+fn offset_width(child_lens: &[usize]) -> usize {
+    let last = child_lens.iter().rev().skip(1).sum::<usize>();
+    if last <= 0xff {
+        1
+    } else if last <= 0xffff {
+        2
+    } else if u32::try_from(last).is_ok() {
+        4
+    } else {
+        8
+    }
+}
+
+fn varint_len(mut n: usize) -> usize {
+    let mut len = 1;
+    while n >= 0x80 {
+        n >>= 7;
+        len += 1;
+    }
+    len
+}
+
+fn string_index(strings: &IndexSet<&str>, string: &str) -> usize {
+    strings
+        .get_index_of(string)
+        .expect("names are collected into the string table")
+}
+
+fn write_str(output: &mut Vec<u8>, s: &str) {
+    write_varint(output, s.len());
+    output.extend_from_slice(s.as_bytes());
+}
+
+fn write_varint(output: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        output.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    output.push(n as u8);
+}
+
+/// Encoded element, decoded lazily
+///
+/// Creating a document reads the header and the string table only.
+#[derive(Debug, Clone)]
+pub struct Document<'a> {
+    bytes: &'a [u8],
+    strings: Vec<&'a str>,
+    root: usize,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(MAGIC.len()).ok() != Some(MAGIC) {
+            return Err(DecodeError {
+                kind: DecodeErrorKind::InvalidMagic,
+                offset: 0,
+            });
+        }
+        let version = reader.byte()?;
+        if version != VERSION {
+            return Err(reader.error_before(DecodeErrorKind::UnsupportedVersion(version), 1));
+        }
+        let count = reader.varint()?;
+        // Each string takes a byte at least, which bounds the count of an invalid input.
+        let mut strings = Vec::with_capacity(count.min(bytes.len()));
+        for _ in 0..count {
+            strings.push(reader.str()?);
+        }
+        Ok(Document {
+            bytes,
+            strings,
+            root: reader.position,
+        })
+    }
+
+    pub fn root(&self) -> Result<Node<'_>, DecodeError> {
+        self.node(self.root)
+    }
+
+    fn node(&self, position: usize) -> Result<Node<'_>, DecodeError> {
+        let mut reader = Reader {
+            bytes: self.bytes,
+            position,
+        };
+        match reader.byte()? {
+            TEXT => Ok(Node::String(reader.str()?)),
+            TAG => {
+                let name = self.string(&mut reader)?;
+                let attribute_count = reader.varint()?;
+                let attributes = reader.position;
+                for _ in 0..attribute_count {
+                    self.attribute(&mut reader)?;
+                }
+                let child_count = reader.varint()?;
+                let offset_width = if child_count > 1 {
+                    match reader.byte()? {
+                        width @ (1 | 2 | 4 | 8) => usize::from(width),
+                        width => {
+                            return Err(
+                                reader.error_before(DecodeErrorKind::InvalidOffsetWidth(width), 1)
+                            )
+                        }
+                    }
+                } else {
+                    0
+                };
+                let offsets = reader.position;
+                reader.take(offset_width.saturating_mul(child_count.saturating_sub(1)))?;
+                Ok(Node::Tag(TagNode {
+                    document: self,
+                    name,
+                    attribute_count,
+                    attributes,
+                    child_count,
+                    offset_width,
+                    offsets,
+                    children: reader.position,
+                }))
+            }
+            t => Err(reader.error_before(DecodeErrorKind::InvalidElementType(t), 1)),
+        }
+    }
+
+    /// Decode the node with the position of its end.
+    ///
+    /// Each child has to start where the previous one ends, so that no bytes are decoded twice.
+    fn decode<'d>(
+        &'d self,
+        node: Node<'d>,
+        depth: usize,
+    ) -> Result<(ElementRef<'d>, usize), DecodeError> {
+        match node {
+            Node::Tag(tag) => {
+                if depth >= MAX_DEPTH {
+                    return Err(DecodeError {
+                        kind: DecodeErrorKind::DepthLimitExceeded,
+                        offset: tag.children,
+                    });
+                }
+                let mut element_list = Vec::with_capacity(tag.child_count);
+                let mut end = tag.children;
+                for index in 0..tag.child_count {
+                    if tag.child_position(index)? != end {
+                        return Err(DecodeError {
+                            kind: DecodeErrorKind::InvalidOffset,
+                            offset: tag.offsets + tag.offset_width * index.saturating_sub(1),
+                        });
+                    }
+                    let (element, child_end) = self.decode(self.node(end)?, depth + 1)?;
+                    element_list.push(element);
+                    end = child_end;
+                }
+                let tag = TagRef {
+                    name: Cow::Borrowed(tag.name),
+                    attributes: tag
+                        .attributes()
+                        .map(|(name, value)| (Cow::Borrowed(name), value))
+                        .collect(),
+                    element_list,
+                };
+                Ok((ElementRef::Tag(tag), end))
+            }
+            Node::String(s) => {
+                // The string is a part of the bytes.
+                let end = s.as_ptr() as usize - self.bytes.as_ptr() as usize + s.len();
+                Ok((ElementRef::String(Cow::Borrowed(s)), end))
+            }
+        }
+    }
+
+    fn string(&self, reader: &mut Reader<'a>) -> Result<&'a str, DecodeError> {
+        let start = reader.position;
+        let index = reader.varint()?;
+        self.strings.get(index).copied().ok_or(DecodeError {
+            kind: DecodeErrorKind::InvalidStringIndex(index),
+            offset: start,
+        })
+    }
+
+    fn attribute(
+        &self,
+        reader: &mut Reader<'a>,
+    ) -> Result<(&'a str, AttributeValueRef<'a>), DecodeError> {
+        let name = self.string(reader)?;
+        let value = match reader.byte()? {
+            STRING => AttributeValueRef::String(Cow::Borrowed(reader.str()?)),
+            NUMBER => {
+                let mut bytes = [0; 4];
+                bytes.copy_from_slice(reader.take(4)?);
+                AttributeValueRef::Number(f32::from_le_bytes(bytes))
+            }
+            BOOL => match reader.byte()? {
+                0 => AttributeValueRef::Bool(false),
+                1 => AttributeValueRef::Bool(true),
+                b => return Err(reader.error_before(DecodeErrorKind::InvalidBool(b), 1)),
+            },
+            NULL => AttributeValueRef::Null,
+            t => return Err(reader.error_before(DecodeErrorKind::InvalidValueType(t), 1)),
+        };
+        Ok((name, value))
+    }
+}
+
+/// Element in a [`Document`], borrowing from the encoded bytes
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Tag(TagNode<'a>),
+    String(&'a str),
+}
+
+impl<'a> Node<'a> {
+    /// Decode the element and its descendants, borrowing the strings.
+    ///
+    /// The descendants are checked as by [`from_slice`].
+    pub fn to_element_ref(&self) -> Result<ElementRef<'a>, DecodeError> {
+        match self {
+            Node::Tag(tag) => tag.document.decode(*self, 0).map(|(element, _)| element),
+            Node::String(s) => Ok(ElementRef::String(Cow::Borrowed(s))),
+        }
+    }
+
+    /// Decode the element and its descendants.
+    pub fn to_element(&self) -> Result<Element, DecodeError> {
+        self.to_element_ref().map(ElementRef::into_owned)
+    }
+}
+
+/// Tag in a [`Document`], whose children are decoded on access
+#[derive(Debug, Clone, Copy)]
+pub struct TagNode<'a> {
+    document: &'a Document<'a>,
+    name: &'a str,
+    attribute_count: usize,
+    /// Position of the first attribute
+    attributes: usize,
+    child_count: usize,
+    offset_width: usize,
+    /// Position of the offsets of the children but the first
+    offsets: usize,
+    /// Position of the first child
+    children: usize,
+}
+
+impl<'a> TagNode<'a> {
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn attributes(&self) -> impl Iterator<Item = (&'a str, AttributeValueRef<'a>)> + 'a {
+        let document = self.document;
+        let mut reader = Reader {
+            bytes: document.bytes,
+            position: self.attributes,
+        };
+        (0..self.attribute_count).map(move |_| {
+            document
+                .attribute(&mut reader)
+                .expect("attributes are checked when the tag is read")
+        })
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<AttributeValueRef<'a>> {
+        self.attributes()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// Number of children
+    pub fn len(&self) -> usize {
+        self.child_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.child_count == 0
+    }
+
+    /// Decode the child at the index, or `None` if it is out of range.
+    pub fn child(&self, index: usize) -> Option<Result<Node<'a>, DecodeError>> {
+        if index >= self.child_count {
+            return None;
+        }
+        Some(
+            self.child_position(index)
+                .and_then(|position| self.document.node(position)),
+        )
+    }
+
+    /// Position of the child at the index, which is in range.
+    fn child_position(&self, index: usize) -> Result<usize, DecodeError> {
+        let mut offset = 0;
+        if index > 0 {
+            let position = self.offsets + self.offset_width * (index - 1);
+            let mut bytes = [0; 8];
+            bytes[..self.offset_width]
+                .copy_from_slice(&self.document.bytes[position..position + self.offset_width]);
+            // An offset beyond the address space is beyond the bytes too.
+            offset = usize::try_from(u64::from_le_bytes(bytes)).unwrap_or(usize::MAX);
+        }
+        match self.children.checked_add(offset) {
+            Some(position) if position < self.document.bytes.len() => Ok(position),
+            _ => Err(DecodeError {
+                kind: DecodeErrorKind::UnexpectedEof,
+                offset: self.document.bytes.len(),
+            }),
+        }
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = Result<Node<'a>, DecodeError>> + 'a {
+        let tag = *self;
+        (0..self.child_count).filter_map(move |index| tag.child(index))
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Error on the bytes just read
+    fn error_before(&self, kind: DecodeErrorKind, len: usize) -> DecodeError {
+        DecodeError {
+            kind,
+            offset: self.position - len,
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes = self
+            .position
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or(DecodeError {
+                kind: DecodeErrorKind::UnexpectedEof,
+                offset: self.bytes.len(),
+            })?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn varint(&mut self) -> Result<usize, DecodeError> {
+        let start = self.position;
+        let mut n: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            let bits = usize::from(byte & 0x7f);
+            if shift >= usize::BITS || (bits << shift) >> shift != bits {
+                return Err(DecodeError {
+                    kind: DecodeErrorKind::InvalidVarint,
+                    offset: start,
+                });
+            }
+            n |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+
+    fn str(&mut self) -> Result<&'a str, DecodeError> {
+        let len = self.varint()?;
+        let start = self.position;
+        std::str::from_utf8(self.take(len)?).map_err(|_| DecodeError {
+            kind: DecodeErrorKind::InvalidUtf8,
+            offset: start,
+        })
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    let element: Element = serde_json::from_str(
+        r#"["div", {"id": "a", "class": "b", "n": 1.5, "hidden": true, "open": false, "x": null},
+            ["div", {"class": "c"}, "text", ["br"]], "", "日本語", ["div", {"class": "c"}]]"#,
+    )
+    .unwrap();
+    let bytes = to_vec(&element);
+    assert_eq!(from_slice(&bytes), Ok(element.clone()));
+
+    let document = Document::new(&bytes).unwrap();
+    assert_eq!(
+        document.strings,
+        ["div", "id", "class", "n", "hidden", "open", "x", "br"]
+    );
+    assert_eq!(
+        document.root().unwrap().to_element_ref().unwrap(),
+        ElementRef::from(&element)
+    );
+
+    let text = Element::String("a".to_string());
+    assert_eq!(from_slice(&to_vec(&text)), Ok(text));
+
+    // Offsets take 4 bytes after a child longer than 64 KiB.
+    let long = Element::Tag(
+        crate::Tag::from_parts(
+            "p",
+            crate::Attributes::default(),
+            vec!["a".repeat(0x10000).into(), "b".into()],
+        )
+        .unwrap(),
+    );
+    let bytes = to_vec(&long);
+    assert_eq!(from_slice(&bytes), Ok(long));
+    match Document::new(&bytes).unwrap().root().unwrap() {
+        Node::Tag(tag) => {
+            assert_eq!(tag.offset_width, 4);
+            assert!(matches!(tag.child(1), Some(Ok(Node::String("b")))));
+        }
+        Node::String(s) => panic!("not a tag: {s:?}"),
+    }
+
+    // Offsets take 8 bytes after children of 4 GiB or more.
+    #[cfg(target_pointer_width = "64")]
+    assert_eq!(offset_width(&[1 << 32, 1]), 8);
+    let bytes = [
+        &b"JML\x01\x01\x01a\x01\x00\x00\x02\x08"[..],
+        &3u64.to_le_bytes(),
+        b"\x00\x01x\x00\x01y",
+    ]
+    .concat();
+    assert_eq!(
+        from_slice(&bytes),
+        Ok(crate::test_helpers::tag(
+            "a",
+            &[],
+            vec!["x".into(), "y".into()]
+        ))
+    );
+}
+
+#[test]
+fn test_random_access() {
+    let children: Vec<_> = (0..300)
+        .map(|i| format!(r#"["li", {{"value": {i}}}, "{i}"]"#))
+        .collect();
+    let json = format!(r#"["ol", {{"start": "0"}}, {}]"#, children.join(","));
+    let element: Element = serde_json::from_str(&json).unwrap();
+    let bytes = to_vec(&element);
+    let json = serde_json::to_vec(&element).unwrap();
+    assert!(bytes.len() < json.len() * 2 / 3);
+
+    let document = Document::new(&bytes).unwrap();
+    let tag = match document.root().unwrap() {
+        Node::Tag(tag) => tag,
+        Node::String(s) => panic!("not a tag: {s:?}"),
+    };
+    assert_eq!(tag.name(), "ol");
+    assert_eq!(
+        tag.attribute("start"),
+        Some(AttributeValueRef::String(Cow::Borrowed("0")))
+    );
+    assert_eq!(tag.len(), 300);
+    assert!(tag.child(300).is_none());
+    match tag.child(256).unwrap().unwrap() {
+        Node::Tag(li) => {
+            assert_eq!(li.attribute("value"), Some(AttributeValueRef::Number(256.)));
+            assert!(matches!(li.child(0), Some(Ok(Node::String("256")))));
+        }
+        Node::String(s) => panic!("not a tag: {s:?}"),
+    }
+    assert_eq!(tag.children().count(), 300);
+}
+
+#[test]
+fn test_decode_errors() {
+    let error = |bytes: &[u8]| from_slice(bytes).unwrap_err();
+    assert_eq!(error(b"XML\x01").kind(), &DecodeErrorKind::InvalidMagic);
+    assert_eq!(
+        error(b"JML\x02"),
+        DecodeError {
+            kind: DecodeErrorKind::UnsupportedVersion(2),
+            offset: 3
+        }
+    );
+    assert_eq!(error(b"JML\x01").kind(), &DecodeErrorKind::UnexpectedEof);
+    assert_eq!(
+        error(b"JML\x01\x00\x01\x00").kind(),
+        &DecodeErrorKind::InvalidStringIndex(0)
+    );
+    assert_eq!(
+        error(b"JML\x01\x00\x02").kind(),
+        &DecodeErrorKind::InvalidElementType(2)
+    );
+    assert_eq!(
+        error(b"JML\x01\x00\x00\x01\xff").kind(),
+        &DecodeErrorKind::InvalidUtf8
+    );
+    assert_eq!(
+        error(b"JML\x01\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\x01").kind(),
+        &DecodeErrorKind::InvalidVarint
+    );
+    assert_eq!(
+        error(b"JML\x01\x01\x01a\x01\x00\x01\x00\x04").kind(),
+        &DecodeErrorKind::InvalidValueType(4)
+    );
+    assert_eq!(
+        error(b"JML\x01\x00\x00\x00\x00").kind(),
+        &DecodeErrorKind::TrailingBytes
+    );
+    assert_eq!(
+        error(b"JML\x01\x01\x01a\x01\x00\x00\x02\x03").kind(),
+        &DecodeErrorKind::InvalidOffsetWidth(3)
+    );
+    // The second child is the first one again.
+    assert_eq!(
+        error(b"JML\x01\x01\x01a\x01\x00\x00\x02\x01\x00\x00\x00"),
+        DecodeError {
+            kind: DecodeErrorKind::InvalidOffset,
+            offset: 12
+        }
+    );
+    let mut nested = b"JML\x01\x01\x01a".to_vec();
+    for _ in 0..1_000_000 {
+        nested.extend_from_slice(b"\x01\x00\x00\x01");
+    }
+    nested.extend_from_slice(b"\x00\x00");
+    assert_eq!(error(&nested).kind(), &DecodeErrorKind::DepthLimitExceeded);
+    // The offset of the second child points past the end.
+    assert_eq!(
+        error(b"JML\x01\x01\x01a\x01\x00\x00\x02\x01\x05\x00\x00").kind(),
+        &DecodeErrorKind::UnexpectedEof
+    );
+}
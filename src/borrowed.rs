@@ -0,0 +1,585 @@
+//! Elements borrowing their strings from the input
+//!
+//! [`ElementRef`] deserializes like [`Element`], but tag names, attribute names and values and
+//! text borrow from the input where the format allows it.
+//! Strings which have to be decoded, such as JSON strings with escapes, are owned instead.
+//!
+//! ```
+//! use std::borrow::Cow;
+//!
+//! use jsonml::{Element, ElementRef};
+//!
+//! let json = r#"["p", {"class": "greeting"}, "Hello, \"world\""]"#;
+//! let element: ElementRef = serde_json::from_str(json).unwrap();
+//! if let ElementRef::Tag(tag) = &element {
+//!     assert!(matches!(tag.name(), Cow::Borrowed("p")));
+//!     assert!(matches!(&tag.element_list()[0], ElementRef::String(Cow::Owned(_))));
+//! }
+//!
+//! let owned: Element = element.into_owned();
+//! assert_eq!(owned, serde_json::from_str(json).unwrap());
+//! ```
+
+use std::{borrow::Cow, fmt};
+
+use indexmap::IndexMap;
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeSeq, SerializeTupleVariant},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{AttributeValue, Attributes, Element, Tag};
+
+/// Element borrowing from the input, as deserialized
+#[derive(Debug, PartialEq, Clone)]
+pub enum ElementRef<'a> {
+    Tag(TagRef<'a>),
+    String(Cow<'a, str>),
+}
+
+/// Attributes of a borrowed tag, in the order as written
+pub type AttributesRef<'a> = IndexMap<Cow<'a, str>, AttributeValueRef<'a>>;
+
+/// Tag borrowing from the input
+///
+/// As with deserialized [`Tag`]s, names are kept as found without validation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TagRef<'a> {
+    pub(crate) name: Cow<'a, str>,
+    pub(crate) attributes: AttributesRef<'a>,
+    pub(crate) element_list: Vec<ElementRef<'a>>,
+}
+
+/// Attribute value borrowing from the input
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum AttributeValueRef<'a> {
+    String(Cow<'a, str>),
+    Number(f32),
+    Bool(bool),
+
+    #[default]
+    Null,
+}
+
+impl<'a> ElementRef<'a> {
+    /// Convert into an owned element, moving the strings which are already owned.
+    pub fn into_owned(self) -> Element {
+        match self {
+            ElementRef::Tag(tag) => Element::Tag(tag.into_owned()),
+            ElementRef::String(s) => Element::String(s.into_owned()),
+        }
+    }
+}
+
+impl<'a> TagRef<'a> {
+    /// Take the name, the attributes and the children.
+    pub fn into_parts(self) -> (Cow<'a, str>, AttributesRef<'a>, Vec<ElementRef<'a>>) {
+        (self.name, self.attributes, self.element_list)
+    }
+
+    pub fn name(&self) -> &Cow<'a, str> {
+        &self.name
+    }
+
+    pub fn attributes(&self) -> &AttributesRef<'a> {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeValueRef<'a>> {
+        self.attributes.get(name)
+    }
+
+    pub fn element_list(&self) -> &[ElementRef<'a>] {
+        &self.element_list
+    }
+
+    /// Convert into an owned tag, moving the strings which are already owned.
+    #[allow(deprecated)]
+    pub fn into_owned(self) -> Tag {
+        Tag {
+            name: self.name.into_owned(),
+            attributes: self
+                .attributes
+                .into_iter()
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect(),
+            element_list: self
+                .element_list
+                .into_iter()
+                .map(ElementRef::into_owned)
+                .collect(),
+        }
+    }
+}
+
+impl<'a> AttributeValueRef<'a> {
+    /// Convert into an owned value, moving the string if it is already owned.
+    pub fn into_owned(self) -> AttributeValue {
+        match self {
+            AttributeValueRef::String(s) => AttributeValue::String(s.into_owned()),
+            AttributeValueRef::Number(n) => AttributeValue::Number(n),
+            AttributeValueRef::Bool(b) => AttributeValue::Bool(b),
+            AttributeValueRef::Null => AttributeValue::Null,
+        }
+    }
+}
+
+impl<'a> From<ElementRef<'a>> for Element {
+    fn from(element: ElementRef<'a>) -> Self {
+        element.into_owned()
+    }
+}
+
+impl<'a> From<&'a Element> for ElementRef<'a> {
+    fn from(element: &'a Element) -> Self {
+        match element {
+            Element::Tag(tag) => ElementRef::Tag(tag.into()),
+            Element::String(s) => ElementRef::String(Cow::Borrowed(s)),
+        }
+    }
+}
+
+impl<'a> From<&'a Tag> for TagRef<'a> {
+    #[allow(deprecated)]
+    fn from(tag: &'a Tag) -> Self {
+        TagRef {
+            name: Cow::Borrowed(&tag.name),
+            attributes: borrow_attributes(&tag.attributes),
+            element_list: tag.element_list.iter().map(ElementRef::from).collect(),
+        }
+    }
+}
+
+impl<'a> From<&'a AttributeValue> for AttributeValueRef<'a> {
+    fn from(value: &'a AttributeValue) -> Self {
+        match value {
+            AttributeValue::String(s) => AttributeValueRef::String(Cow::Borrowed(s)),
+            AttributeValue::Number(n) => AttributeValueRef::Number(*n),
+            AttributeValue::Bool(b) => AttributeValueRef::Bool(*b),
+            AttributeValue::Null => AttributeValueRef::Null,
+        }
+    }
+}
+
+fn borrow_attributes(attributes: &Attributes) -> AttributesRef<'_> {
+    attributes
+        .iter()
+        .map(|(key, value)| (Cow::Borrowed(key.as_str()), value.into()))
+        .collect()
+}
+
+impl<'a> Serialize for ElementRef<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !serializer.is_human_readable() {
+            return match self {
+                ElementRef::Tag(TagRef {
+                    name,
+                    attributes,
+                    element_list,
+                }) => {
+                    let mut variant = serializer.serialize_tuple_variant("Element", 0, "Tag", 3)?;
+                    variant.serialize_field(name)?;
+                    variant.serialize_field(attributes)?;
+                    variant.serialize_field(element_list)?;
+                    variant.end()
+                }
+                ElementRef::String(string) => {
+                    serializer.serialize_newtype_variant("Element", 1, "String", string)
+                }
+            };
+        }
+        match self {
+            ElementRef::Tag(TagRef {
+                name,
+                attributes,
+                element_list,
+            }) => {
+                let mut seq = serializer.serialize_seq(Some(
+                    1 + usize::from(!attributes.is_empty()) + element_list.len(),
+                ))?;
+                seq.serialize_element(name)?;
+                if !attributes.is_empty() {
+                    seq.serialize_element(attributes)?;
+                }
+                for element in element_list {
+                    seq.serialize_element(element)?;
+                }
+                seq.end()
+            }
+            ElementRef::String(string) => serializer.serialize_str(string),
+        }
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for ElementRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            return Ok(match CompactElementRef::deserialize(deserializer)? {
+                CompactElementRef::Tag(
+                    CowStr(name),
+                    DeserializeAttributes(attributes),
+                    element_list,
+                ) => ElementRef::Tag(TagRef {
+                    name,
+                    attributes,
+                    element_list,
+                }),
+                CompactElementRef::String(CowStr(string)) => ElementRef::String(string),
+            });
+        }
+        deserializer.deserialize_any(ElementRefVisitor)
+    }
+}
+
+/// Representation of [`ElementRef`] for formats which are not human-readable, as for [`Element`]
+#[derive(Deserialize)]
+#[serde(rename = "Element")]
+enum CompactElementRef<'a> {
+    Tag(
+        #[serde(borrow)] CowStr<'a>,
+        #[serde(borrow)] DeserializeAttributes<'a>,
+        #[serde(borrow)] Vec<ElementRef<'a>>,
+    ),
+    String(#[serde(borrow)] CowStr<'a>),
+}
+
+impl<'a> Serialize for AttributeValueRef<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            match self {
+                AttributeValueRef::String(s) => serializer.serialize_str(s),
+                AttributeValueRef::Number(n) => serializer.serialize_f32(*n),
+                AttributeValueRef::Bool(b) => serializer.serialize_bool(*b),
+                AttributeValueRef::Null => serializer.serialize_unit(),
+            }
+        } else {
+            match self {
+                AttributeValueRef::String(s) => {
+                    serializer.serialize_newtype_variant("AttributeValue", 0, "String", s)
+                }
+                AttributeValueRef::Number(n) => {
+                    serializer.serialize_newtype_variant("AttributeValue", 1, "Number", n)
+                }
+                AttributeValueRef::Bool(b) => {
+                    serializer.serialize_newtype_variant("AttributeValue", 2, "Bool", b)
+                }
+                AttributeValueRef::Null => {
+                    serializer.serialize_unit_variant("AttributeValue", 3, "Null")
+                }
+            }
+        }
+    }
+}
+
+/// Representation of [`AttributeValueRef`] for formats which are not human-readable
+#[derive(Deserialize)]
+#[serde(rename = "AttributeValue")]
+enum CompactAttributeValueRef<'a> {
+    String(#[serde(borrow)] CowStr<'a>),
+    Number(f32),
+    Bool(bool),
+    Null,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for AttributeValueRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            return Ok(match CompactAttributeValueRef::deserialize(deserializer)? {
+                CompactAttributeValueRef::String(CowStr(s)) => AttributeValueRef::String(s),
+                CompactAttributeValueRef::Number(n) => AttributeValueRef::Number(n),
+                CompactAttributeValueRef::Bool(b) => AttributeValueRef::Bool(b),
+                CompactAttributeValueRef::Null => AttributeValueRef::Null,
+            });
+        }
+        deserializer.deserialize_any(AttributeValueRefVisitor)
+    }
+}
+
+struct AttributeValueRefVisitor;
+
+impl<'de> Visitor<'de> for AttributeValueRefVisitor {
+    type Value = AttributeValueRef<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("attribute value, which is string, number, boolean or null")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::String(Cow::Borrowed(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::String(Cow::Owned(v.to_string())))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::String(Cow::Owned(v)))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::Number(v as f32))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::Number(v as f32))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::Number(v as f32))
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::Bool(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValueRef::Null)
+    }
+}
+
+/// Attributes with borrowed names, which `IndexMap` deserializes as owned
+struct DeserializeAttributes<'a>(AttributesRef<'a>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for DeserializeAttributes<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_map(AttributesVisitor)
+            .map(DeserializeAttributes)
+    }
+}
+
+/// String borrowed from the input where possible
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct CowStr<'a>(#[serde(borrow)] Cow<'a, str>);
+
+/// Second item of a tag, told apart by its type without buffering it
+enum AttributesOrElement<'a> {
+    Attributes(AttributesRef<'a>),
+    Element(ElementRef<'a>),
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for AttributesOrElement<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(AttributesOrElementVisitor)
+    }
+}
+
+struct AttributesOrElementVisitor;
+
+impl<'de> Visitor<'de> for AttributesOrElementVisitor {
+    type Value = AttributesOrElement<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("JsonML attributes or element")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        ElementRefVisitor
+            .visit_borrowed_str(v)
+            .map(AttributesOrElement::Element)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        ElementRefVisitor
+            .visit_str(v)
+            .map(AttributesOrElement::Element)
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        ElementRefVisitor
+            .visit_string(v)
+            .map(AttributesOrElement::Element)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        ElementRefVisitor
+            .visit_seq(seq)
+            .map(AttributesOrElement::Element)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        AttributesVisitor
+            .visit_map(map)
+            .map(AttributesOrElement::Attributes)
+    }
+}
+
+struct AttributesVisitor;
+
+impl<'de> Visitor<'de> for AttributesVisitor {
+    type Value = AttributesRef<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("JsonML attributes")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut attributes = AttributesRef::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((CowStr(key), value)) = map.next_entry()? {
+            attributes.insert(key, value);
+        }
+        Ok(attributes)
+    }
+}
+
+struct ElementRefVisitor;
+
+impl<'de> Visitor<'de> for ElementRefVisitor {
+    type Value = ElementRef<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("JsonML element, which is tag or string")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(ElementRef::String(Cow::Borrowed(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(ElementRef::String(Cow::Owned(v.to_string())))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(ElementRef::String(Cow::Owned(v)))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        if let Some(CowStr(name)) = seq.next_element()? {
+            let mut attributes = AttributesRef::default();
+            let mut element_list = vec![];
+            if let Some(attributes_or_element) = seq.next_element()? {
+                match attributes_or_element {
+                    AttributesOrElement::Attributes(attrs) => attributes = attrs,
+                    AttributesOrElement::Element(element) => element_list.push(element),
+                }
+            }
+            while let Some(element) = seq.next_element()? {
+                element_list.push(element);
+            }
+            Ok(ElementRef::Tag(TagRef {
+                name,
+                attributes,
+                element_list,
+            }))
+        } else {
+            Err(de::Error::missing_field("name"))
+        }
+    }
+}
+
+#[test]
+fn test_element_ref_borrow() {
+    let json = r#"["a", {"href": "h", "title": "\"t\"", "n": 1}, "b", ["c", "d\n"]]"#;
+    let element: ElementRef = serde_json::from_str(json).unwrap();
+    let tag = match &element {
+        ElementRef::Tag(tag) => tag,
+        ElementRef::String(_) => panic!("not a tag: {element:?}"),
+    };
+    assert!(matches!(tag.name(), Cow::Borrowed("a")));
+    assert!(matches!(
+        tag.attribute("href"),
+        Some(AttributeValueRef::String(Cow::Borrowed("h")))
+    ));
+    assert!(matches!(
+        tag.attribute("title"),
+        Some(AttributeValueRef::String(Cow::Owned(title))) if title == "\"t\""
+    ));
+    assert_eq!(tag.attribute("n"), Some(&AttributeValueRef::Number(1.)));
+    assert!(matches!(
+        &tag.element_list()[0],
+        ElementRef::String(Cow::Borrowed("b"))
+    ));
+    assert!(tag
+        .attributes()
+        .keys()
+        .all(|key| matches!(key, Cow::Borrowed(_))));
+
+    let owned: Element = serde_json::from_str(json).unwrap();
+    assert_eq!(ElementRef::from(&owned), element);
+    assert_eq!(
+        serde_json::to_string(&element).unwrap(),
+        serde_json::to_string(&owned).unwrap()
+    );
+    assert_eq!(element.into_owned(), owned);
+}
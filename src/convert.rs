@@ -0,0 +1,610 @@
+//! Conversion between Rust types and elements
+//!
+//! [`ToJsonML`] and [`FromJsonML`] can be derived with the `macros` feature.
+//! A struct maps to a tag named after the struct in lowercase, or `#[jsonml(tag = "name")]`.
+//! Its fields map as follows, in the order of declaration for children:
+//!
+//! * `#[jsonml(attribute)]`, the default: an attribute named after the field, or
+//!   `#[jsonml(attribute = "name")]`, whose type implements [`ToAttributeValue`] and
+//!   [`FromAttributeValue`]
+//! * `#[jsonml(child)]`: a child element, whose type implements [`ToJsonML`] and [`FromJsonML`];
+//!   `Option<T>` for an optional child and `Vec<T>` for a run of children
+//! * `#[jsonml(text)]`: a text child, whose type implements [`Display`] and [`FromStr`]
+//! * `#[jsonml(skip)]`: not converted, and [`Default`] when converted from an element
+//!
+//! An enum maps to alternative tags, one for each variant.
+//! Unit variants and variants with named fields map like structs, named after the variant, and
+//! a variant with a single unnamed field maps as that field.
+//! Two variants cannot map to the same tag name.
+//!
+//! Converting from an element ignores unknown attributes and whitespace-only text between child
+//! elements.
+//!
+//! ```
+//! # #[cfg(feature = "macros")]
+//! # {
+//! use jsonml::{FromJsonML, ToJsonML};
+//!
+//! #[derive(Debug, PartialEq, ToJsonML, FromJsonML)]
+//! #[jsonml(tag = "article")]
+//! struct Comment {
+//!     #[jsonml(attribute = "data-id")]
+//!     id: u32,
+//!     #[jsonml(child)]
+//!     author: Author,
+//!     #[jsonml(child)]
+//!     paragraphs: Vec<Paragraph>,
+//! }
+//!
+//! #[derive(Debug, PartialEq, ToJsonML, FromJsonML)]
+//! enum Author {
+//!     Anonymous,
+//!     #[jsonml(tag = "address")]
+//!     Named {
+//!         #[jsonml(text)]
+//!         name: String,
+//!     },
+//! }
+//!
+//! #[derive(Debug, PartialEq, ToJsonML, FromJsonML)]
+//! #[jsonml(tag = "p")]
+//! struct Paragraph {
+//!     #[jsonml(text)]
+//!     text: String,
+//! }
+//!
+//! let element: jsonml::Element = serde_json::from_str(
+//!     r#"["article", {"data-id": "7"}, ["address", "Alice"], ["p", "Hello"], ["p", "Bye"]]"#,
+//! )
+//! .unwrap();
+//! let comment = Comment::from_jsonml(&element).unwrap();
+//! assert_eq!(
+//!     comment,
+//!     Comment {
+//!         id: 7,
+//!         author: Author::Named {
+//!             name: "Alice".to_string(),
+//!         },
+//!         paragraphs: vec![
+//!             Paragraph {
+//!                 text: "Hello".to_string(),
+//!             },
+//!             Paragraph {
+//!                 text: "Bye".to_string(),
+//!             },
+//!         ],
+//!     }
+//! );
+//! assert_eq!(Comment::from_jsonml(&comment.to_jsonml()).unwrap(), comment);
+//! # }
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{html, AttributeValue, Element, Tag};
+
+/// Conversion into an element
+pub trait ToJsonML {
+    fn to_jsonml(&self) -> Element;
+}
+
+/// Conversion from an element
+pub trait FromJsonML: Sized {
+    fn from_jsonml(element: &Element) -> Result<Self, FromJsonMLError>;
+
+    /// Whether the element is of the shape converted from, such as a tag with the expected name
+    ///
+    /// Used to find optional and repeated children, and the variant of an enum.
+    /// Accepts any element by default.
+    fn matches(element: &Element) -> bool {
+        let _ = element;
+        true
+    }
+}
+
+impl ToJsonML for Element {
+    fn to_jsonml(&self) -> Element {
+        self.clone()
+    }
+}
+
+impl FromJsonML for Element {
+    fn from_jsonml(element: &Element) -> Result<Self, FromJsonMLError> {
+        Ok(element.clone())
+    }
+}
+
+/// Conversion into an attribute value, where `None` omits the attribute
+pub trait ToAttributeValue {
+    fn to_attribute_value(&self) -> Option<AttributeValue>;
+}
+
+/// Conversion from an attribute value
+pub trait FromAttributeValue: Sized {
+    /// Convert the value, or `None` if it is invalid.
+    fn from_attribute_value(value: &AttributeValue) -> Option<Self>;
+
+    /// Value for an absent attribute, or `None` if the attribute is required as by default
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+impl ToAttributeValue for AttributeValue {
+    fn to_attribute_value(&self) -> Option<AttributeValue> {
+        Some(self.clone())
+    }
+}
+
+impl FromAttributeValue for AttributeValue {
+    fn from_attribute_value(value: &AttributeValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+impl ToAttributeValue for String {
+    fn to_attribute_value(&self) -> Option<AttributeValue> {
+        Some(AttributeValue::String(self.clone()))
+    }
+}
+
+/// Numbers are formatted as in HTML.
+impl FromAttributeValue for String {
+    fn from_attribute_value(value: &AttributeValue) -> Option<Self> {
+        match value {
+            AttributeValue::String(s) => Some(s.clone()),
+            AttributeValue::Number(n) => Some(html::format_number(*n)),
+            _ => None,
+        }
+    }
+}
+
+impl ToAttributeValue for bool {
+    fn to_attribute_value(&self) -> Option<AttributeValue> {
+        Some(AttributeValue::Bool(*self))
+    }
+}
+
+/// As a boolean attribute in HTML, a present attribute is `true` unless it is `false` or
+/// `"false"`, and an absent attribute is `false`.
+impl FromAttributeValue for bool {
+    fn from_attribute_value(value: &AttributeValue) -> Option<Self> {
+        match value {
+            AttributeValue::Bool(b) => Some(*b),
+            AttributeValue::Null => Some(true),
+            AttributeValue::String(s) => match s.as_str() {
+                "" | "true" => Some(true),
+                "false" => Some(false),
+                _ => None,
+            },
+            AttributeValue::Number(_) => None,
+        }
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(false)
+    }
+}
+
+impl<T: ToAttributeValue> ToAttributeValue for Option<T> {
+    fn to_attribute_value(&self) -> Option<AttributeValue> {
+        self.as_ref().and_then(T::to_attribute_value)
+    }
+}
+
+/// An absent attribute is `None`.
+impl<T: FromAttributeValue> FromAttributeValue for Option<T> {
+    fn from_attribute_value(value: &AttributeValue) -> Option<Self> {
+        T::from_attribute_value(value).map(Some)
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+macro_rules! impl_attribute_value_for_integer {
+    ($($integer:ty),*) => {
+        $(
+            /// A number if exactly representable as `f32`, a string otherwise.
+            impl ToAttributeValue for $integer {
+                fn to_attribute_value(&self) -> Option<AttributeValue> {
+                    let number = *self as f32;
+                    Some(if integer_from_f32(number) == Some(*self) {
+                        AttributeValue::Number(number)
+                    } else {
+                        AttributeValue::String(self.to_string())
+                    })
+                }
+            }
+
+            /// Converted from a whole number in range, or parsed from a string.
+            impl FromAttributeValue for $integer {
+                fn from_attribute_value(value: &AttributeValue) -> Option<Self> {
+                    match value {
+                        AttributeValue::Number(n) => integer_from_f32(*n),
+                        AttributeValue::String(s) => s.trim().parse().ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_attribute_value_for_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+/// Convert a whole number without rounding or saturating.
+fn integer_from_f32<T: TryFrom<i128>>(n: f32) -> Option<T> {
+    if !n.is_finite() || n.fract() != 0.0 {
+        return None;
+    }
+    // Whole numbers of `f32` in the range of the integer types are exact in `i128`, and larger
+    // ones saturate out of the range.
+    T::try_from(n as i128).ok()
+}
+
+impl ToAttributeValue for f32 {
+    fn to_attribute_value(&self) -> Option<AttributeValue> {
+        Some(AttributeValue::Number(*self))
+    }
+}
+
+impl FromAttributeValue for f32 {
+    fn from_attribute_value(value: &AttributeValue) -> Option<Self> {
+        match value {
+            AttributeValue::Number(n) => Some(*n),
+            AttributeValue::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// A number if exactly representable as `f32`, a string otherwise.
+impl ToAttributeValue for f64 {
+    fn to_attribute_value(&self) -> Option<AttributeValue> {
+        let number = *self as f32;
+        Some(if f64::from(number) == *self {
+            AttributeValue::Number(number)
+        } else {
+            AttributeValue::String(self.to_string())
+        })
+    }
+}
+
+/// Converted from the shortest decimal of a number, so that `0.1` stays `0.1` rather than
+/// becoming the `f64` nearest to the `f32`, or parsed from a string.
+impl FromAttributeValue for f64 {
+    fn from_attribute_value(value: &AttributeValue) -> Option<Self> {
+        match value {
+            AttributeValue::Number(n) => html::format_number(*n).parse().ok(),
+            AttributeValue::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Error on converting from an element
+#[derive(Debug, Clone, PartialEq)]
+pub struct FromJsonMLError {
+    kind: FromJsonMLErrorKind,
+    path: Vec<usize>,
+}
+
+impl FromJsonMLError {
+    pub fn new(kind: FromJsonMLErrorKind) -> Self {
+        FromJsonMLError {
+            kind,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> &FromJsonMLErrorKind {
+        &self.kind
+    }
+
+    /// Indices of the offending element in the element lists from the converted element
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    fn in_child(mut self, index: usize) -> Self {
+        self.path.insert(0, index);
+        self
+    }
+}
+
+impl Display for FromJsonMLError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at path {:?}", self.kind, self.path)
+    }
+}
+
+impl Error for FromJsonMLError {}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum FromJsonMLErrorKind {
+    /// A string is found where one of the tags is expected.
+    ExpectedTag {
+        expected: Vec<String>,
+    },
+    /// A tag is found where one of the other tags is expected.
+    UnexpectedTag {
+        expected: Vec<String>,
+        found: String,
+    },
+    MissingAttribute {
+        name: String,
+    },
+    InvalidAttribute {
+        name: String,
+        value: AttributeValue,
+    },
+    /// No child is left for the field.
+    MissingChild {
+        field: String,
+    },
+    InvalidText {
+        field: String,
+        text: String,
+    },
+    /// A child is left after all the fields.
+    UnexpectedChild,
+    /// None of the variants of the enum converts the element.
+    NoMatchingVariant {
+        type_name: String,
+    },
+}
+
+impl Display for FromJsonMLErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tags = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| format!("<{name}>"))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        };
+        match self {
+            FromJsonMLErrorKind::ExpectedTag { expected } => {
+                write!(f, "expected {}, found text", tags(expected))
+            }
+            FromJsonMLErrorKind::UnexpectedTag { expected, found } => {
+                write!(f, "expected {}, found <{found}>", tags(expected))
+            }
+            FromJsonMLErrorKind::MissingAttribute { name } => {
+                write!(f, "missing attribute {name:?}")
+            }
+            FromJsonMLErrorKind::InvalidAttribute { name, value } => {
+                write!(f, "invalid value {value:?} of attribute {name:?}")
+            }
+            FromJsonMLErrorKind::MissingChild { field } => {
+                write!(f, "missing child for field {field:?}")
+            }
+            FromJsonMLErrorKind::InvalidText { field, text } => {
+                write!(f, "invalid text {text:?} for field {field:?}")
+            }
+            FromJsonMLErrorKind::UnexpectedChild => write!(f, "unexpected child"),
+            FromJsonMLErrorKind::NoMatchingVariant { type_name } => {
+                write!(f, "no variant of {type_name} matches")
+            }
+        }
+    }
+}
+
+/// Whether the element is a tag with one of the names
+#[allow(deprecated)]
+pub fn is_tag(element: &Element, names: &[&str]) -> bool {
+    matches!(element, Element::Tag(tag) if names.contains(&tag.name.as_str()))
+}
+
+/// Get the tag if it has one of the names.
+#[allow(deprecated)]
+pub fn expect_tag<'a>(element: &'a Element, names: &[&str]) -> Result<&'a Tag, FromJsonMLError> {
+    let expected = || names.iter().map(|name| name.to_string()).collect();
+    match element {
+        Element::Tag(tag) if names.contains(&tag.name.as_str()) => Ok(tag),
+        Element::Tag(tag) => Err(FromJsonMLError::new(FromJsonMLErrorKind::UnexpectedTag {
+            expected: expected(),
+            found: tag.name.clone(),
+        })),
+        Element::String(_) => Err(FromJsonMLError::new(FromJsonMLErrorKind::ExpectedTag {
+            expected: expected(),
+        })),
+    }
+}
+
+/// Convert the attribute of the tag.
+#[allow(deprecated)]
+pub fn attribute<T: FromAttributeValue>(tag: &Tag, name: &str) -> Result<T, FromJsonMLError> {
+    match tag.attributes.get(name) {
+        Some(value) => T::from_attribute_value(value).ok_or_else(|| {
+            FromJsonMLError::new(FromJsonMLErrorKind::InvalidAttribute {
+                name: name.to_string(),
+                value: value.clone(),
+            })
+        }),
+        None => T::from_missing().ok_or_else(|| {
+            FromJsonMLError::new(FromJsonMLErrorKind::MissingAttribute {
+                name: name.to_string(),
+            })
+        }),
+    }
+}
+
+/// Cursor over the children of a tag, converted in order
+#[derive(Debug, Clone)]
+pub struct Children<'a> {
+    element_list: &'a [Element],
+    index: usize,
+}
+
+impl<'a> Children<'a> {
+    #[allow(deprecated)]
+    pub fn new(tag: &'a Tag) -> Self {
+        Children {
+            element_list: &tag.element_list,
+            index: 0,
+        }
+    }
+
+    /// Peek the next element, skipping whitespace-only strings.
+    fn peek_element(&mut self) -> Option<&'a Element> {
+        while let Some(Element::String(s)) = self.element_list.get(self.index) {
+            if !s.chars().all(html::is_html_whitespace) {
+                break;
+            }
+            self.index += 1;
+        }
+        self.element_list.get(self.index)
+    }
+
+    fn convert<T: FromJsonML>(&mut self, element: &Element) -> Result<T, FromJsonMLError> {
+        let index = self.index;
+        self.index += 1;
+        T::from_jsonml(element).map_err(|error| error.in_child(index))
+    }
+
+    /// Convert the next element, which is required for the field.
+    pub fn child<T: FromJsonML>(&mut self, field: &str) -> Result<T, FromJsonMLError> {
+        match self.peek_element() {
+            Some(element) => self.convert(element),
+            None => Err(FromJsonMLError::new(FromJsonMLErrorKind::MissingChild {
+                field: field.to_string(),
+            })),
+        }
+    }
+
+    /// Convert the next element if it [matches](FromJsonML::matches).
+    pub fn optional_child<T: FromJsonML>(&mut self) -> Result<Option<T>, FromJsonMLError> {
+        match self.peek_element() {
+            Some(element) if T::matches(element) => self.convert(element).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Convert the following elements while they [match](FromJsonML::matches).
+    pub fn children<T: FromJsonML>(&mut self) -> Result<Vec<T>, FromJsonMLError> {
+        let mut children = Vec::new();
+        while let Some(child) = self.optional_child()? {
+            children.push(child);
+        }
+        Ok(children)
+    }
+
+    /// Parse the next string, or an empty string if the next element is not a string.
+    pub fn text<T: FromStr>(&mut self, field: &str) -> Result<T, FromJsonMLError> {
+        let index = self.index;
+        let text = match self.element_list.get(index) {
+            Some(Element::String(s)) => {
+                self.index += 1;
+                s.as_str()
+            }
+            _ => "",
+        };
+        text.parse().map_err(|_| {
+            FromJsonMLError::new(FromJsonMLErrorKind::InvalidText {
+                field: field.to_string(),
+                text: text.to_string(),
+            })
+            .in_child(index)
+        })
+    }
+
+    /// Check that no elements are left other than whitespace-only strings.
+    pub fn finish(mut self) -> Result<(), FromJsonMLError> {
+        match self.peek_element() {
+            Some(_) => {
+                Err(FromJsonMLError::new(FromJsonMLErrorKind::UnexpectedChild).in_child(self.index))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_attribute_value() {
+    assert_eq!(3u8.to_attribute_value(), Some(AttributeValue::Number(3.0)));
+    assert_eq!(
+        16_777_217i64.to_attribute_value(),
+        Some(AttributeValue::String("16777217".to_string()))
+    );
+    for value in [i32::MAX, i32::MIN, i32::MIN + 1] {
+        let attribute_value = value.to_attribute_value().unwrap();
+        assert_eq!(i32::from_attribute_value(&attribute_value), Some(value));
+    }
+    assert_eq!(
+        i32::MIN.to_attribute_value(),
+        Some(AttributeValue::Number(-2_147_483_648.0))
+    );
+    for value in [i64::MAX, i64::MIN] {
+        let attribute_value = value.to_attribute_value().unwrap();
+        assert_eq!(i64::from_attribute_value(&attribute_value), Some(value));
+    }
+    for value in [u64::MAX, 0] {
+        let attribute_value = value.to_attribute_value().unwrap();
+        assert_eq!(u64::from_attribute_value(&attribute_value), Some(value));
+    }
+    assert_eq!(
+        u64::MAX.to_attribute_value(),
+        Some(AttributeValue::String(u64::MAX.to_string()))
+    );
+    assert_eq!(
+        i32::from_attribute_value(&AttributeValue::Number(2_147_483_648.0)),
+        None
+    );
+    assert_eq!(
+        u8::from_attribute_value(&AttributeValue::Number(255.0)),
+        Some(255)
+    );
+    assert_eq!(u8::from_attribute_value(&AttributeValue::Number(1.5)), None);
+    assert_eq!(None::<bool>.to_attribute_value(), None);
+    assert_eq!(
+        u8::from_attribute_value(&AttributeValue::String(" 42 ".to_string())),
+        Some(42)
+    );
+    assert_eq!(
+        u8::from_attribute_value(&AttributeValue::Number(-1.0)),
+        None
+    );
+    assert_eq!(
+        f64::from_attribute_value(&AttributeValue::Number(0.1)),
+        Some(0.1)
+    );
+    assert_eq!(
+        String::from_attribute_value(&AttributeValue::Number(1.5)),
+        Some("1.5".to_string())
+    );
+    assert_eq!(
+        bool::from_attribute_value(&AttributeValue::Null),
+        Some(true)
+    );
+    assert_eq!(bool::from_missing(), Some(false));
+    assert_eq!(u8::from_missing(), None);
+    assert_eq!(Option::<u8>::from_missing(), Some(None));
+}
+
+#[test]
+fn test_children() {
+    let element: Element =
+        serde_json::from_str(r#"["p", {"n": "1"}, "12", " ", ["b"], ["b"], "\n", ["i"], " "]"#)
+            .unwrap();
+    let tag = expect_tag(&element, &["div", "p"]).unwrap();
+    assert_eq!(attribute::<u8>(tag, "n"), Ok(1));
+    let mut children = Children::new(tag);
+    assert_eq!(children.text::<u32>("number"), Ok(12));
+    assert_eq!(children.children::<Element>().unwrap().len(), 3);
+    assert_eq!(children.clone().finish(), Ok(()));
+    assert_eq!(
+        children.child::<Element>("rest"),
+        Err(FromJsonMLError::new(FromJsonMLErrorKind::MissingChild {
+            field: "rest".to_string()
+        }))
+    );
+    assert!(!is_tag(&element, &["b"]));
+}
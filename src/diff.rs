@@ -0,0 +1,722 @@
+//! Structural diff and patch of JsonML elements
+//!
+//! A patch is a list of operations applied in order.
+//! Paths are indices of children from the root element, and refer to the tree as patched by the
+//! preceding operations.
+
+use std::{error::Error, fmt, fmt::Display, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{AttributeValue, Attributes, Element, Tag};
+
+/// Patch operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// Insert a child into the tag at the path.
+    Insert {
+        path: Vec<usize>,
+        index: usize,
+        element: Element,
+    },
+
+    /// Remove a child from the tag at the path.
+    Remove {
+        path: Vec<usize>,
+        index: usize,
+    },
+
+    /// Move a child of the tag at the path, where `to` is the index after removing it from `from`.
+    Move {
+        path: Vec<usize>,
+        from: usize,
+        to: usize,
+    },
+
+    SetAttribute {
+        path: Vec<usize>,
+        name: String,
+        value: AttributeValue,
+    },
+
+    RemoveAttribute {
+        path: Vec<usize>,
+        name: String,
+    },
+
+    /// Change the string at the path.
+    SetText {
+        path: Vec<usize>,
+        text: String,
+    },
+
+    /// Replace the element at the path, when it changes between a tag and a string or its tag
+    /// name changes.
+    Replace {
+        path: Vec<usize>,
+        element: Element,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// Attributes which identify children, such as `key` or `id`
+    ///
+    /// Children with the same tag name and the same value of the first of these attributes are
+    /// matched, and moved if they are reordered.
+    /// Other children are matched with equal children first, and then in order with children of
+    /// the same kind.
+    pub keys: Vec<String>,
+}
+
+/// Operations which patch `old` into `new`
+///
+/// ```
+/// use jsonml::{diff, Element};
+///
+/// let old: Element = serde_json::from_str(r#"["ul", ["li", "a"], ["li", "b"]]"#).unwrap();
+/// let new: Element = serde_json::from_str(r#"["ul", ["li", "a"], ["li", "c"]]"#).unwrap();
+/// let patch = diff::diff(&old, &new);
+/// assert_eq!(
+///     serde_json::to_string(&patch).unwrap(),
+///     r#"[{"op":"set_text","path":[1,0],"text":"c"}]"#
+/// );
+///
+/// let mut element = old;
+/// diff::apply_patch(&mut element, &patch).unwrap();
+/// assert_eq!(element, new);
+/// ```
+pub fn diff(old: &Element, new: &Element) -> Vec<Operation> {
+    diff_with_options(old, new, &DiffOptions::default())
+}
+
+pub fn diff_with_options(old: &Element, new: &Element, options: &DiffOptions) -> Vec<Operation> {
+    let mut differ = Differ {
+        options,
+        operations: vec![],
+        path: vec![],
+    };
+    differ.element(old, new);
+    differ.operations
+}
+
+struct Differ<'a> {
+    options: &'a DiffOptions,
+    operations: Vec<Operation>,
+    path: Vec<usize>,
+}
+
+impl Differ<'_> {
+    #[allow(deprecated)]
+    fn element(&mut self, old: &Element, new: &Element) {
+        match (old, new) {
+            (Element::String(old), Element::String(new)) => {
+                if old != new {
+                    self.operations.push(Operation::SetText {
+                        path: self.path.clone(),
+                        text: new.clone(),
+                    });
+                }
+            }
+            (Element::Tag(old), Element::Tag(new)) if old.name == new.name => {
+                self.attributes(&old.attributes, &new.attributes);
+                self.children(&old.element_list, &new.element_list);
+            }
+            _ => self.operations.push(Operation::Replace {
+                path: self.path.clone(),
+                element: new.clone(),
+            }),
+        }
+    }
+
+    fn attributes(&mut self, old: &Attributes, new: &Attributes) {
+        let removed = old.keys().filter(|name| !new.contains_key(*name));
+        for name in removed {
+            self.operations.push(Operation::RemoveAttribute {
+                path: self.path.clone(),
+                name: name.clone(),
+            });
+        }
+
+        // Attributes are set in the new order, so that new ones are appended as there.
+        let set = new
+            .iter()
+            .filter(|(name, value)| old.get(*name) != Some(value));
+        for (name, value) in set {
+            self.operations.push(Operation::SetAttribute {
+                path: self.path.clone(),
+                name: name.clone(),
+                value: value.clone(),
+            });
+        }
+    }
+
+    fn children(&mut self, old: &[Element], new: &[Element]) {
+        let matches = match_children(old, new, &self.options.keys);
+        let mut matched = vec![false; old.len()];
+        for old_index in matches.iter().flatten() {
+            matched[*old_index] = true;
+        }
+
+        for index in (0..old.len()).rev().filter(|index| !matched[*index]) {
+            self.operations.push(Operation::Remove {
+                path: self.path.clone(),
+                index,
+            });
+        }
+
+        // Old indices of the current children
+        let mut current: Vec<Option<usize>> = (0..old.len())
+            .filter(|index| matched[*index])
+            .map(Some)
+            .collect();
+        for (index, element) in new.iter().enumerate() {
+            match matches[index] {
+                Some(old_index) => {
+                    let from = index
+                        + current[index..]
+                            .iter()
+                            .position(|current| *current == Some(old_index))
+                            .expect("matched children after the index are not yet placed");
+                    if from != index {
+                        self.operations.push(Operation::Move {
+                            path: self.path.clone(),
+                            from,
+                            to: index,
+                        });
+                        let moved = current.remove(from);
+                        current.insert(index, moved);
+                    }
+                    self.path.push(index);
+                    self.element(&old[old_index], element);
+                    self.path.pop();
+                }
+                None => {
+                    self.operations.push(Operation::Insert {
+                        path: self.path.clone(),
+                        index,
+                        element: element.clone(),
+                    });
+                    current.insert(index, None);
+                }
+            }
+        }
+    }
+}
+
+/// Old index matched with each new child
+///
+/// Children with the same tag name and the same value of the first of the key attributes are
+/// matched.
+/// Other children are matched with equal children first, and then in order with children of the
+/// same kind between them.
+pub(crate) fn match_children(
+    old: &[Element],
+    new: &[Element],
+    keys: &[String],
+) -> Vec<Option<usize>> {
+    let mut matches = vec![None; new.len()];
+    let mut matched = vec![false; old.len()];
+    let old_keys: Vec<_> = old.iter().map(|element| key(element, keys)).collect();
+    let new_keys: Vec<_> = new.iter().map(|element| key(element, keys)).collect();
+
+    for (new_index, new_key) in new_keys.iter().enumerate() {
+        if new_key.is_none() {
+            continue;
+        }
+        let found = (0..old.len()).find(|old_index| {
+            !matched[*old_index] && old_keys[*old_index].as_ref() == new_key.as_ref()
+        });
+        if let Some(old_index) = found {
+            matched[old_index] = true;
+            matches[new_index] = Some(old_index);
+        }
+    }
+
+    let old_unkeyed: Vec<_> = (0..old.len()).filter(|i| old_keys[*i].is_none()).collect();
+    let new_unkeyed: Vec<_> = (0..new.len()).filter(|i| new_keys[*i].is_none()).collect();
+    let equal = |i: usize, j: usize| old[old_unkeyed[i]] == new[new_unkeyed[j]];
+    let mut match_equal = |i: usize, j: usize| {
+        matched[old_unkeyed[i]] = true;
+        matches[new_unkeyed[j]] = Some(old_unkeyed[i]);
+    };
+
+    // Longest common subsequence, after the common prefix and suffix
+    let (n, m) = (old_unkeyed.len(), new_unkeyed.len());
+    let prefix = (0..n.min(m)).take_while(|i| equal(*i, *i)).count();
+    let suffix = (0..n.min(m) - prefix)
+        .take_while(|k| equal(n - 1 - k, m - 1 - k))
+        .count();
+    for k in 0..prefix {
+        match_equal(k, k);
+    }
+    for k in 0..suffix {
+        match_equal(n - 1 - k, m - 1 - k);
+    }
+    let mut pairs = vec![];
+    common_subsequence(prefix..n - suffix, prefix..m - suffix, &equal, &mut pairs);
+    for (i, j) in pairs {
+        match_equal(i, j);
+    }
+
+    let mut lower = 0;
+    for (position, &new_index) in new_unkeyed.iter().enumerate() {
+        if let Some(old_index) = matches[new_index] {
+            lower = old_index + 1;
+            continue;
+        }
+        let upper = new_unkeyed[position..]
+            .iter()
+            .find_map(|new_index| matches[*new_index])
+            .unwrap_or(old.len());
+        let found = (lower..upper).find(|old_index| {
+            !matched[*old_index]
+                && old_keys[*old_index].is_none()
+                && same_kind(&old[*old_index], &new[new_index])
+        });
+        if let Some(old_index) = found {
+            matched[old_index] = true;
+            matches[new_index] = Some(old_index);
+            lower = old_index + 1;
+        }
+    }
+    matches
+}
+
+/// Pairs of indices of a longest common subsequence of the ranges, in order
+///
+/// Hirschberg's algorithm takes space linear in the lengths of the ranges instead of the
+/// product.
+fn common_subsequence(
+    old: Range<usize>,
+    new: Range<usize>,
+    equal: &dyn Fn(usize, usize) -> bool,
+    pairs: &mut Vec<(usize, usize)>,
+) {
+    if old.is_empty() || new.is_empty() {
+        return;
+    }
+    if old.len() == 1 {
+        if let Some(j) = new.clone().find(|j| equal(old.start, *j)) {
+            pairs.push((old.start, j));
+        }
+        return;
+    }
+    let middle = old.start + old.len() / 2;
+    let forward = subsequence_lengths(old.start..middle, new.clone(), equal, false);
+    let backward = subsequence_lengths(middle..old.end, new.clone(), equal, true);
+    let split = (0..=new.len())
+        .rev()
+        .max_by_key(|k| forward[*k] + backward[new.len() - k])
+        .expect("the range of splits is not empty");
+    common_subsequence(
+        old.start..middle,
+        new.start..new.start + split,
+        equal,
+        pairs,
+    );
+    common_subsequence(middle..old.end, new.start + split..new.end, equal, pairs);
+}
+
+/// Lengths of the longest common subsequences of the old range with each prefix of the new
+/// range by length, or with each suffix if `reverse`
+fn subsequence_lengths(
+    old: Range<usize>,
+    new: Range<usize>,
+    equal: &dyn Fn(usize, usize) -> bool,
+    reverse: bool,
+) -> Vec<usize> {
+    let mut lengths = vec![0; new.len() + 1];
+    for step in 0..old.len() {
+        let i = if reverse {
+            old.end - 1 - step
+        } else {
+            old.start + step
+        };
+        let mut diagonal = 0;
+        for k in 1..=new.len() {
+            let j = if reverse {
+                new.end - k
+            } else {
+                new.start + k - 1
+            };
+            let above = lengths[k];
+            lengths[k] = if equal(i, j) {
+                diagonal + 1
+            } else {
+                above.max(lengths[k - 1])
+            };
+            diagonal = above;
+        }
+    }
+    lengths
+}
+
+#[allow(deprecated)]
+fn key<'a>(element: &'a Element, keys: &[String]) -> Option<(&'a str, &'a AttributeValue)> {
+    match element {
+        Element::Tag(tag) => keys
+            .iter()
+            .find_map(|key| tag.attributes.get(key))
+            .map(|value| (tag.name.as_str(), value)),
+        Element::String(_) => None,
+    }
+}
+
+#[allow(deprecated)]
+fn same_kind(a: &Element, b: &Element) -> bool {
+    match (a, b) {
+        (Element::Tag(a), Element::Tag(b)) => a.name == b.name,
+        (Element::String(_), Element::String(_)) => true,
+        _ => false,
+    }
+}
+
+/// Error of applying a patch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    kind: PatchErrorKind,
+    operation: usize,
+}
+
+impl PatchError {
+    pub fn kind(&self) -> &PatchErrorKind {
+        &self.kind
+    }
+
+    /// Index of the failed operation
+    pub fn operation(&self) -> usize {
+        self.operation
+    }
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} in operation {}", self.kind, self.operation)
+    }
+}
+
+impl Error for PatchError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PatchErrorKind {
+    /// No element at the path
+    InvalidPath(Vec<usize>),
+    /// The element at the path is not a tag.
+    NotATag(Vec<usize>),
+    /// The element at the path is not a string.
+    NotAString(Vec<usize>),
+    /// The child index is out of range.
+    InvalidIndex(usize),
+}
+
+impl Display for PatchErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchErrorKind::InvalidPath(path) => write!(f, "no element at path {path:?}"),
+            PatchErrorKind::NotATag(path) => write!(f, "element at path {path:?} is not a tag"),
+            PatchErrorKind::NotAString(path) => {
+                write!(f, "element at path {path:?} is not a string")
+            }
+            PatchErrorKind::InvalidIndex(index) => write!(f, "child index {index} is out of range"),
+        }
+    }
+}
+
+/// Apply operations in order
+///
+/// The operations before a failed one are kept applied.
+pub fn apply_patch(element: &mut Element, patch: &[Operation]) -> Result<(), PatchError> {
+    for (index, operation) in patch.iter().enumerate() {
+        apply(element, operation).map_err(|kind| PatchError {
+            kind,
+            operation: index,
+        })?;
+    }
+    Ok(())
+}
+
+#[allow(deprecated)]
+fn apply(root: &mut Element, operation: &Operation) -> Result<(), PatchErrorKind> {
+    match operation {
+        Operation::Insert {
+            path,
+            index,
+            element,
+        } => {
+            let children = &mut tag(root, path)?.element_list;
+            if *index > children.len() {
+                return Err(PatchErrorKind::InvalidIndex(*index));
+            }
+            children.insert(*index, element.clone());
+        }
+        Operation::Remove { path, index } => {
+            let children = &mut tag(root, path)?.element_list;
+            if *index >= children.len() {
+                return Err(PatchErrorKind::InvalidIndex(*index));
+            }
+            children.remove(*index);
+        }
+        Operation::Move { path, from, to } => {
+            let children = &mut tag(root, path)?.element_list;
+            if *from >= children.len() {
+                return Err(PatchErrorKind::InvalidIndex(*from));
+            }
+            if *to >= children.len() {
+                return Err(PatchErrorKind::InvalidIndex(*to));
+            }
+            let element = children.remove(*from);
+            children.insert(*to, element);
+        }
+        Operation::SetAttribute { path, name, value } => {
+            tag(root, path)?
+                .attributes
+                .insert(name.clone(), value.clone());
+        }
+        Operation::RemoveAttribute { path, name } => {
+            tag(root, path)?.attributes.shift_remove(name);
+        }
+        Operation::SetText { path, text } => match element(root, path)? {
+            Element::String(s) => *s = text.clone(),
+            Element::Tag(_) => return Err(PatchErrorKind::NotAString(path.clone())),
+        },
+        Operation::Replace { path, element: new } => *element(root, path)? = new.clone(),
+    }
+    Ok(())
+}
+
+#[allow(deprecated)]
+fn element<'a>(root: &'a mut Element, path: &[usize]) -> Result<&'a mut Element, PatchErrorKind> {
+    let mut element = root;
+    for index in path {
+        element = match element {
+            Element::Tag(tag) => tag.element_list.get_mut(*index),
+            Element::String(_) => None,
+        }
+        .ok_or_else(|| PatchErrorKind::InvalidPath(path.to_vec()))?;
+    }
+    Ok(element)
+}
+
+fn tag<'a>(root: &'a mut Element, path: &[usize]) -> Result<&'a mut Tag, PatchErrorKind> {
+    match element(root, path)? {
+        Element::Tag(tag) => Ok(tag),
+        Element::String(_) => Err(PatchErrorKind::NotATag(path.to_vec())),
+    }
+}
+
+#[cfg(test)]
+use crate::test_helpers::from_json;
+
+#[cfg(test)]
+fn assert_patch(old: &str, new: &str, options: &DiffOptions) -> Vec<Operation> {
+    let (old, new) = (from_json(old), from_json(new));
+    let patch = diff_with_options(&old, &new, options);
+    let json = serde_json::to_string(&patch).unwrap();
+    let patch: Vec<Operation> = serde_json::from_str(&json).unwrap();
+    let mut element = old;
+    apply_patch(&mut element, &patch).unwrap();
+    assert_eq!(element, new);
+    patch
+}
+
+#[test]
+fn test_diff_attributes_and_text() {
+    let patch = assert_patch(
+        r#"["p", {"class": "a", "title": "t"}, "text"]"#,
+        r#"["p", {"class": "b", "hidden": true}, "changed"]"#,
+        &DiffOptions::default(),
+    );
+    assert_eq!(
+        patch,
+        [
+            Operation::RemoveAttribute {
+                path: vec![],
+                name: "title".to_string()
+            },
+            Operation::SetAttribute {
+                path: vec![],
+                name: "class".to_string(),
+                value: AttributeValue::String("b".to_string())
+            },
+            Operation::SetAttribute {
+                path: vec![],
+                name: "hidden".to_string(),
+                value: AttributeValue::Bool(true)
+            },
+            Operation::SetText {
+                path: vec![0],
+                text: "changed".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        diff(&from_json(r#"["p", "a"]"#), &from_json(r#"["p", "a"]"#)),
+        []
+    );
+}
+
+#[test]
+fn test_diff_children() {
+    let patch = assert_patch(
+        r#"["div", "a", ["p", "b"], ["span"], "c"]"#,
+        r#"["div", ["h1"], "a", ["p", "b"], ["em", "c"]]"#,
+        &DiffOptions::default(),
+    );
+    assert_eq!(
+        patch,
+        [
+            Operation::Remove {
+                path: vec![],
+                index: 3
+            },
+            Operation::Remove {
+                path: vec![],
+                index: 2
+            },
+            Operation::Insert {
+                path: vec![],
+                index: 0,
+                element: from_json(r#"["h1"]"#)
+            },
+            Operation::Insert {
+                path: vec![],
+                index: 3,
+                element: from_json(r#"["em", "c"]"#)
+            },
+        ]
+    );
+    assert_patch(
+        r#"["div", "a"]"#,
+        r#"["span", "a"]"#,
+        &DiffOptions::default(),
+    );
+    assert_patch(r#"["div", "a"]"#, r#""a""#, &DiffOptions::default());
+}
+
+#[test]
+fn test_diff_keyed_children() {
+    let options = DiffOptions {
+        keys: vec!["key".to_string(), "id".to_string()],
+    };
+    let patch = assert_patch(
+        r#"["ul", ["li", {"key": 1}, "a"], ["li", {"key": 2}, "b"], ["li", {"id": "c"}, "c"]]"#,
+        r#"["ul", ["li", {"id": "c"}, "c"], ["li", {"key": 1}, "a"], ["li", {"key": 3}, "d"]]"#,
+        &options,
+    );
+    assert_eq!(
+        patch,
+        [
+            Operation::Remove {
+                path: vec![],
+                index: 1
+            },
+            Operation::Move {
+                path: vec![],
+                from: 1,
+                to: 0
+            },
+            Operation::Insert {
+                path: vec![],
+                index: 2,
+                element: from_json(r#"["li", {"key": 3}, "d"]"#)
+            },
+        ]
+    );
+
+    let patch = assert_patch(
+        r#"["ul", ["li", {"key": 1}, "a"], ["li", {"key": 2}, "b"]]"#,
+        r#"["ul", ["li", {"key": 2}, "b"], ["li", {"key": 1}, "a"]]"#,
+        &DiffOptions::default(),
+    );
+    assert_eq!(patch.len(), 2);
+}
+
+#[test]
+fn test_common_subsequence() {
+    for (old, new, len) in [
+        ("abcbdab", "bdcaba", 4),
+        ("", "ab", 0),
+        ("aaaa", "aa", 2),
+        ("abc", "xyz", 0),
+        ("xaybzc", "abc", 3),
+    ] {
+        let (old, new): (Vec<char>, Vec<char>) = (old.chars().collect(), new.chars().collect());
+        let mut pairs = vec![];
+        common_subsequence(
+            0..old.len(),
+            0..new.len(),
+            &|i, j| old[i] == new[j],
+            &mut pairs,
+        );
+        assert_eq!(pairs.len(), len);
+        assert!(pairs.iter().all(|&(i, j)| old[i] == new[j]));
+        assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+    }
+}
+
+#[test]
+fn test_diff_many_children() {
+    let children = |range: &mut dyn Iterator<Item = usize>| {
+        Element::Tag(
+            Tag::from_parts(
+                "ul",
+                Attributes::new(),
+                range.map(|i| Element::String(i.to_string())).collect(),
+            )
+            .unwrap(),
+        )
+    };
+    let old = children(&mut (0..2000));
+    let new = children(&mut (0..2000).rev());
+    let mut element = old.clone();
+    apply_patch(&mut element, &diff(&old, &new)).unwrap();
+    assert_eq!(element, new);
+}
+
+#[test]
+fn test_apply_patch_errors() {
+    let mut element = from_json(r#"["p", "a"]"#);
+    let error = apply_patch(
+        &mut element,
+        &[
+            Operation::SetText {
+                path: vec![0],
+                text: "b".to_string(),
+            },
+            Operation::Remove {
+                path: vec![0],
+                index: 0,
+            },
+        ],
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), &PatchErrorKind::NotATag(vec![0]));
+    assert_eq!(
+        error.to_string(),
+        "element at path [0] is not a tag in operation 1"
+    );
+    assert_eq!(element, from_json(r#"["p", "b"]"#));
+
+    let error = apply_patch(
+        &mut element,
+        &[Operation::Insert {
+            path: vec![],
+            index: 2,
+            element: from_json(r#""c""#),
+        }],
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), &PatchErrorKind::InvalidIndex(2));
+    let error = apply_patch(
+        &mut element,
+        &[Operation::SetText {
+            path: vec![1],
+            text: "c".to_string(),
+        }],
+    )
+    .unwrap_err();
+    assert_eq!(error.kind(), &PatchErrorKind::InvalidPath(vec![1]));
+}
@@ -0,0 +1,1301 @@
+//! HTML parsing into and rendering from JsonML elements
+//!
+//! The parser follows the [HTML Standard](https://html.spec.whatwg.org/multipage/parsing.html)
+//! by way of [`html5ever`], so implied tags such as `html`, `head`, `body` or `tbody`
+//! are inserted, void elements never receive children, character references are decoded
+//! and tag names are folded to lower case.
+//!
+//! Comments, processing instructions and the document type declaration have no JsonML
+//! representation and are dropped.
+//!
+//! Elements can also be built with typed builders, one for each element such as [`a()`].
+
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{self, Display},
+    io,
+};
+
+use html5ever::{
+    interface::{ElementFlags, NodeOrText, QuirksMode, TreeSink},
+    local_name, namespace_url, ns, parse_document as parse_html_document,
+    parse_fragment as parse_html_fragment,
+    tendril::{StrTendril, TendrilSink},
+    Attribute, ExpandedName, ParseOpts, QualName,
+};
+
+use html_escape::{encode_double_quoted_attribute, encode_unquoted_attribute};
+
+use crate::{AttributeValue, Attributes, Element, Tag};
+
+mod builder;
+pub(crate) mod minify;
+pub(crate) mod pretty;
+
+pub use builder::*;
+
+/// Options for the HTML parser
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Drop text nodes which consist only of ASCII whitespace.
+    ///
+    /// The HTML Standard keeps most inter-element whitespace, which is what the parser does by
+    /// default.
+    pub drop_whitespace_text: bool,
+}
+
+/// Parse a whole HTML document
+///
+/// The returned element is always the `html` root element.
+pub fn parse_document(input: &str) -> Element {
+    parse_document_with_options(input, &ParseOptions::default())
+}
+
+/// Parse a whole HTML document with options
+pub fn parse_document_with_options(input: &str, options: &ParseOptions) -> Element {
+    let sink = parse_html_document(Sink::default(), ParseOpts::default()).one(input);
+    let root = sink.nodes[sink.document]
+        .children
+        .iter()
+        .copied()
+        .find(|&child| matches!(sink.nodes[child].data, NodeData::Element { .. }))
+        .expect("HTML parser always creates the root element");
+    sink.to_element(root, options)
+        .expect("root element is not text")
+}
+
+/// Parse an HTML fragment as it would appear inside a `body` element
+pub fn parse_fragment(input: &str) -> Vec<Element> {
+    parse_fragment_with_options(input, &ParseOptions::default())
+}
+
+/// Parse an HTML fragment as it would appear inside a `body` element, with options
+pub fn parse_fragment_with_options(input: &str, options: &ParseOptions) -> Vec<Element> {
+    let sink = parse_html_fragment(
+        Sink::default(),
+        ParseOpts::default(),
+        QualName::new(None, ns!(html), local_name!("body")),
+        vec![],
+    )
+    .one(input);
+    // The fragment parsing algorithm puts the nodes into an `html` element under the document.
+    let root = sink.nodes[sink.document].children[0];
+    sink.children_to_elements(root, options)
+}
+
+#[derive(Debug)]
+enum NodeData {
+    Document,
+    Element {
+        name: QualName,
+        attributes: Vec<Attribute>,
+        template_contents: Option<usize>,
+    },
+    Text(StrTendril),
+    Other,
+}
+
+#[derive(Debug)]
+struct Node {
+    data: NodeData,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// Arena based tree which html5ever builds the document into.
+#[derive(Debug)]
+struct Sink {
+    nodes: Vec<Node>,
+    document: usize,
+}
+
+impl Default for Sink {
+    fn default() -> Self {
+        Sink {
+            nodes: vec![Node {
+                data: NodeData::Document,
+                parent: None,
+                children: vec![],
+            }],
+            document: 0,
+        }
+    }
+}
+
+impl Sink {
+    fn new_node(&mut self, data: NodeData) -> usize {
+        self.nodes.push(Node {
+            data,
+            parent: None,
+            children: vec![],
+        });
+        self.nodes.len() - 1
+    }
+
+    fn detach(&mut self, target: usize) {
+        if let Some(parent) = self.nodes[target].parent.take() {
+            self.nodes[parent].children.retain(|&child| child != target);
+        }
+    }
+
+    /// Insert a node or text at `index` of the parent's children, merging adjacent text.
+    fn insert(&mut self, parent: usize, index: usize, child: NodeOrText<usize>) {
+        match child {
+            NodeOrText::AppendText(text) => {
+                if index > 0 {
+                    let previous = self.nodes[parent].children[index - 1];
+                    if let NodeData::Text(ref mut existing) = self.nodes[previous].data {
+                        existing.push_tendril(&text);
+                        return;
+                    }
+                }
+                let node = self.new_node(NodeData::Text(text));
+                self.nodes[node].parent = Some(parent);
+                self.nodes[parent].children.insert(index, node);
+            }
+            NodeOrText::AppendNode(node) => {
+                self.detach(node);
+                let index = index.min(self.nodes[parent].children.len());
+                self.nodes[node].parent = Some(parent);
+                self.nodes[parent].children.insert(index, node);
+            }
+        }
+    }
+
+    fn children_to_elements(&self, node: usize, options: &ParseOptions) -> Vec<Element> {
+        let node = match self.nodes[node].data {
+            NodeData::Element {
+                template_contents: Some(contents),
+                ..
+            } => contents,
+            _ => node,
+        };
+        self.nodes[node]
+            .children
+            .iter()
+            .filter_map(|&child| self.to_element(child, options))
+            .collect()
+    }
+
+    #[allow(deprecated)]
+    fn to_element(&self, node: usize, options: &ParseOptions) -> Option<Element> {
+        match &self.nodes[node].data {
+            NodeData::Element {
+                name, attributes, ..
+            } => Some(Element::Tag(Tag {
+                name: name.local.to_string(),
+                attributes: attributes
+                    .iter()
+                    .map(|attribute| {
+                        (
+                            qualified_name(&attribute.name),
+                            AttributeValue::String(attribute.value.to_string()),
+                        )
+                    })
+                    .collect::<Attributes>(),
+                element_list: self.children_to_elements(node, options),
+            })),
+            NodeData::Text(text) => {
+                if options.drop_whitespace_text && text.chars().all(|c| c.is_ascii_whitespace()) {
+                    None
+                } else {
+                    Some(Element::String(text.to_string()))
+                }
+            }
+            NodeData::Document | NodeData::Other => None,
+        }
+    }
+}
+
+/// Attribute names in foreign content such as `xlink:href` keep their prefix.
+fn qualified_name(name: &QualName) -> String {
+    match &name.prefix {
+        Some(prefix) => format!("{}:{}", prefix, name.local),
+        None => name.local.to_string(),
+    }
+}
+
+impl TreeSink for Sink {
+    type Handle = usize;
+    type Output = Self;
+
+    fn finish(self) -> Self::Output {
+        self
+    }
+
+    // Parse errors are recovered from as the HTML Standard specifies.
+    fn parse_error(&mut self, _msg: Cow<'static, str>) {}
+
+    fn get_document(&mut self) -> Self::Handle {
+        self.document
+    }
+
+    fn elem_name<'a>(&'a self, target: &'a Self::Handle) -> ExpandedName<'a> {
+        match &self.nodes[*target].data {
+            NodeData::Element { name, .. } => name.expanded(),
+            _ => panic!("not an element"),
+        }
+    }
+
+    fn create_element(
+        &mut self,
+        name: QualName,
+        attrs: Vec<Attribute>,
+        flags: ElementFlags,
+    ) -> Self::Handle {
+        let template_contents = if flags.template {
+            Some(self.new_node(NodeData::Document))
+        } else {
+            None
+        };
+        self.new_node(NodeData::Element {
+            name,
+            attributes: attrs,
+            template_contents,
+        })
+    }
+
+    fn create_comment(&mut self, _text: StrTendril) -> Self::Handle {
+        self.new_node(NodeData::Other)
+    }
+
+    fn create_pi(&mut self, _target: StrTendril, _data: StrTendril) -> Self::Handle {
+        self.new_node(NodeData::Other)
+    }
+
+    fn append(&mut self, parent: &Self::Handle, child: NodeOrText<Self::Handle>) {
+        let index = self.nodes[*parent].children.len();
+        self.insert(*parent, index, child);
+    }
+
+    fn append_based_on_parent_node(
+        &mut self,
+        element: &Self::Handle,
+        prev_element: &Self::Handle,
+        child: NodeOrText<Self::Handle>,
+    ) {
+        if self.nodes[*element].parent.is_some() {
+            self.append_before_sibling(element, child);
+        } else {
+            self.append(prev_element, child);
+        }
+    }
+
+    fn append_doctype_to_document(
+        &mut self,
+        _name: StrTendril,
+        _public_id: StrTendril,
+        _system_id: StrTendril,
+    ) {
+    }
+
+    fn get_template_contents(&mut self, target: &Self::Handle) -> Self::Handle {
+        match self.nodes[*target].data {
+            NodeData::Element {
+                template_contents: Some(contents),
+                ..
+            } => contents,
+            _ => panic!("not a template element"),
+        }
+    }
+
+    fn same_node(&self, x: &Self::Handle, y: &Self::Handle) -> bool {
+        x == y
+    }
+
+    fn set_quirks_mode(&mut self, _mode: QuirksMode) {}
+
+    fn append_before_sibling(
+        &mut self,
+        sibling: &Self::Handle,
+        new_node: NodeOrText<Self::Handle>,
+    ) {
+        let parent = self.nodes[*sibling].parent.expect("sibling has a parent");
+        if let NodeOrText::AppendNode(node) = new_node {
+            self.detach(node);
+        }
+        let index = self.nodes[parent]
+            .children
+            .iter()
+            .position(|&child| child == *sibling)
+            .expect("sibling is a child of its parent");
+        self.insert(parent, index, new_node);
+    }
+
+    fn add_attrs_if_missing(&mut self, target: &Self::Handle, attrs: Vec<Attribute>) {
+        if let NodeData::Element { attributes, .. } = &mut self.nodes[*target].data {
+            for attr in attrs {
+                if !attributes.iter().any(|existing| existing.name == attr.name) {
+                    attributes.push(attr);
+                }
+            }
+        }
+    }
+
+    fn remove_from_parent(&mut self, target: &Self::Handle) {
+        self.detach(*target);
+    }
+
+    fn reparent_children(&mut self, node: &Self::Handle, new_parent: &Self::Handle) {
+        let children = std::mem::take(&mut self.nodes[*node].children);
+        for &child in &children {
+            self.nodes[child].parent = Some(*new_parent);
+        }
+        self.nodes[*new_parent].children.extend(children);
+    }
+}
+
+/// How attribute values other than strings are rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AttributeValueStyle {
+    /// Render [`AttributeValue::Bool`] `true` and [`AttributeValue::Null`] as
+    /// [boolean attributes](https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#boolean-attributes)
+    /// without values, and omit attributes with `false`.
+    #[default]
+    Html,
+
+    /// Render booleans and null as JSON literals, such as `disabled="false"`.
+    Json,
+}
+
+/// Options for HTML rendering
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    pub attribute_value_style: AttributeValueStyle,
+    pub layout: Layout,
+
+    /// Write attributes sorted by name instead of in the order of the tag.
+    pub sort_attributes: bool,
+}
+
+/// Layout of rendered HTML
+#[derive(Debug, Clone, Default)]
+pub enum Layout {
+    /// Render in one line, as [`Display`](std::fmt::Display) for [`Element`] does.
+    #[default]
+    Compact,
+
+    /// Pretty-print with line breaks and indentation.
+    Pretty(PrettyOptions),
+
+    /// Render as short as possible.
+    ///
+    /// With the default options, a tree which the HTML parser produces is rendered so that it
+    /// parses back into the same tree.
+    Minified(MinifyOptions),
+}
+
+/// Options for pretty-printing HTML
+///
+/// Block elements are put on their own lines, while no whitespace is inserted inside inline
+/// elements such as `span` and whitespace-sensitive elements such as `pre`.
+#[derive(Debug, Clone)]
+pub struct PrettyOptions {
+    /// Indentation for each level, two spaces by default
+    pub indent: String,
+
+    /// Width in characters to wrap inline content at, 80 by default
+    pub line_width: usize,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        PrettyOptions {
+            indent: "  ".to_string(),
+            line_width: 80,
+        }
+    }
+}
+
+/// Options for minifying HTML
+///
+/// Attribute values are unquoted where that is shorter and empty values are omitted.
+#[derive(Debug, Clone)]
+pub struct MinifyOptions {
+    /// Omit end tags such as `</li>`, `</p>` and `</td>` where the HTML Standard allows,
+    /// enabled by default.
+    pub omit_optional_end_tags: bool,
+
+    /// Collapse each run of whitespace in text into a space and remove whitespace next to block
+    /// elements, except in whitespace-sensitive elements such as `pre`; disabled by default.
+    ///
+    /// This is lossy: the output parses back into a tree with whitespace collapsed and
+    /// whitespace text between blocks, such as `" "` between two `p`, dropped.
+    pub collapse_whitespace: bool,
+}
+
+impl Default for MinifyOptions {
+    fn default() -> Self {
+        MinifyOptions {
+            omit_optional_end_tags: true,
+            collapse_whitespace: false,
+        }
+    }
+}
+
+/// Error on rendering an element which cannot be written as HTML
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderError {
+    kind: RenderErrorKind,
+    path: Vec<usize>,
+}
+
+impl RenderError {
+    pub(crate) fn new(kind: RenderErrorKind, path: &[usize]) -> Self {
+        RenderError {
+            kind,
+            path: path.to_vec(),
+        }
+    }
+
+    pub fn kind(&self) -> &RenderErrorKind {
+        &self.kind
+    }
+
+    /// Indices of the offending element in the element lists from the rendered element
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at path {:?}", self.kind, self.path)
+    }
+}
+
+impl Error for RenderError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RenderErrorKind {
+    InvalidTagName {
+        name: String,
+        reason: InvalidNameReason,
+    },
+    InvalidAttributeName {
+        name: String,
+        reason: InvalidNameReason,
+    },
+    /// The text of a raw text element such as `script` contains its end tag, which cannot be
+    /// escaped.
+    EndTagInRawText { name: String },
+    /// A void element such as `br` has children, which it cannot contain.
+    ChildrenInVoidElement { name: String },
+}
+
+impl Display for RenderErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderErrorKind::InvalidTagName { name, reason } => {
+                write!(f, "invalid tag name {name:?}: {reason}")
+            }
+            RenderErrorKind::InvalidAttributeName { name, reason } => {
+                write!(f, "invalid attribute name {name:?}: {reason}")
+            }
+            RenderErrorKind::EndTagInRawText { name } => {
+                write!(f, "text of {name:?} element contains its end tag")
+            }
+            RenderErrorKind::ChildrenInVoidElement { name } => {
+                write!(f, "void element {name:?} has children")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidNameReason {
+    Empty,
+    InvalidChar(char),
+}
+
+impl Display for InvalidNameReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidNameReason::Empty => write!(f, "name is empty"),
+            InvalidNameReason::InvalidChar(c) => write!(f, "{c:?} is not allowed"),
+        }
+    }
+}
+
+/// Error on writing an element as HTML
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum WriteError {
+    Render(RenderError),
+    Fmt(fmt::Error),
+    Io(io::Error),
+}
+
+impl Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Render(error) => error.fmt(f),
+            WriteError::Fmt(error) => error.fmt(f),
+            WriteError::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WriteError::Render(error) => Some(error),
+            WriteError::Fmt(error) => Some(error),
+            WriteError::Io(error) => Some(error),
+        }
+    }
+}
+
+impl From<RenderError> for WriteError {
+    fn from(error: RenderError) -> Self {
+        WriteError::Render(error)
+    }
+}
+
+impl From<fmt::Error> for WriteError {
+    fn from(error: fmt::Error) -> Self {
+        WriteError::Fmt(error)
+    }
+}
+
+/// Adapter from [`io::Write`] to [`fmt::Write`] which keeps the I/O error.
+pub(crate) struct IoWriter<'a, W: io::Write + ?Sized> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<'a, W: io::Write + ?Sized> IoWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W) -> Self {
+        IoWriter { inner, error: None }
+    }
+
+    pub(crate) fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+impl<W: io::Write + ?Sized> fmt::Write for IoWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Render an element as HTML
+///
+/// This is what [`Element::try_to_html`] does.
+/// See [`Element::write_html`] and [`Element::write_html_io`] for writing without building a
+/// string.
+pub fn to_string(element: &Element) -> Result<String, RenderError> {
+    element.try_to_html()
+}
+
+/// Render an element as HTML, with options
+pub fn to_string_with_options(
+    element: &Element,
+    options: &RenderOptions,
+) -> Result<String, RenderError> {
+    element.try_to_html_with_options(options)
+}
+
+pub(crate) fn write_attribute<W>(
+    writer: &mut W,
+    key: &str,
+    value: &AttributeValue,
+    options: &RenderOptions,
+) -> fmt::Result
+where
+    W: fmt::Write + ?Sized,
+{
+    match (options.attribute_value_style, value) {
+        (AttributeValueStyle::Html, AttributeValue::Bool(true) | AttributeValue::Null) => {
+            write!(writer, " {key}")
+        }
+        (AttributeValueStyle::Html, AttributeValue::Bool(false)) => Ok(()),
+        _ if matches!(options.layout, Layout::Minified(_)) => {
+            let value = match value {
+                AttributeValue::String(s) => Cow::Borrowed(s.as_str()),
+                AttributeValue::Number(n) => Cow::Owned(format_number(*n)),
+                AttributeValue::Bool(b) => Cow::Owned(b.to_string()),
+                AttributeValue::Null => Cow::Borrowed("null"),
+            };
+            write_minified_attribute(writer, key, &value)
+        }
+        _ => write!(writer, r#" {key}="{value}""#),
+    }
+}
+
+fn write_minified_attribute<W>(writer: &mut W, key: &str, value: &str) -> fmt::Result
+where
+    W: fmt::Write + ?Sized,
+{
+    // https://html.spec.whatwg.org/multipage/syntax.html#attributes-2
+    if value.is_empty() {
+        return write!(writer, " {key}");
+    }
+    if !value
+        .chars()
+        .any(|c| is_html_whitespace(c) || matches!(c, '"' | '\'' | '=' | '<' | '>' | '`' | '&'))
+    {
+        return write!(writer, " {key}={value}");
+    }
+    let unquoted = encode_unquoted_attribute(value);
+    let quoted = encode_double_quoted_attribute(value);
+    if unquoted.len() <= quoted.len() + 2 {
+        write!(writer, " {key}={unquoted}")
+    } else {
+        write!(writer, r#" {key}="{quoted}""#)
+    }
+}
+
+/// Format a number with the shortest digits which identify the `f32`, in the way
+/// JavaScript's `Number.prototype.toString` does.
+// https://tc39.es/ecma262/#sec-numeric-types-number-tostring
+pub(crate) fn format_number(number: f32) -> String {
+    if number.is_nan() {
+        return "NaN".to_string();
+    }
+    if number.is_infinite() {
+        return if number > 0.0 {
+            "Infinity"
+        } else {
+            "-Infinity"
+        }
+        .to_string();
+    }
+    if number == 0.0 {
+        return "0".to_string();
+    }
+    let sign = if number < 0.0 { "-" } else { "" };
+    let scientific = format!("{:e}", number.abs());
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("scientific notation has an exponent");
+    let exponent: i32 = exponent.parse().expect("exponent is an integer");
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+    if k <= n && n <= 21 {
+        format!("{sign}{digits}{}", "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        let (integer, fraction) = digits.split_at(n as usize);
+        format!("{sign}{integer}.{fraction}")
+    } else if -6 < n && n <= 0 {
+        format!("{sign}0.{}{digits}", "0".repeat(-n as usize))
+    } else {
+        let exponent_sign = if n - 1 < 0 { '-' } else { '+' };
+        format!("{sign}{mantissa}e{exponent_sign}{}", (n - 1).abs())
+    }
+}
+
+/// Check that the tag name can be written, which consists of ASCII alphanumerics.
+// https://html.spec.whatwg.org/multipage/syntax.html#syntax-tag-name
+pub(crate) fn validate_tag_name(name: &str) -> Result<(), InvalidNameReason> {
+    if name.is_empty() {
+        return Err(InvalidNameReason::Empty);
+    }
+    match name.chars().find(|c| !c.is_ascii_alphanumeric()) {
+        Some(c) => Err(InvalidNameReason::InvalidChar(c)),
+        None => Ok(()),
+    }
+}
+
+/// Check that the attribute name can be written.
+// https://html.spec.whatwg.org/multipage/syntax.html#attributes-2
+pub(crate) fn validate_attribute_name(name: &str) -> Result<(), InvalidNameReason> {
+    if name.is_empty() {
+        return Err(InvalidNameReason::Empty);
+    }
+    match name.chars().find(|&c| {
+        c.is_control()
+            || matches!(c, ' ' | '"' | '\'' | '>' | '/' | '=' | '\u{FDD0}'..='\u{FDEF}')
+            // Noncharacters at the end of each plane
+            || c as u32 & 0xFFFE == 0xFFFE
+    }) {
+        Some(c) => Err(InvalidNameReason::InvalidChar(c)),
+        None => Ok(()),
+    }
+}
+
+// https://html.spec.whatwg.org/multipage/parsing.html#serializes-as-void
+pub(crate) fn is_void_element(name: &str) -> bool {
+    [
+        "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+        "keygen", "link", "meta", "param", "source", "track", "wbr",
+    ]
+    .iter()
+    .any(|void| name.eq_ignore_ascii_case(void))
+}
+
+/// Elements whose text children are serialized literally
+///
+/// `noscript` is not included as if scripting were disabled.
+/// Escapable raw text elements, `textarea` and `title`, have their text escaped as usual.
+// https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+pub(crate) fn is_raw_text_element(name: &str) -> bool {
+    [
+        "style",
+        "script",
+        "xmp",
+        "iframe",
+        "noembed",
+        "noframes",
+        "plaintext",
+    ]
+    .iter()
+    .any(|raw| name.eq_ignore_ascii_case(raw))
+}
+
+/// Elements which are rendered inline by default, where whitespace around them is significant
+// https://html.spec.whatwg.org/multipage/dom.html#phrasing-content
+pub(crate) fn is_inline_element(name: &str) -> bool {
+    [
+        "a", "abbr", "acronym", "audio", "b", "bdi", "bdo", "big", "br", "button", "canvas",
+        "cite", "code", "data", "del", "dfn", "em", "embed", "font", "i", "iframe", "img", "input",
+        "ins", "kbd", "label", "mark", "math", "meter", "object", "output", "picture", "progress",
+        "q", "rp", "rt", "ruby", "s", "samp", "select", "slot", "small", "span", "strike",
+        "strong", "sub", "sup", "svg", "textarea", "time", "tt", "u", "var", "video", "wbr",
+    ]
+    .iter()
+    .any(|inline| name.eq_ignore_ascii_case(inline))
+}
+
+/// Elements whose whitespace in content is preserved
+pub(crate) fn is_whitespace_sensitive_element(name: &str) -> bool {
+    ["pre", "textarea", "listing", "plaintext", "xmp"]
+        .iter()
+        .any(|sensitive| name.eq_ignore_ascii_case(sensitive))
+        || is_raw_text_element(name)
+}
+
+// https://infra.spec.whatwg.org/#ascii-whitespace
+pub(crate) fn is_html_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\x0C' | '\r' | ' ')
+}
+
+/// Whether the text contains an end tag of the element, which the tokenizer would take as the
+/// end of raw text.
+// https://html.spec.whatwg.org/multipage/parsing.html#script-data-end-tag-name-state
+pub(crate) fn contains_end_tag(text: &str, name: &str) -> bool {
+    let bytes = text.as_bytes();
+    bytes
+        .windows(2 + name.len())
+        .enumerate()
+        .any(|(i, window)| {
+            window.starts_with(b"</")
+                && window[2..].eq_ignore_ascii_case(name.as_bytes())
+                && matches!(
+                    bytes.get(i + window.len()),
+                    None | Some(b'\t' | b'\n' | b'\x0C' | b'\r' | b' ' | b'/' | b'>')
+                )
+        })
+}
+
+#[cfg(test)]
+use crate::test_helpers::{tag, text};
+
+#[test]
+fn test_validate_attribute_name() {
+    for name in [
+        "a",
+        "data-x",
+        "@click",
+        ":a",
+        "\u{FDCF}",
+        "\u{FFFD}",
+        "\u{10FFFD}",
+    ] {
+        assert_eq!(validate_attribute_name(name), Ok(()), "{name:?}");
+    }
+    assert_eq!(validate_attribute_name(""), Err(InvalidNameReason::Empty));
+    for c in [
+        '\u{0}',
+        '\u{7F}',
+        ' ',
+        '"',
+        '\'',
+        '>',
+        '/',
+        '=',
+        '\u{FDD0}',
+        '\u{FDEF}',
+        '\u{FFFE}',
+        '\u{1FFFF}',
+        '\u{10FFFF}',
+    ] {
+        assert_eq!(
+            validate_attribute_name(&format!("a{c}")),
+            Err(InvalidNameReason::InvalidChar(c))
+        );
+    }
+}
+
+#[test]
+fn test_parse_document_implied_tags() {
+    assert_eq!(
+        parse_document("<!DOCTYPE html><title>a</title><p>b"),
+        tag(
+            "html",
+            &[],
+            vec![
+                tag("head", &[], vec![tag("title", &[], vec![text("a")])]),
+                tag("body", &[], vec![tag("p", &[], vec![text("b")])]),
+            ]
+        )
+    );
+}
+
+#[test]
+fn test_parse_fragment_void_elements_and_case_folding() {
+    assert_eq!(
+        parse_fragment(r#"<P CLASS="x">a<BR>b<img src="c">d</P>"#),
+        vec![tag(
+            "p",
+            &[("class", "x")],
+            vec![
+                text("a"),
+                tag("br", &[], vec![]),
+                text("b"),
+                tag("img", &[("src", "c")], vec![]),
+                text("d"),
+            ]
+        )]
+    );
+}
+
+#[test]
+fn test_parse_fragment_implied_end_tags() {
+    assert_eq!(
+        parse_fragment("<ul><li>a<li>b</ul>"),
+        vec![tag(
+            "ul",
+            &[],
+            vec![
+                tag("li", &[], vec![text("a")]),
+                tag("li", &[], vec![text("b")])
+            ]
+        )]
+    );
+}
+
+#[test]
+fn test_parse_fragment_character_references() {
+    assert_eq!(
+        parse_fragment(r#"<a title="&quot;&#65;">&copy;&nbsp;&amp</a>"#),
+        vec![tag(
+            "a",
+            &[("title", "\"A")],
+            vec![text("\u{00A9}\u{00A0}&")]
+        )]
+    );
+}
+
+#[test]
+fn test_parse_fragment_whitespace_text() {
+    let input = "<div>\n  <span>a</span>\n</div>";
+    assert_eq!(
+        parse_fragment(input),
+        vec![tag(
+            "div",
+            &[],
+            vec![text("\n  "), tag("span", &[], vec![text("a")]), text("\n")]
+        )]
+    );
+    assert_eq!(
+        parse_fragment_with_options(
+            input,
+            &ParseOptions {
+                drop_whitespace_text: true
+            }
+        ),
+        vec![tag("div", &[], vec![tag("span", &[], vec![text("a")])])]
+    );
+}
+
+#[test]
+fn test_parse_fragment_foreign_attributes() {
+    assert_eq!(
+        parse_fragment(r##"<svg><use xlink:href="#a"></use></svg>"##),
+        vec![tag(
+            "svg",
+            &[],
+            vec![tag("use", &[("xlink:href", "#a")], vec![])]
+        )]
+    );
+}
+
+#[test]
+fn test_parse_fragment_round_trip() {
+    let elements = parse_fragment(r#"<div id="a">b<span>c</span><p>d<p>e</div>"#);
+    let html = elements
+        .iter()
+        .map(|element| element.to_string())
+        .collect::<String>();
+    assert_eq!(html, r#"<div id="a">b<span>c</span><p>d</p><p>e</p></div>"#);
+    assert_eq!(parse_fragment(&html), elements);
+}
+
+#[test]
+fn test_parse_fragment_round_trip_escaped() {
+    let elements = parse_fragment(
+        r#"<p>a &lt; b<br>&amp;</p><script>if (a < b) {}</script><textarea>&lt;/textarea&gt;</textarea>"#,
+    );
+    let html = elements
+        .iter()
+        .map(|element| element.to_string())
+        .collect::<String>();
+    assert_eq!(parse_fragment(&html), elements);
+}
+
+#[test]
+fn test_to_string_sort_attributes() {
+    let elements = parse_fragment(r#"<a href="h" class="c" id="i"></a>"#);
+    assert_eq!(
+        to_string(&elements[0]),
+        Ok(r#"<a href="h" class="c" id="i"></a>"#.to_string())
+    );
+    assert_eq!(
+        to_string_with_options(
+            &elements[0],
+            &RenderOptions {
+                sort_attributes: true,
+                ..RenderOptions::default()
+            }
+        ),
+        Ok(r#"<a class="c" href="h" id="i"></a>"#.to_string())
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_to_string_attribute_value_style() {
+    let element = Element::Tag(Tag {
+        name: "input".to_string(),
+        attributes: Attributes::from([("disabled".to_string(), AttributeValue::Bool(true))]),
+        element_list: vec![],
+    });
+    assert_eq!(to_string(&element), Ok("<input disabled>".to_string()));
+    assert_eq!(
+        to_string_with_options(
+            &element,
+            &RenderOptions {
+                attribute_value_style: AttributeValueStyle::Json,
+                ..RenderOptions::default()
+            }
+        ),
+        Ok(r#"<input disabled="true">"#.to_string())
+    );
+
+    let element = Element::Tag(Tag {
+        name: "input".to_string(),
+        attributes: Attributes::from([("disabled".to_string(), AttributeValue::Bool(false))]),
+        element_list: vec![],
+    });
+    assert_eq!(to_string(&element), Ok("<input>".to_string()));
+
+    let element = Element::Tag(Tag {
+        name: "input".to_string(),
+        attributes: Attributes::from([("hidden".to_string(), AttributeValue::Null)]),
+        element_list: vec![],
+    });
+    assert_eq!(to_string(&element), Ok("<input hidden>".to_string()));
+    assert_eq!(
+        to_string_with_options(
+            &element,
+            &RenderOptions {
+                attribute_value_style: AttributeValueStyle::Json,
+                ..RenderOptions::default()
+            }
+        ),
+        Ok(r#"<input hidden="null">"#.to_string())
+    );
+}
+
+#[test]
+fn test_format_number() {
+    assert_eq!(format_number(0.1), "0.1");
+    assert_eq!(format_number(1.0), "1");
+    assert_eq!(format_number(-2.5), "-2.5");
+    assert_eq!(format_number(-0.0), "0");
+    assert_eq!(format_number(1e21), "1e+21");
+    assert_eq!(format_number(1.5e-7), "1.5e-7");
+    assert_eq!(format_number(0.000001), "0.000001");
+    assert_eq!(format_number(16777216.0), "16777216");
+    assert_eq!(format_number(f32::NAN), "NaN");
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_write_html_io() {
+    let element = Element::Tag(Tag {
+        name: "p".to_string(),
+        attributes: Attributes::from([("hidden".to_string(), AttributeValue::Null)]),
+        element_list: vec![text("a & b"), tag("br", &[], vec![])],
+    });
+    let mut output = vec![];
+    element
+        .write_html_io(&mut output, &RenderOptions::default())
+        .unwrap();
+    assert_eq!(output, b"<p hidden>a &amp; b<br></p>");
+}
+
+#[test]
+fn test_write_html_io_error() {
+    struct Failing;
+
+    impl io::Write for Failing {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    assert!(matches!(
+        text("a").write_html_io(&mut Failing, &RenderOptions::default()),
+        Err(WriteError::Io(error)) if error.kind() == io::ErrorKind::BrokenPipe
+    ));
+}
+
+#[test]
+fn test_write_html_raw_text_split() {
+    let element = tag("script", &[], vec![text("a</scr"), text("ipt>")]);
+    let mut output = String::new();
+    assert!(matches!(
+        element.write_html(&mut output, &RenderOptions::default()),
+        Err(WriteError::Render(error))
+            if matches!(error.kind(), RenderErrorKind::EndTagInRawText { .. })
+    ));
+    assert_eq!(output, "<script>");
+}
+
+#[test]
+fn test_write_html_deep() {
+    let mut element = text("a");
+    for _ in 0..1000 {
+        element = tag("b", &[], vec![element]);
+    }
+    let mut output = String::new();
+    element
+        .write_html(&mut output, &RenderOptions::default())
+        .unwrap();
+    assert_eq!(output.len(), 1 + 1000 * "<b></b>".len());
+}
+
+#[cfg(test)]
+fn pretty(element: &Element, line_width: usize) -> String {
+    element
+        .try_to_html_with_options(&RenderOptions {
+            layout: Layout::Pretty(PrettyOptions {
+                line_width,
+                ..PrettyOptions::default()
+            }),
+            ..RenderOptions::default()
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_pretty_blocks() {
+    let element = parse_document(
+        "<title>a</title><ul><li>b <a href=x>c</a></li><li><p>d</p><pre> e\n f</pre></li></ul>",
+    );
+    assert_eq!(
+        pretty(&element, 80),
+        r#"<html>
+  <head>
+    <title>a</title>
+  </head>
+  <body>
+    <ul>
+      <li>b <a href="x">c</a></li>
+      <li>
+        <p>d</p>
+        <pre> e
+ f</pre>
+      </li>
+    </ul>
+  </body>
+</html>"#
+    );
+}
+
+#[test]
+fn test_pretty_wrap_inline_content() {
+    let element = tag(
+        "p",
+        &[],
+        vec![
+            text("one two three "),
+            tag("span", &[], vec![text("four five")]),
+            text(" six"),
+            tag("b", &[], vec![text("seven")]),
+        ],
+    );
+    assert_eq!(
+        pretty(&element, 16),
+        "<p>\n  one two three\n  <span>four five</span>\n  six<b>seven</b>\n</p>"
+    );
+    assert_eq!(
+        pretty(&element, 80),
+        "<p>one two three <span>four five</span> six<b>seven</b></p>"
+    );
+}
+
+#[test]
+fn test_pretty_mixed_content() {
+    let element = tag(
+        "div",
+        &[],
+        vec![
+            text("\n  a "),
+            tag("em", &[], vec![text("b")]),
+            tag("div", &[], vec![]),
+            text("\n"),
+        ],
+    );
+    assert_eq!(
+        pretty(&element, 80),
+        "<div>\n  a <em>b</em>\n  <div></div>\n</div>"
+    );
+}
+
+#[test]
+fn test_pretty_error_path() {
+    let element = tag(
+        "div",
+        &[],
+        vec![
+            tag("p", &[], vec![]),
+            tag("p", &[], vec![tag("b-", &[], vec![])]),
+        ],
+    );
+    let error = element
+        .try_to_html_with_options(&RenderOptions {
+            layout: Layout::Pretty(PrettyOptions::default()),
+            ..RenderOptions::default()
+        })
+        .unwrap_err();
+    assert_eq!(error.path(), &[1, 0]);
+}
+
+#[cfg(test)]
+fn minify(element: &Element, options: MinifyOptions) -> String {
+    element
+        .try_to_html_with_options(&RenderOptions {
+            layout: Layout::Minified(options),
+            ..RenderOptions::default()
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_minify() {
+    let element = parse_document(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>a  b</title>
+  </head>
+  <body class="x y">
+    <ul>
+      <li>c</li>
+      <li><a href="d.html">e</a> <b>f</b></li>
+    </ul>
+    <p>g</p>
+    <p>h</p>
+    <pre>
+ i  j</pre>
+    <table><tr><td>k</td><td>l</td></tr></table>
+  </body>
+</html>
+"#,
+    );
+    assert_eq!(
+        minify(
+            &element,
+            MinifyOptions {
+                collapse_whitespace: true,
+                ..MinifyOptions::default()
+            }
+        ),
+        r#"<html><head><title>a b</title><body class="x y"><ul><li>c<li><a href=d.html>e</a> <b>f</b></ul><p>g<p>h<pre> i  j</pre><table><tbody><tr><td>k<td>l</table>"#
+    );
+}
+
+#[test]
+fn test_minify_round_trip() {
+    for input in [
+        "<ul><li>a</li> <li>b</li></ul><dl><dt>c<dd>d</dl>",
+        "<p>a</p><div>b</div><a href=x><p>c</p></a><p>d</p>text",
+        "<select><optgroup label=a><option>b<option selected>c</optgroup></select>",
+        "<table><caption>a</caption><colgroup><col></colgroup><thead><tr><th>b</thead><tbody><tr><td>c</tbody><tfoot><tr><td>d</table>",
+        "<ruby>a<rp>(<rt>b<rp>)</ruby><p title='\"&amp;'>x<span> y </span>",
+        "<span>a  b</span><p>c</p> <p>d</p>\n<p>e</p>",
+        "<pre>\n\nx</pre><textarea>\n\ny</textarea><listing>\n\nz</listing><pre>\nw</pre>",
+    ] {
+        let element = tag("div", &[], parse_fragment(input));
+        for options in [
+            MinifyOptions::default(),
+            MinifyOptions {
+                omit_optional_end_tags: false,
+                ..MinifyOptions::default()
+            },
+        ] {
+            let minified = minify(&element, options);
+            assert_eq!(parse_fragment(&minified), vec![element.clone()], "{minified}");
+        }
+    }
+
+    let element = tag(
+        "div",
+        &[],
+        parse_fragment("<p>a</p><table><tbody><tr><td>b</td></tr></tbody></table>"),
+    );
+    let minified = minify(&element, MinifyOptions::default());
+    assert_eq!(
+        minified,
+        "<div><p>a</p><table><tbody><tr><td>b</table></div>"
+    );
+    assert_eq!(
+        parse_document(&minified),
+        tag(
+            "html",
+            &[],
+            vec![tag("head", &[], vec![]), tag("body", &[], vec![element])]
+        )
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_minify_attribute_values() {
+    let element = Element::Tag(Tag {
+        name: "input".to_string(),
+        attributes: Attributes::from([(
+            "value".to_string(),
+            AttributeValue::String("a b".to_string()),
+        )]),
+        element_list: vec![],
+    });
+    assert_eq!(
+        minify(&element, MinifyOptions::default()),
+        r#"<input value="a b">"#
+    );
+    let element = Element::Tag(Tag {
+        name: "input".to_string(),
+        attributes: Attributes::from([("size".to_string(), AttributeValue::Number(1.5))]),
+        element_list: vec![],
+    });
+    assert_eq!(
+        minify(&element, MinifyOptions::default()),
+        "<input size=1.5>"
+    );
+}
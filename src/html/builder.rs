@@ -0,0 +1,597 @@
+//! Typed builders of HTML elements
+//!
+//! Each element has a function starting its builder, such as [`a`] for [`elements::A`].
+//! A builder has methods for the [global attributes](https://html.spec.whatwg.org/multipage/dom.html#global-attributes)
+//! and the attributes of its element, so misspelled or misplaced attributes fail to compile.
+//! Other attributes are set with `attribute`, `data_attribute` or `aria`.
+//! Void elements such as `img` have no methods adding children.
+//!
+//! Builders convert into [`Element`] and [`Tag`].
+//!
+//! ```
+//! use jsonml::{html, Element};
+//!
+//! let element: Element = html::ul()
+//!     .class("menu")
+//!     .child(html::li().child(html::a().href("/").child("Home")))
+//!     .children(["About", "Contact"].map(|item| html::li().child(item)))
+//!     .into();
+//! assert_eq!(
+//!     element,
+//!     serde_json::from_str::<Element>(
+//!         r#"["ul", {"class": "menu"},
+//!             ["li", ["a", {"href": "/"}, "Home"]],
+//!             ["li", "About"],
+//!             ["li", "Contact"]]"#
+//!     )
+//!     .unwrap()
+//! );
+//! ```
+//!
+//! ```compile_fail
+//! jsonml::html::a().herf("/");
+//! ```
+
+use crate::{Attributes, Tag};
+
+macro_rules! setter {
+    ($method:ident $attribute:literal String) => {
+        #[doc = concat!("Set the `", $attribute, "` attribute.")]
+        pub fn $method(self, value: impl Into<String>) -> Self {
+            self.set($attribute, AttributeValue::String(value.into()))
+        }
+    };
+    ($method:ident $attribute:literal bool) => {
+        #[doc = concat!("Set the boolean `", $attribute, "` attribute, omitted in HTML if `false`.")]
+        pub fn $method(self, value: bool) -> Self {
+            self.set($attribute, AttributeValue::Bool(value))
+        }
+    };
+    ($method:ident $attribute:literal $number:ident) => {
+        #[doc = concat!("Set the `", $attribute, "` attribute.")]
+        pub fn $method(self, value: $number) -> Self {
+            self.set($attribute, AttributeValue::Number(value as f32))
+        }
+    };
+}
+
+macro_rules! children {
+    () => {
+        /// Append a child.
+        #[allow(deprecated)]
+        pub fn child(mut self, child: impl Into<Element>) -> Self {
+            self.tag.element_list.push(child.into());
+            self
+        }
+
+        /// Append children.
+        #[allow(deprecated)]
+        pub fn children<I>(mut self, children: I) -> Self
+        where
+            I: IntoIterator,
+            I::Item: Into<Element>,
+        {
+            self.tag
+                .element_list
+                .extend(children.into_iter().map(Into::into));
+            self
+        }
+    };
+    (void) => {};
+}
+
+macro_rules! elements {
+    ($(
+        $function:ident $Type:ident $name:literal $($void:ident)? {
+            $($method:ident $attribute:literal $kind:ident),* $(,)?
+        }
+    )*) => {
+        /// Builders of HTML elements, started by the functions in [`html`](crate::html)
+        pub mod elements {
+            use crate::{AttributeValue, Element, Tag};
+
+            $(
+                #[doc = concat!("Builder of the `", $name, "` element")]
+                #[derive(Debug, Clone, PartialEq)]
+                pub struct $Type {
+                    pub(super) tag: Tag,
+                }
+
+                impl $Type {
+                    #[allow(deprecated)]
+                    fn set(mut self, name: &str, value: AttributeValue) -> Self {
+                        self.tag.attributes.insert(name.to_string(), value);
+                        self
+                    }
+
+                    /// Set an attribute which has no method.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if the name cannot be rendered, as checked by
+                    /// [`Tag::set_attribute`].
+                    pub fn attribute(
+                        mut self,
+                        name: impl Into<String>,
+                        value: impl Into<AttributeValue>,
+                    ) -> Self {
+                        if let Err(error) = self.tag.set_attribute(name, value) {
+                            panic!("{}", error);
+                        }
+                        self
+                    }
+
+                    /// Set the `data-*` attribute with the name following `data-`.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if the name cannot be rendered, as with `attribute`.
+                    pub fn data_attribute(self, name: &str, value: impl Into<String>) -> Self {
+                        self.attribute(format!("data-{name}"), AttributeValue::String(value.into()))
+                    }
+
+                    /// Set the `aria-*` attribute with the name following `aria-`.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if the name cannot be rendered, as with `attribute`.
+                    pub fn aria(self, name: &str, value: impl Into<String>) -> Self {
+                        self.attribute(format!("aria-{name}"), AttributeValue::String(value.into()))
+                    }
+
+                    setter!(accesskey "accesskey" String);
+                    setter!(autocapitalize "autocapitalize" String);
+                    setter!(class "class" String);
+                    setter!(contenteditable "contenteditable" String);
+                    setter!(dir "dir" String);
+                    setter!(draggable "draggable" String);
+                    setter!(hidden "hidden" bool);
+                    setter!(id "id" String);
+                    setter!(inert "inert" bool);
+                    setter!(lang "lang" String);
+                    setter!(role "role" String);
+                    setter!(spellcheck "spellcheck" String);
+                    setter!(style "style" String);
+                    setter!(tabindex "tabindex" i32);
+                    setter!(title "title" String);
+                    setter!(translate "translate" String);
+                    $(setter!($method $attribute $kind);)*
+                    children!($($void)?);
+                }
+
+                impl From<$Type> for Tag {
+                    fn from(builder: $Type) -> Self {
+                        builder.tag
+                    }
+                }
+
+                impl From<$Type> for Element {
+                    fn from(builder: $Type) -> Self {
+                        Element::Tag(builder.tag)
+                    }
+                }
+            )*
+        }
+
+        $(
+            #[doc = concat!("Start building a `", $name, "` element.")]
+            #[allow(deprecated)]
+            pub fn $function() -> elements::$Type {
+                elements::$Type {
+                    tag: Tag {
+                        name: $name.to_string(),
+                        attributes: Attributes::new(),
+                        element_list: Vec::new(),
+                    },
+                }
+            }
+        )*
+    };
+}
+
+// https://html.spec.whatwg.org/multipage/indices.html#elements-3
+elements! {
+    // Document metadata
+    html Html "html" {}
+    head Head "head" {}
+    title Title "title" {}
+    base Base "base" void { href "href" String, target "target" String }
+    link Link "link" void {
+        href "href" String,
+        rel "rel" String,
+        r#as "as" String,
+        r#type "type" String,
+        media "media" String,
+        hreflang "hreflang" String,
+        sizes "sizes" String,
+        crossorigin "crossorigin" String,
+        integrity "integrity" String,
+        referrerpolicy "referrerpolicy" String,
+    }
+    meta Meta "meta" void {
+        name "name" String,
+        content "content" String,
+        charset "charset" String,
+        http_equiv "http-equiv" String,
+        media "media" String,
+    }
+    style Style "style" { media "media" String }
+    body Body "body" {}
+
+    // Sections
+    article Article "article" {}
+    section Section "section" {}
+    nav Nav "nav" {}
+    aside Aside "aside" {}
+    h1 H1 "h1" {}
+    h2 H2 "h2" {}
+    h3 H3 "h3" {}
+    h4 H4 "h4" {}
+    h5 H5 "h5" {}
+    h6 H6 "h6" {}
+    hgroup Hgroup "hgroup" {}
+    header Header "header" {}
+    footer Footer "footer" {}
+    address Address "address" {}
+
+    // Grouping content
+    p P "p" {}
+    hr Hr "hr" void {}
+    pre Pre "pre" {}
+    blockquote Blockquote "blockquote" { cite "cite" String }
+    ol Ol "ol" { reversed "reversed" bool, start "start" i32, r#type "type" String }
+    ul Ul "ul" {}
+    menu Menu "menu" {}
+    li Li "li" { value "value" i32 }
+    dl Dl "dl" {}
+    dt Dt "dt" {}
+    dd Dd "dd" {}
+    figure Figure "figure" {}
+    figcaption Figcaption "figcaption" {}
+    main Main "main" {}
+    search Search "search" {}
+    div Div "div" {}
+
+    // Text-level semantics
+    a A "a" {
+        href "href" String,
+        target "target" String,
+        download "download" String,
+        ping "ping" String,
+        rel "rel" String,
+        hreflang "hreflang" String,
+        r#type "type" String,
+        referrerpolicy "referrerpolicy" String,
+    }
+    em Em "em" {}
+    strong Strong "strong" {}
+    small Small "small" {}
+    s S "s" {}
+    cite Cite "cite" {}
+    q Q "q" { cite "cite" String }
+    dfn Dfn "dfn" {}
+    abbr Abbr "abbr" {}
+    ruby Ruby "ruby" {}
+    rt Rt "rt" {}
+    rp Rp "rp" {}
+    data Data "data" { value "value" String }
+    time Time "time" { datetime "datetime" String }
+    code Code "code" {}
+    var Var "var" {}
+    samp Samp "samp" {}
+    kbd Kbd "kbd" {}
+    sub Sub "sub" {}
+    sup Sup "sup" {}
+    i I "i" {}
+    b B "b" {}
+    u U "u" {}
+    mark Mark "mark" {}
+    bdi Bdi "bdi" {}
+    bdo Bdo "bdo" {}
+    span Span "span" {}
+    br Br "br" void {}
+    wbr Wbr "wbr" void {}
+
+    // Edits
+    ins Ins "ins" { cite "cite" String, datetime "datetime" String }
+    del Del "del" { cite "cite" String, datetime "datetime" String }
+
+    // Embedded content
+    picture Picture "picture" {}
+    source Source "source" void {
+        src "src" String,
+        srcset "srcset" String,
+        sizes "sizes" String,
+        r#type "type" String,
+        media "media" String,
+        width "width" u32,
+        height "height" u32,
+    }
+    img Img "img" void {
+        alt "alt" String,
+        src "src" String,
+        srcset "srcset" String,
+        sizes "sizes" String,
+        crossorigin "crossorigin" String,
+        usemap "usemap" String,
+        ismap "ismap" bool,
+        width "width" u32,
+        height "height" u32,
+        referrerpolicy "referrerpolicy" String,
+        decoding "decoding" String,
+        loading "loading" String,
+        fetchpriority "fetchpriority" String,
+    }
+    iframe Iframe "iframe" {
+        src "src" String,
+        srcdoc "srcdoc" String,
+        name "name" String,
+        sandbox "sandbox" String,
+        allow "allow" String,
+        allowfullscreen "allowfullscreen" bool,
+        width "width" u32,
+        height "height" u32,
+        referrerpolicy "referrerpolicy" String,
+        loading "loading" String,
+    }
+    embed Embed "embed" void {
+        src "src" String,
+        r#type "type" String,
+        width "width" u32,
+        height "height" u32,
+    }
+    object Object "object" {
+        data "data" String,
+        r#type "type" String,
+        name "name" String,
+        form "form" String,
+        width "width" u32,
+        height "height" u32,
+    }
+    video Video "video" {
+        src "src" String,
+        crossorigin "crossorigin" String,
+        poster "poster" String,
+        preload "preload" String,
+        autoplay "autoplay" bool,
+        playsinline "playsinline" bool,
+        r#loop "loop" bool,
+        muted "muted" bool,
+        controls "controls" bool,
+        width "width" u32,
+        height "height" u32,
+    }
+    audio Audio "audio" {
+        src "src" String,
+        crossorigin "crossorigin" String,
+        preload "preload" String,
+        autoplay "autoplay" bool,
+        r#loop "loop" bool,
+        muted "muted" bool,
+        controls "controls" bool,
+    }
+    track Track "track" void {
+        kind "kind" String,
+        src "src" String,
+        srclang "srclang" String,
+        label "label" String,
+        default "default" bool,
+    }
+    map Map "map" { name "name" String }
+    area Area "area" void {
+        alt "alt" String,
+        coords "coords" String,
+        shape "shape" String,
+        href "href" String,
+        target "target" String,
+        download "download" String,
+        ping "ping" String,
+        rel "rel" String,
+        referrerpolicy "referrerpolicy" String,
+    }
+
+    // Tabular data
+    table Table "table" {}
+    caption Caption "caption" {}
+    colgroup Colgroup "colgroup" { span "span" u32 }
+    col Col "col" void { span "span" u32 }
+    tbody Tbody "tbody" {}
+    thead Thead "thead" {}
+    tfoot Tfoot "tfoot" {}
+    tr Tr "tr" {}
+    td Td "td" { colspan "colspan" u32, rowspan "rowspan" u32, headers "headers" String }
+    th Th "th" {
+        colspan "colspan" u32,
+        rowspan "rowspan" u32,
+        headers "headers" String,
+        scope "scope" String,
+        abbr "abbr" String,
+    }
+
+    // Forms
+    form Form "form" {
+        accept_charset "accept-charset" String,
+        action "action" String,
+        autocomplete "autocomplete" String,
+        enctype "enctype" String,
+        method "method" String,
+        name "name" String,
+        novalidate "novalidate" bool,
+        rel "rel" String,
+        target "target" String,
+    }
+    label Label "label" { r#for "for" String }
+    input Input "input" void {
+        accept "accept" String,
+        alt "alt" String,
+        autocomplete "autocomplete" String,
+        checked "checked" bool,
+        dirname "dirname" String,
+        disabled "disabled" bool,
+        form "form" String,
+        formaction "formaction" String,
+        formenctype "formenctype" String,
+        formmethod "formmethod" String,
+        formnovalidate "formnovalidate" bool,
+        formtarget "formtarget" String,
+        height "height" u32,
+        list "list" String,
+        max "max" String,
+        maxlength "maxlength" u32,
+        min "min" String,
+        minlength "minlength" u32,
+        multiple "multiple" bool,
+        name "name" String,
+        pattern "pattern" String,
+        placeholder "placeholder" String,
+        readonly "readonly" bool,
+        required "required" bool,
+        size "size" u32,
+        src "src" String,
+        step "step" String,
+        r#type "type" String,
+        value "value" String,
+        width "width" u32,
+    }
+    button Button "button" {
+        disabled "disabled" bool,
+        form "form" String,
+        formaction "formaction" String,
+        formenctype "formenctype" String,
+        formmethod "formmethod" String,
+        formnovalidate "formnovalidate" bool,
+        formtarget "formtarget" String,
+        name "name" String,
+        popovertarget "popovertarget" String,
+        r#type "type" String,
+        value "value" String,
+    }
+    select Select "select" {
+        autocomplete "autocomplete" String,
+        disabled "disabled" bool,
+        form "form" String,
+        multiple "multiple" bool,
+        name "name" String,
+        required "required" bool,
+        size "size" u32,
+    }
+    datalist Datalist "datalist" {}
+    optgroup Optgroup "optgroup" { disabled "disabled" bool, label "label" String }
+    option Option "option" {
+        disabled "disabled" bool,
+        label "label" String,
+        selected "selected" bool,
+        value "value" String,
+    }
+    textarea Textarea "textarea" {
+        autocomplete "autocomplete" String,
+        cols "cols" u32,
+        dirname "dirname" String,
+        disabled "disabled" bool,
+        form "form" String,
+        maxlength "maxlength" u32,
+        minlength "minlength" u32,
+        name "name" String,
+        placeholder "placeholder" String,
+        readonly "readonly" bool,
+        required "required" bool,
+        rows "rows" u32,
+        wrap "wrap" String,
+    }
+    output Output "output" { r#for "for" String, form "form" String, name "name" String }
+    progress Progress "progress" { value "value" f32, max "max" f32 }
+    meter Meter "meter" {
+        value "value" f32,
+        min "min" f32,
+        max "max" f32,
+        low "low" f32,
+        high "high" f32,
+        optimum "optimum" f32,
+    }
+    fieldset Fieldset "fieldset" { disabled "disabled" bool, form "form" String, name "name" String }
+    legend Legend "legend" {}
+
+    // Interactive elements
+    details Details "details" { name "name" String, open "open" bool }
+    summary Summary "summary" {}
+    dialog Dialog "dialog" { open "open" bool }
+
+    // Scripting
+    script Script "script" {
+        src "src" String,
+        r#type "type" String,
+        nomodule "nomodule" bool,
+        r#async "async" bool,
+        defer "defer" bool,
+        crossorigin "crossorigin" String,
+        integrity "integrity" String,
+        referrerpolicy "referrerpolicy" String,
+    }
+    noscript Noscript "noscript" {}
+    template Template "template" {}
+    slot Slot "slot" { name "name" String }
+    canvas Canvas "canvas" { width "width" u32, height "height" u32 }
+}
+
+#[cfg(test)]
+use crate::{AttributeValue, Element};
+
+#[test]
+#[should_panic(expected = "invalid attribute name \"a b\"")]
+fn test_builder_invalid_attribute_name() {
+    p().attribute("a b", "x");
+}
+
+#[test]
+#[should_panic(expected = "invalid attribute name \"data-a=b\"")]
+fn test_builder_invalid_data_attribute_name() {
+    div().data_attribute("a=b", "x");
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_builder() {
+    let tag: Tag = input()
+        .r#type("checkbox")
+        .checked(true)
+        .maxlength(3)
+        .data_attribute("id", "7")
+        .aria("label", "Agree")
+        .attribute("onclick", "go()")
+        .into();
+    assert_eq!(
+        tag,
+        Tag {
+            name: "input".to_string(),
+            attributes: Attributes::from([
+                (
+                    "type".to_string(),
+                    AttributeValue::String("checkbox".to_string())
+                ),
+                ("checked".to_string(), AttributeValue::Bool(true)),
+                ("maxlength".to_string(), AttributeValue::Number(3.0)),
+                (
+                    "data-id".to_string(),
+                    AttributeValue::String("7".to_string())
+                ),
+                (
+                    "aria-label".to_string(),
+                    AttributeValue::String("Agree".to_string())
+                ),
+                (
+                    "onclick".to_string(),
+                    AttributeValue::String("go()".to_string())
+                ),
+            ]),
+            element_list: vec![],
+        }
+    );
+    assert_eq!(
+        Element::from(meta().http_equiv("refresh")).to_string(),
+        "<meta http-equiv=\"refresh\">"
+    );
+    assert_eq!(
+        Element::from(p().hidden(false).child("a").child(br()).child("b")).to_string(),
+        "<p>a<br>b</p>"
+    );
+}
@@ -0,0 +1,270 @@
+//! Minifying HTML renderer
+//!
+//! End tags are omitted where the HTML Standard allows
+//! ([optional tags](https://html.spec.whatwg.org/multipage/syntax.html#optional-tags)),
+//! and attribute values are unquoted where that is shorter, so that the output parses back into
+//! the same tree as the rendered element.
+//! Whitespace which does not affect rendering may also be collapsed, which changes the tree.
+
+use std::{borrow::Cow, fmt};
+
+use html_escape::encode_text;
+
+use super::{
+    is_html_whitespace, is_inline_element, is_raw_text_element, is_void_element,
+    is_whitespace_sensitive_element, MinifyOptions, RenderOptions, WriteError,
+};
+use crate::{Element, Tag};
+
+pub(crate) fn write<W>(
+    element: &Element,
+    writer: &mut W,
+    options: &RenderOptions,
+    minify: &MinifyOptions,
+) -> Result<(), WriteError>
+where
+    W: fmt::Write + ?Sized,
+{
+    let mut minifier = Minifier {
+        writer,
+        options,
+        minify,
+        path: vec![],
+    };
+    match element {
+        Element::Tag(tag) => minifier.write_tag(tag, None, None, false),
+        Element::String(s) => {
+            let text = if minify.collapse_whitespace {
+                collapse_whitespace(s)
+            } else {
+                Cow::Borrowed(s.as_str())
+            };
+            Ok(minifier.writer.write_str(&encode_text(&text))?)
+        }
+    }
+}
+
+/// Child which is actually written: an element with its index, or text after collapsing
+/// whitespace.
+enum Item<'a> {
+    Element(usize, &'a Element),
+    Text(Cow<'a, str>),
+}
+
+struct Minifier<'a, W: ?Sized> {
+    writer: &'a mut W,
+    options: &'a RenderOptions,
+    minify: &'a MinifyOptions,
+    path: Vec<usize>,
+}
+
+impl<W: fmt::Write + ?Sized> Minifier<'_, W> {
+    /// `sensitive` is whether an ancestor preserves whitespace.
+    #[allow(deprecated)]
+    fn write_tag(
+        &mut self,
+        tag: &Tag,
+        parent: Option<&str>,
+        next: Option<&Item>,
+        sensitive: bool,
+    ) -> Result<(), WriteError> {
+        tag.write_html_start_tag(self.writer, self.options, &self.path)?;
+        if is_void_element(&tag.name) {
+            return Ok(());
+        }
+        // The parser drops a newline right after these start tags, so keep a leading one.
+        if ["pre", "textarea", "listing"]
+            .iter()
+            .any(|name| tag.name.eq_ignore_ascii_case(name))
+            && matches!(tag.element_list.first(), Some(Element::String(s)) if s.starts_with('\n'))
+        {
+            self.writer.write_char('\n')?;
+        }
+        if is_raw_text_element(&tag.name) {
+            tag.write_html_content(self.writer, self.options, &mut self.path)?;
+        } else {
+            let sensitive = sensitive || is_whitespace_sensitive_element(&tag.name);
+            let items = self.items(tag, sensitive);
+            for (index, item) in items.iter().enumerate() {
+                match item {
+                    Item::Element(child_index, Element::Tag(child)) => {
+                        self.path.push(*child_index);
+                        self.write_tag(child, Some(&tag.name), items.get(index + 1), sensitive)?;
+                        self.path.pop();
+                    }
+                    Item::Element(_, Element::String(s)) => {
+                        self.writer.write_str(&encode_text(s))?;
+                    }
+                    Item::Text(text) => self.writer.write_str(&encode_text(text))?,
+                }
+            }
+        }
+        if !(self.minify.omit_optional_end_tags && can_omit_end_tag(&tag.name, parent, next)) {
+            tag.write_html_end_tag(self.writer)?;
+        }
+        Ok(())
+    }
+
+    #[allow(deprecated)]
+    fn items<'e>(&self, tag: &'e Tag, sensitive: bool) -> Vec<Item<'e>> {
+        let element_list = &tag.element_list;
+        if !self.minify.collapse_whitespace || sensitive {
+            return element_list
+                .iter()
+                .enumerate()
+                .map(|(index, element)| Item::Element(index, element))
+                .collect();
+        }
+        let is_block = |element: Option<&Element>| match element {
+            Some(Element::Tag(tag)) => !is_inline_element(&tag.name),
+            Some(Element::String(_)) => false,
+            None => !is_inline_element(&tag.name),
+        };
+        let mut items = vec![];
+        let mut index = 0;
+        while index < element_list.len() {
+            if let Element::Tag(_) = element_list[index] {
+                items.push(Item::Element(index, &element_list[index]));
+                index += 1;
+                continue;
+            }
+            let len = element_list[index..]
+                .iter()
+                .take_while(|element| matches!(element, Element::String(_)))
+                .count();
+            let text: String = element_list[index..index + len]
+                .iter()
+                .filter_map(|element| match element {
+                    Element::String(s) => Some(s.as_str()),
+                    Element::Tag(_) => None,
+                })
+                .collect();
+            let mut text = collapse_whitespace(&text).into_owned();
+            if is_block(index.checked_sub(1).map(|previous| &element_list[previous])) {
+                text = text.trim_start_matches(' ').to_string();
+            }
+            if is_block(element_list.get(index + len)) {
+                text.truncate(text.trim_end_matches(' ').len());
+            }
+            if !text.is_empty() {
+                items.push(Item::Text(Cow::Owned(text)));
+            }
+            index += len;
+        }
+        items
+    }
+}
+
+/// Replace each run of whitespace with a space.
+fn collapse_whitespace(text: &str) -> Cow<'_, str> {
+    let mut collapsed = String::new();
+    let mut last_end = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if !is_html_whitespace(c) {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(next, c)) = chars.peek() {
+            if !is_html_whitespace(c) {
+                break;
+            }
+            end = next + c.len_utf8();
+            chars.next();
+        }
+        if &text[start..end] != " " {
+            collapsed.push_str(&text[last_end..start]);
+            collapsed.push(' ');
+            last_end = end;
+        }
+    }
+    if last_end == 0 {
+        Cow::Borrowed(text)
+    } else {
+        collapsed.push_str(&text[last_end..]);
+        Cow::Owned(collapsed)
+    }
+}
+
+// https://html.spec.whatwg.org/multipage/syntax.html#optional-tags
+#[allow(deprecated)]
+fn can_omit_end_tag(name: &str, parent: Option<&str>, next: Option<&Item>) -> bool {
+    let next_name = match next {
+        Some(Item::Element(_, Element::Tag(tag))) => Some(tag.name.to_ascii_lowercase()),
+        _ => None,
+    };
+    let next_is =
+        |names: &[&str]| matches!(&next_name, Some(next) if names.contains(&next.as_str()));
+    let next_is_whitespace = match next {
+        Some(Item::Text(text)) => text.starts_with(is_html_whitespace),
+        Some(Item::Element(_, Element::String(s))) => s.starts_with(is_html_whitespace),
+        _ => false,
+    };
+    let last = next.is_none();
+    match name.to_ascii_lowercase().as_str() {
+        "html" | "body" => true,
+        "head" | "colgroup" | "caption" => !next_is_whitespace,
+        "li" => last || next_is(&["li"]),
+        "dt" => next_is(&["dt", "dd"]),
+        "dd" => last || next_is(&["dd", "dt"]),
+        "rt" | "rp" => last || next_is(&["rt", "rp"]),
+        "optgroup" => last || next_is(&["optgroup", "hr"]),
+        "option" => last || next_is(&["option", "optgroup", "hr"]),
+        "thead" => next_is(&["tbody", "tfoot"]),
+        "tbody" => last || next_is(&["tbody", "tfoot"]),
+        "tfoot" => last,
+        "tr" => last || next_is(&["tr"]),
+        "td" | "th" => last || next_is(&["td", "th"]),
+        "p" => {
+            next_is(&[
+                "address",
+                "article",
+                "aside",
+                "blockquote",
+                "details",
+                "dialog",
+                "div",
+                "dl",
+                "fieldset",
+                "figcaption",
+                "figure",
+                "footer",
+                "form",
+                "h1",
+                "h2",
+                "h3",
+                "h4",
+                "h5",
+                "h6",
+                "header",
+                "hgroup",
+                "hr",
+                "main",
+                "menu",
+                "nav",
+                "ol",
+                "p",
+                "pre",
+                "search",
+                "section",
+                // Without a doctype the parser is in quirks mode, where
+                // `<table>` does not close an open `<p>`.
+                "ul",
+            ]) || (last
+                && !matches!(parent, Some(parent) if
+                    ["a", "audio", "del", "ins", "map", "noscript", "video"]
+                        .iter()
+                        .any(|name| parent.eq_ignore_ascii_case(name))
+                    // Autonomous custom elements have a hyphen in their names.
+                    || parent.contains('-')))
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn test_collapse_whitespace() {
+    assert_eq!(collapse_whitespace("a b"), Cow::Borrowed("a b"));
+    assert_eq!(collapse_whitespace(" a \n\t b  "), " a b ");
+    assert_eq!(collapse_whitespace("\n"), " ");
+}
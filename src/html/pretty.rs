@@ -0,0 +1,234 @@
+//! Pretty-printing HTML renderer
+//!
+//! Block elements are put on their own lines and indented by depth.
+//! Content in an inline formatting context is kept on one line when it fits in the line width,
+//! and otherwise wrapped at the whitespace already in its text.
+//! No whitespace is inserted inside inline elements such as `span` and `a`, nor inside
+//! whitespace-sensitive elements such as `pre` and `textarea`.
+//!
+//! Whitespace in text of a block element may be collapsed or moved to line breaks, which keeps
+//! the rendering unless the element is styled with the CSS `white-space` property.
+
+use std::fmt;
+
+use html_escape::encode_text;
+
+use super::{
+    is_html_whitespace, is_inline_element, is_whitespace_sensitive_element, PrettyOptions,
+    RenderOptions, WriteError,
+};
+use crate::{Element, Tag};
+
+#[allow(deprecated)]
+pub(crate) fn write<W>(
+    element: &Element,
+    writer: &mut W,
+    options: &RenderOptions,
+    pretty: &PrettyOptions,
+) -> Result<(), WriteError>
+where
+    W: fmt::Write + ?Sized,
+{
+    let mut printer = Printer {
+        output: Column {
+            inner: writer,
+            column: 0,
+        },
+        options,
+        pretty,
+        path: vec![],
+    };
+    match element {
+        Element::Tag(tag) if !is_inline_element(&tag.name) => printer.write_block(element, tag, 0),
+        _ => element.write_html_at(&mut printer.output, options, &mut printer.path),
+    }
+}
+
+/// Writer which keeps track of the column of the cursor.
+struct Column<'a, W: ?Sized> {
+    inner: &'a mut W,
+    column: usize,
+}
+
+impl<W: fmt::Write + ?Sized> fmt::Write for Column<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match s.rfind('\n') {
+            Some(index) => self.column = s[index + 1..].chars().count(),
+            None => self.column += s.chars().count(),
+        }
+        self.inner.write_str(s)
+    }
+}
+
+/// Writer which only counts characters, failing on exceeding the limit or on a line break.
+struct Measure {
+    width: usize,
+    limit: usize,
+}
+
+impl fmt::Write for Measure {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.contains('\n') {
+            return Err(fmt::Error);
+        }
+        self.width += s.chars().count();
+        if self.width > self.limit {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+struct Printer<'a, W: ?Sized> {
+    output: Column<'a, W>,
+    options: &'a RenderOptions,
+    pretty: &'a PrettyOptions,
+    path: Vec<usize>,
+}
+
+impl<W: fmt::Write + ?Sized> Printer<'_, W> {
+    fn newline(&mut self, depth: usize) -> fmt::Result {
+        fmt::Write::write_char(&mut self.output, '\n')?;
+        for _ in 0..depth {
+            fmt::Write::write_str(&mut self.output, &self.pretty.indent)?;
+        }
+        Ok(())
+    }
+
+    /// Width of the element written in one line, if it is not wider than `limit`
+    fn measure(&mut self, element: &Element, limit: usize) -> Result<Option<usize>, WriteError> {
+        let mut measure = Measure { width: 0, limit };
+        let path_len = self.path.len();
+        let result = element.write_html_at(&mut measure, self.options, &mut self.path);
+        self.path.truncate(path_len);
+        match result {
+            Ok(()) => Ok(Some(measure.width)),
+            Err(WriteError::Fmt(_)) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    #[allow(deprecated)]
+    fn write_block(
+        &mut self,
+        element: &Element,
+        tag: &Tag,
+        depth: usize,
+    ) -> Result<(), WriteError> {
+        let all_inline = tag.element_list.iter().all(is_inline);
+        let limit = self.pretty.line_width.saturating_sub(self.output.column);
+        if tag.element_list.is_empty()
+            || is_whitespace_sensitive_element(&tag.name)
+            || (all_inline && self.measure(element, limit)?.is_some())
+        {
+            return element.write_html_at(&mut self.output, self.options, &mut self.path);
+        }
+
+        tag.write_html_start_tag(&mut self.output, self.options, &self.path)?;
+        if super::is_void_element(&tag.name) {
+            return Ok(());
+        }
+        let mut index = 0;
+        while index < tag.element_list.len() {
+            let child = &tag.element_list[index];
+            match child {
+                Element::Tag(child_tag) if !is_inline(child) => {
+                    self.newline(depth + 1)?;
+                    self.path.push(index);
+                    self.write_block(child, child_tag, depth + 1)?;
+                    self.path.pop();
+                    index += 1;
+                }
+                _ => {
+                    let len = tag.element_list[index..]
+                        .iter()
+                        .take_while(|child| is_inline(child))
+                        .count();
+                    let run = &tag.element_list[index..index + len];
+                    if !run.iter().all(is_whitespace_text) {
+                        self.newline(depth + 1)?;
+                        self.write_inline_run(run, index, depth + 1)?;
+                    }
+                    index += len;
+                }
+            }
+        }
+        self.newline(depth)?;
+        tag.write_html_end_tag(&mut self.output)?;
+        Ok(())
+    }
+
+    /// Write inline content, breaking lines at whitespace between words and elements.
+    fn write_inline_run(
+        &mut self,
+        run: &[Element],
+        offset: usize,
+        depth: usize,
+    ) -> Result<(), WriteError> {
+        let mut pending_space = false;
+        let mut line_start = true;
+        for (index, element) in run.iter().enumerate() {
+            match element {
+                Element::String(s) => {
+                    pending_space |= s.starts_with(is_html_whitespace);
+                    for (i, word) in s
+                        .split(is_html_whitespace)
+                        .filter(|word| !word.is_empty())
+                        .enumerate()
+                    {
+                        pending_space |= i > 0;
+                        let word = encode_text(word);
+                        self.break_or_space(
+                            &mut pending_space,
+                            line_start,
+                            word.chars().count(),
+                            depth,
+                        )?;
+                        fmt::Write::write_str(&mut self.output, &word)?;
+                        line_start = false;
+                    }
+                    pending_space |= s.ends_with(is_html_whitespace);
+                }
+                Element::Tag(_) => {
+                    self.path.push(offset + index);
+                    let width = self.measure(element, usize::MAX)?.unwrap_or(usize::MAX);
+                    self.break_or_space(&mut pending_space, line_start, width, depth)?;
+                    element.write_html_at(&mut self.output, self.options, &mut self.path)?;
+                    self.path.pop();
+                    line_start = false;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn break_or_space(
+        &mut self,
+        pending_space: &mut bool,
+        line_start: bool,
+        width: usize,
+        depth: usize,
+    ) -> fmt::Result {
+        if *pending_space && !line_start {
+            if self.output.column.saturating_add(1).saturating_add(width) > self.pretty.line_width {
+                self.newline(depth)?;
+            } else {
+                fmt::Write::write_char(&mut self.output, ' ')?;
+            }
+        }
+        *pending_space = false;
+        Ok(())
+    }
+}
+
+#[allow(deprecated)]
+fn is_inline(element: &Element) -> bool {
+    match element {
+        Element::Tag(tag) => is_inline_element(&tag.name),
+        Element::String(_) => true,
+    }
+}
+
+fn is_whitespace_text(element: &Element) -> bool {
+    matches!(element, Element::String(s) if s.chars().all(is_html_whitespace))
+}
@@ -0,0 +1,601 @@
+//! Iterators over elements and their descendants
+//!
+//! Every iterator starts at the element it was created from and implements [`Traverse`], which
+//! reports the depth and the index path of the last yielded element.
+
+use std::{collections::VecDeque, iter::Enumerate, slice};
+
+use crate::{AttributeValue, Attributes, Element, InvalidNameError, Tag};
+
+/// Iterator which knows the position of the last yielded element
+pub trait Traverse: Iterator + Sized {
+    /// Indices of the children leading from the root to the last yielded element
+    fn path(&self) -> &[usize];
+
+    /// Depth of the last yielded element, 0 for the root
+    fn depth(&self) -> usize {
+        self.path().len()
+    }
+
+    /// Yield the depth with each item.
+    fn with_depth(self) -> WithDepth<Self> {
+        WithDepth { inner: self }
+    }
+
+    /// Yield the index path with each item.
+    fn with_path(self) -> WithPath<Self> {
+        WithPath { inner: self }
+    }
+}
+
+/// Iterator yielding items with their depth, created by [`Traverse::with_depth`]
+#[derive(Debug, Clone)]
+pub struct WithDepth<I> {
+    inner: I,
+}
+
+impl<I: Traverse> Iterator for WithDepth<I> {
+    type Item = (usize, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        Some((self.inner.depth(), item))
+    }
+}
+
+/// Iterator yielding items with their index path, created by [`Traverse::with_path`]
+#[derive(Debug, Clone)]
+pub struct WithPath<I> {
+    inner: I,
+}
+
+impl<I: Traverse> Iterator for WithPath<I> {
+    type Item = (Vec<usize>, I::Item);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.inner.next()?;
+        Some((self.inner.path().to_vec(), item))
+    }
+}
+
+#[allow(deprecated)]
+fn children(element: &Element) -> &[Element] {
+    match element {
+        Element::Tag(tag) => &tag.element_list,
+        Element::String(_) => &[],
+    }
+}
+
+/// Depth-first iterator visiting elements before their children, created by
+/// [`Element::pre_order`]
+#[derive(Debug, Clone)]
+pub struct PreOrder<'a> {
+    root: Option<&'a Element>,
+    stack: Vec<Enumerate<slice::Iter<'a, Element>>>,
+    path: Vec<usize>,
+}
+
+impl<'a> Iterator for PreOrder<'a> {
+    type Item = &'a Element;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            self.stack.push(children(root).iter().enumerate());
+            return Some(root);
+        }
+        loop {
+            let depth = self.stack.len();
+            match self.stack.last_mut()?.next() {
+                Some((index, element)) => {
+                    self.path.truncate(depth - 1);
+                    self.path.push(index);
+                    self.stack.push(children(element).iter().enumerate());
+                    return Some(element);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+impl Traverse for PreOrder<'_> {
+    fn path(&self) -> &[usize] {
+        &self.path
+    }
+}
+
+/// Depth-first iterator visiting elements after their children, created by
+/// [`Element::post_order`]
+#[derive(Debug, Clone)]
+pub struct PostOrder<'a> {
+    stack: Vec<(&'a Element, Enumerate<slice::Iter<'a, Element>>)>,
+    path: Vec<usize>,
+    // The path still ends with the index of the last yielded element.
+    yielded: bool,
+}
+
+impl<'a> Iterator for PostOrder<'a> {
+    type Item = &'a Element;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.yielded {
+            self.path.pop();
+            self.yielded = false;
+        }
+        loop {
+            let (element, children) = self.stack.last_mut()?;
+            match children.next() {
+                Some((index, child)) => {
+                    self.path.push(index);
+                    self.stack
+                        .push((child, self::children(child).iter().enumerate()));
+                }
+                None => {
+                    let element = *element;
+                    self.stack.pop();
+                    self.yielded = true;
+                    return Some(element);
+                }
+            }
+        }
+    }
+}
+
+impl Traverse for PostOrder<'_> {
+    fn path(&self) -> &[usize] {
+        &self.path
+    }
+}
+
+/// Breadth-first iterator, created by [`Element::breadth_first`]
+#[derive(Debug, Clone)]
+pub struct BreadthFirst<'a> {
+    // Elements with the record of their parent and their index
+    queue: VecDeque<(&'a Element, Option<usize>, usize)>,
+    // Parent record and index of each yielded element, to rebuild paths
+    records: Vec<(Option<usize>, usize)>,
+    path: Vec<usize>,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = &'a Element;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (element, parent, index) = self.queue.pop_front()?;
+        let record = self.records.len();
+        self.records.push((parent, index));
+        for (index, child) in children(element).iter().enumerate() {
+            self.queue.push_back((child, Some(record), index));
+        }
+
+        self.path.clear();
+        let mut record = Some(record);
+        // The root has no parent and is not part of the path.
+        while let Some((Some(parent), index)) = record.map(|record| self.records[record]) {
+            self.path.push(index);
+            record = Some(parent);
+        }
+        self.path.reverse();
+        Some(element)
+    }
+}
+
+impl Traverse for BreadthFirst<'_> {
+    fn path(&self) -> &[usize] {
+        &self.path
+    }
+}
+
+/// Iterator over text nodes in document order, created by [`Element::texts`]
+#[derive(Debug, Clone)]
+pub struct Texts<'a> {
+    inner: PreOrder<'a>,
+}
+
+impl<'a> Iterator for Texts<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.find_map(|element| match element {
+            Element::String(s) => Some(s.as_str()),
+            Element::Tag(_) => None,
+        })
+    }
+}
+
+impl Traverse for Texts<'_> {
+    fn path(&self) -> &[usize] {
+        self.inner.path()
+    }
+}
+
+/// Iterator over tags in document order, created by [`Element::tags`]
+#[derive(Debug, Clone)]
+pub struct Tags<'a> {
+    inner: PreOrder<'a>,
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = &'a Tag;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.find_map(|element| match element {
+            Element::Tag(tag) => Some(tag),
+            Element::String(_) => None,
+        })
+    }
+}
+
+impl Traverse for Tags<'_> {
+    fn path(&self) -> &[usize] {
+        self.inner.path()
+    }
+}
+
+/// Mutable reference to an element without its children
+///
+/// The children are yielded separately, so they cannot be borrowed together with their parent.
+#[derive(Debug)]
+pub enum NodeMut<'a> {
+    Tag(TagMut<'a>),
+    String(&'a mut String),
+}
+
+/// Mutable reference to a tag's name and attributes
+///
+/// Names are checked as by the methods of [`Tag`].
+#[derive(Debug)]
+pub struct TagMut<'a> {
+    pub(crate) name: &'a mut String,
+    pub(crate) attributes: &'a mut Attributes,
+}
+
+impl TagMut<'_> {
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    /// Rename the tag, leaving it unchanged if the name is invalid.
+    pub fn set_name(&mut self, name: impl Into<String>) -> Result<(), InvalidNameError> {
+        let name = name.into();
+        crate::check_tag_name(&name)?;
+        *self.name = name;
+        Ok(())
+    }
+
+    pub fn attributes(&self) -> &Attributes {
+        self.attributes
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeValue> {
+        self.attributes.get(name)
+    }
+
+    /// Set the attribute, returning the previous value.
+    pub fn set_attribute(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<AttributeValue>,
+    ) -> Result<Option<AttributeValue>, InvalidNameError> {
+        let name = name.into();
+        crate::check_attribute_name(&name)?;
+        Ok(self.attributes.insert(name, value.into()))
+    }
+
+    /// Remove the attribute, returning its value.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<AttributeValue> {
+        self.attributes.shift_remove(name)
+    }
+}
+
+/// Mutable depth-first iterator visiting elements before their children, created by
+/// [`Element::pre_order_mut`]
+#[derive(Debug)]
+pub struct PreOrderMut<'a> {
+    root: Option<&'a mut Element>,
+    stack: Vec<Enumerate<slice::IterMut<'a, Element>>>,
+    path: Vec<usize>,
+}
+
+impl<'a> PreOrderMut<'a> {
+    #[allow(deprecated)]
+    fn split(&mut self, element: &'a mut Element) -> NodeMut<'a> {
+        match element {
+            Element::Tag(Tag {
+                name,
+                attributes,
+                element_list,
+            }) => {
+                self.stack.push(element_list.iter_mut().enumerate());
+                NodeMut::Tag(TagMut { name, attributes })
+            }
+            Element::String(s) => {
+                self.stack.push([].iter_mut().enumerate());
+                NodeMut::String(s)
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for PreOrderMut<'a> {
+    type Item = NodeMut<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(root) = self.root.take() {
+            return Some(self.split(root));
+        }
+        loop {
+            let depth = self.stack.len();
+            match self.stack.last_mut()?.next() {
+                Some((index, element)) => {
+                    self.path.truncate(depth - 1);
+                    self.path.push(index);
+                    return Some(self.split(element));
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+impl Traverse for PreOrderMut<'_> {
+    fn path(&self) -> &[usize] {
+        &self.path
+    }
+}
+
+/// Mutable iterator over text nodes in document order, created by [`Element::texts_mut`]
+#[derive(Debug)]
+pub struct TextsMut<'a> {
+    inner: PreOrderMut<'a>,
+}
+
+impl<'a> Iterator for TextsMut<'a> {
+    type Item = &'a mut String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.find_map(|node| match node {
+            NodeMut::String(s) => Some(s),
+            NodeMut::Tag(_) => None,
+        })
+    }
+}
+
+impl Traverse for TextsMut<'_> {
+    fn path(&self) -> &[usize] {
+        self.inner.path()
+    }
+}
+
+/// Mutable iterator over tags in document order, created by [`Element::tags_mut`]
+#[derive(Debug)]
+pub struct TagsMut<'a> {
+    inner: PreOrderMut<'a>,
+}
+
+impl<'a> Iterator for TagsMut<'a> {
+    type Item = TagMut<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.find_map(|node| match node {
+            NodeMut::Tag(tag) => Some(tag),
+            NodeMut::String(_) => None,
+        })
+    }
+}
+
+impl Traverse for TagsMut<'_> {
+    fn path(&self) -> &[usize] {
+        self.inner.path()
+    }
+}
+
+impl Element {
+    /// Iterate over the element and its descendants, parents before children
+    ///
+    /// ```
+    /// use jsonml::{iter::Traverse, Element};
+    ///
+    /// let element: Element = serde_json::from_str(r#"["ul", ["li", "a"], ["li", "b"]]"#).unwrap();
+    /// let paths: Vec<_> = element.pre_order().with_path().map(|(path, _)| path).collect();
+    /// assert_eq!(paths, [vec![], vec![0], vec![0, 0], vec![1], vec![1, 0]]);
+    /// ```
+    pub fn pre_order(&self) -> PreOrder<'_> {
+        PreOrder {
+            root: Some(self),
+            stack: vec![],
+            path: vec![],
+        }
+    }
+
+    /// Iterate over the element and its descendants, children before parents
+    pub fn post_order(&self) -> PostOrder<'_> {
+        PostOrder {
+            stack: vec![(self, children(self).iter().enumerate())],
+            path: vec![],
+            yielded: false,
+        }
+    }
+
+    /// Iterate over the element and its descendants level by level
+    pub fn breadth_first(&self) -> BreadthFirst<'_> {
+        BreadthFirst {
+            queue: VecDeque::from([(self, None, 0)]),
+            records: vec![],
+            path: vec![],
+        }
+    }
+
+    /// Iterate over the text nodes in the element, including itself
+    pub fn texts(&self) -> Texts<'_> {
+        Texts {
+            inner: self.pre_order(),
+        }
+    }
+
+    /// Iterate over the tags in the element, including itself
+    pub fn tags(&self) -> Tags<'_> {
+        Tags {
+            inner: self.pre_order(),
+        }
+    }
+
+    /// Iterate mutably over the element and its descendants, parents before children
+    pub fn pre_order_mut(&mut self) -> PreOrderMut<'_> {
+        PreOrderMut {
+            root: Some(self),
+            stack: vec![],
+            path: vec![],
+        }
+    }
+
+    /// Iterate mutably over the text nodes in the element, including itself
+    pub fn texts_mut(&mut self) -> TextsMut<'_> {
+        TextsMut {
+            inner: self.pre_order_mut(),
+        }
+    }
+
+    /// Iterate mutably over the names and attributes of the tags in the element, including
+    /// itself
+    pub fn tags_mut(&mut self) -> TagsMut<'_> {
+        TagsMut {
+            inner: self.pre_order_mut(),
+        }
+    }
+}
+
+#[cfg(test)]
+use crate::test_helpers::from_json;
+
+#[cfg(test)]
+fn sample() -> Element {
+    from_json(r#"["div", ["p", "a", ["b", "b"]], "c", ["ul", ["li", "d"]]]"#)
+}
+
+#[cfg(test)]
+#[allow(deprecated)]
+fn label(element: &Element) -> &str {
+    match element {
+        Element::Tag(tag) => &tag.name,
+        Element::String(s) => s,
+    }
+}
+
+#[test]
+fn test_pre_order() {
+    let element = sample();
+    let labels: Vec<_> = element.pre_order().map(label).collect();
+    assert_eq!(labels, ["div", "p", "a", "b", "b", "c", "ul", "li", "d"]);
+    let depths: Vec<_> = element
+        .pre_order()
+        .with_depth()
+        .map(|(depth, _)| depth)
+        .collect();
+    assert_eq!(depths, [0, 1, 2, 2, 3, 1, 1, 2, 3]);
+}
+
+#[test]
+fn test_post_order() {
+    let element = sample();
+    let labels: Vec<_> = element
+        .post_order()
+        .with_path()
+        .map(|(path, element)| format!("{}{path:?}", label(element)))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            "a[0, 0]",
+            "b[0, 1, 0]",
+            "b[0, 1]",
+            "p[0]",
+            "c[1]",
+            "d[2, 0, 0]",
+            "li[2, 0]",
+            "ul[2]",
+            "div[]"
+        ]
+    );
+}
+
+#[test]
+fn test_breadth_first() {
+    let element = sample();
+    let labels: Vec<_> = element
+        .breadth_first()
+        .with_path()
+        .map(|(path, element)| format!("{}{path:?}", label(element)))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            "div[]",
+            "p[0]",
+            "c[1]",
+            "ul[2]",
+            "a[0, 0]",
+            "b[0, 1]",
+            "li[2, 0]",
+            "b[0, 1, 0]",
+            "d[2, 0, 0]"
+        ]
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_texts_and_tags() {
+    let element = sample();
+    assert_eq!(element.texts().collect::<String>(), "abcd");
+    let tags: Vec<_> = element.tags().map(|tag| tag.name.as_str()).collect();
+    assert_eq!(tags, ["div", "p", "b", "ul", "li"]);
+    let paths: Vec<_> = element.texts().with_path().map(|(path, _)| path).collect();
+    assert_eq!(paths, [vec![0, 0], vec![0, 1, 0], vec![1], vec![2, 0, 0]]);
+
+    let text = Element::String("text".to_string());
+    assert_eq!(text.texts().collect::<Vec<_>>(), ["text"]);
+    assert_eq!(text.tags().count(), 0);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_mutable_iterators() {
+    let mut element = sample();
+    for text in element.texts_mut() {
+        text.make_ascii_uppercase();
+    }
+    for (depth, mut tag) in element.tags_mut().with_depth() {
+        tag.set_attribute("depth", depth as f32).unwrap();
+    }
+    assert_eq!(element.texts().collect::<String>(), "ABCD");
+    let depths: Vec<_> = element
+        .tags()
+        .map(|tag| tag.attributes["depth"].clone())
+        .collect();
+    assert_eq!(
+        depths,
+        [0., 1., 2., 1., 2.].map(AttributeValue::Number).to_vec()
+    );
+    assert_eq!(
+        element.pre_order_mut().with_path().count(),
+        element.pre_order().count()
+    );
+}
+
+#[test]
+fn test_tag_mut_names() {
+    let mut element = sample();
+    if let Some(mut tag) = element.tags_mut().next() {
+        assert!(tag.set_name("a b").is_err());
+        assert!(tag.set_attribute("a b", "x").is_err());
+        tag.set_name("section").unwrap();
+        assert_eq!(tag.name(), "section");
+        assert_eq!(tag.remove_attribute("class"), None);
+    }
+    assert!(element.try_to_html().unwrap().starts_with("<section>"));
+}
@@ -1,24 +1,83 @@
 #![doc = include_str!("../README.md")]
 
 use std::{
-    collections::HashMap,
+    borrow::Cow,
     error::Error,
     fmt::{self, Display},
+    io,
     str::FromStr,
 };
 
 use serde::{
-    de::{self, SeqAccess, Visitor},
-    ser::SerializeSeq,
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeSeq, SerializeTupleVariant},
     Deserialize, Deserializer, Serialize, Serializer,
 };
 
 use void::Void;
 
-use html_escape::encode_unquoted_attribute;
+use html_escape::{encode_text, encode_unquoted_attribute};
+use indexmap::IndexMap;
 
+mod macros;
+
+pub mod binary;
+pub mod borrowed;
+pub mod convert;
+pub mod diff;
+pub mod html;
+pub mod iter;
+pub mod merge;
+pub mod select;
+pub mod visit;
+pub mod xml;
+pub mod xpath;
+
+pub use borrowed::ElementRef;
+pub use convert::{FromJsonML, ToJsonML};
+
+/// Build an [`Element`] from HTML markup at compile time, with the `macros` feature
+#[cfg(feature = "macros")]
+pub use jsonml_macros::html;
+
+/// Derive macros for [`convert`], with the `macros` feature
+#[cfg(feature = "macros")]
+pub use jsonml_macros::{FromJsonML, ToJsonML};
+
+#[cfg(test)]
+use serde_test::{assert_de_tokens, assert_tokens, Configure, Token};
+
+/// Fixtures shared by the tests of the modules
 #[cfg(test)]
-use serde_test::{assert_de_tokens, assert_tokens, Token};
+mod test_helpers {
+    use crate::{AttributeValue, Element, Tag};
+
+    /// Tag with string attributes, whose names are not checked.
+    #[allow(deprecated)]
+    pub(crate) fn tag(
+        name: &str,
+        attributes: &[(&str, &str)],
+        element_list: Vec<Element>,
+    ) -> Element {
+        Element::Tag(Tag {
+            name: name.to_string(),
+            attributes: attributes
+                .iter()
+                .map(|(key, value)| (key.to_string(), AttributeValue::String(value.to_string())))
+                .collect(),
+            element_list,
+        })
+    }
+
+    pub(crate) fn text(s: &str) -> Element {
+        Element::String(s.to_string())
+    }
+
+    /// Element from JsonML in JSON
+    pub(crate) fn from_json(json: &str) -> Element {
+        serde_json::from_str(json).unwrap()
+    }
+}
 
 // `Eq` and `Hash` cannot be derived since neither can `AttributeValue`.
 #[derive(Debug, PartialEq, Clone)]
@@ -28,7 +87,11 @@ pub enum Element {
 }
 
 impl Element {
-    #[deprecated(since = "0.4.0")]
+    #[deprecated(
+        since = "0.4.0",
+        note = "use `Element::transform` with `visit::Order::BottomUp`"
+    )]
+    #[allow(deprecated)]
     pub fn map_bottom_up(self, function: fn(Self) -> Self) -> Self {
         match self {
             Element::Tag(Tag {
@@ -47,7 +110,11 @@ impl Element {
         }
     }
 
-    #[deprecated(since = "0.4.0")]
+    #[deprecated(
+        since = "0.4.0",
+        note = "use `Element::transform` with `visit::Order::TopDown`"
+    )]
+    #[allow(deprecated)]
     pub fn map_top_down(self, function: fn(Self) -> Self) -> Self {
         let element = function(self);
         match element {
@@ -66,35 +133,217 @@ impl Element {
             Element::String(_) => element,
         }
     }
+
+    /// Sort the attributes of every tag by name.
+    ///
+    /// Attributes keep the order in which they are written or inserted, which is the order of
+    /// serialization and rendering; sorting gives the same output for the same attributes.
+    pub fn sort_attributes(&mut self) {
+        for tag in self.tags_mut() {
+            tag.attributes.sort_keys();
+        }
+    }
 }
 
-/// WARNING: The fields will be hidden in the future for validation.
+/// Attributes of a tag, in the order as written or inserted
+pub type Attributes = IndexMap<String, AttributeValue>;
+
+/// Element with a name, attributes and children
+///
+/// Only a tag created with [`Tag::new`] or [`Tag::from_parts`], and changed with
+/// [`Tag::set_name`] and [`Tag::set_attribute`], is guaranteed to have names which can be
+/// rendered as HTML.
+/// Deserialization, the parsers and the deprecated fields do not check names: they keep them as
+/// found, such as XML names with prefixes, and rendering reports those which are invalid.
+///
+/// The public fields, which bypass the validation, are deprecated and will be private in a future
+/// version; use [`Tag::from_parts`] and [`Tag::into_parts`] in place of struct expressions and
+/// patterns.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Tag {
+    #[deprecated(since = "0.5.0", note = "use `Tag::name` and `Tag::set_name`")]
     pub name: String,
-    pub attributes: HashMap<String, AttributeValue>,
+    #[deprecated(
+        since = "0.5.0",
+        note = "use `Tag::attributes`, `Tag::set_attribute` and `Tag::remove_attribute`"
+    )]
+    pub attributes: Attributes,
+    #[deprecated(
+        since = "0.5.0",
+        note = "use `Tag::element_list` and `Tag::element_list_mut`"
+    )]
     pub element_list: Vec<Element>,
 }
 
+// The fields are deprecated for users, and used here until they are private.
+#[allow(deprecated)]
+impl Tag {
+    /// Create a tag without attributes and children.
+    ///
+    /// The name must consist of ASCII alphanumerics to be rendered as HTML.
+    pub fn new(name: impl Into<String>) -> Result<Self, InvalidNameError> {
+        Tag::from_parts(name, Attributes::new(), Vec::new())
+    }
+
+    /// Create a tag, checking the names of the tag and the attributes.
+    pub fn from_parts(
+        name: impl Into<String>,
+        attributes: Attributes,
+        element_list: Vec<Element>,
+    ) -> Result<Self, InvalidNameError> {
+        let name = name.into();
+        check_tag_name(&name)?;
+        for key in attributes.keys() {
+            check_attribute_name(key)?;
+        }
+        Ok(Tag {
+            name,
+            attributes,
+            element_list,
+        })
+    }
+
+    /// Take the name, the attributes and the children.
+    pub fn into_parts(self) -> (String, Attributes, Vec<Element>) {
+        (self.name, self.attributes, self.element_list)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Rename the tag, leaving it unchanged if the name is invalid.
+    pub fn set_name(&mut self, name: impl Into<String>) -> Result<(), InvalidNameError> {
+        let name = name.into();
+        check_tag_name(&name)?;
+        self.name = name;
+        Ok(())
+    }
+
+    pub fn attributes(&self) -> &Attributes {
+        &self.attributes
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&AttributeValue> {
+        self.attributes.get(name)
+    }
+
+    /// Set the attribute, returning the previous value.
+    pub fn set_attribute(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<AttributeValue>,
+    ) -> Result<Option<AttributeValue>, InvalidNameError> {
+        let name = name.into();
+        check_attribute_name(&name)?;
+        Ok(self.attributes.insert(name, value.into()))
+    }
+
+    /// Remove the attribute, returning its value.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<AttributeValue> {
+        self.attributes.shift_remove(name)
+    }
+
+    pub fn element_list(&self) -> &[Element] {
+        &self.element_list
+    }
+
+    pub fn element_list_mut(&mut self) -> &mut Vec<Element> {
+        &mut self.element_list
+    }
+
+    /// Append a child.
+    pub fn push(&mut self, element: impl Into<Element>) {
+        self.element_list.push(element.into());
+    }
+}
+
+fn check_tag_name(name: &str) -> Result<(), InvalidNameError> {
+    html::validate_tag_name(name).map_err(|reason| InvalidNameError::InvalidTagName {
+        name: name.to_string(),
+        reason,
+    })
+}
+
+fn check_attribute_name(name: &str) -> Result<(), InvalidNameError> {
+    html::validate_attribute_name(name).map_err(|reason| InvalidNameError::InvalidAttributeName {
+        name: name.to_string(),
+        reason,
+    })
+}
+
+/// Error on a tag or attribute name which cannot be rendered as HTML
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidNameError {
+    InvalidTagName {
+        name: String,
+        reason: html::InvalidNameReason,
+    },
+    InvalidAttributeName {
+        name: String,
+        reason: html::InvalidNameReason,
+    },
+}
+
+impl Display for InvalidNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidNameError::InvalidTagName { name, reason } => {
+                write!(f, "invalid tag name {name:?}: {reason}")
+            }
+            InvalidNameError::InvalidAttributeName { name, reason } => {
+                write!(f, "invalid attribute name {name:?}: {reason}")
+            }
+        }
+    }
+}
+
+impl Error for InvalidNameError {}
+
 impl Default for Element {
     fn default() -> Self {
         Element::String("".to_string())
     }
 }
 
+/// Serialize as JsonML for human-readable formats such as JSON
+///
+/// Other formats, such as bincode and postcard, get an enum of a tag with its name, attributes
+/// and children, and a string, which formats without type information in the data can read back.
 impl Serialize for Element {
+    #[allow(deprecated)]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        if !serializer.is_human_readable() {
+            return match self {
+                Element::Tag(Tag {
+                    name,
+                    attributes,
+                    element_list,
+                }) => {
+                    let mut variant = serializer.serialize_tuple_variant("Element", 0, "Tag", 3)?;
+                    variant.serialize_field(name)?;
+                    variant.serialize_field(attributes)?;
+                    variant.serialize_field(element_list)?;
+                    variant.end()
+                }
+                Element::String(string) => {
+                    serializer.serialize_newtype_variant("Element", 1, "String", string)
+                }
+            };
+        }
         match self {
             Element::Tag(Tag {
                 name,
                 attributes,
                 element_list,
             }) => {
-                let mut seq =
-                    serializer.serialize_seq(Some(1 + attributes.len() + element_list.len()))?;
+                let mut seq = serializer.serialize_seq(Some(
+                    1 + usize::from(!attributes.is_empty()) + element_list.len(),
+                ))?;
                 seq.serialize_element(&name)?;
                 if !attributes.is_empty() {
                     seq.serialize_element(attributes)?;
@@ -111,6 +360,14 @@ impl Serialize for Element {
     }
 }
 
+/// Representation of [`Element`] for formats which are not human-readable
+#[derive(Deserialize)]
+#[serde(rename = "Element")]
+enum CompactElement {
+    Tag(String, Attributes, Vec<Element>),
+    String(String),
+}
+
 struct ElementVisitor;
 
 impl<'de> Visitor<'de> for ElementVisitor {
@@ -127,21 +384,21 @@ impl<'de> Visitor<'de> for ElementVisitor {
         Ok(Element::String(v.to_string()))
     }
 
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Element::String(v))
+    }
+
+    #[allow(deprecated)]
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
     {
         if let Some(name) = seq.next_element::<String>()? {
-            let mut attributes = HashMap::default();
+            let mut attributes = Attributes::default();
             let mut element_list = vec![];
-
-            #[derive(Deserialize)]
-            #[serde(untagged)]
-            enum AttributesOrElement {
-                Attributes(HashMap<String, AttributeValue>),
-                Element(Element),
-            }
-
             if let Some(attributes_or_element) = seq.next_element::<AttributesOrElement>()? {
                 match attributes_or_element {
                     AttributesOrElement::Attributes(attrs) => attributes = attrs,
@@ -162,6 +419,66 @@ impl<'de> Visitor<'de> for ElementVisitor {
     }
 }
 
+/// Second item of a tag, told apart by its type without buffering it
+enum AttributesOrElement {
+    Attributes(Attributes),
+    Element(Element),
+}
+
+impl<'de> Deserialize<'de> for AttributesOrElement {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(AttributesOrElementVisitor)
+    }
+}
+
+struct AttributesOrElementVisitor;
+
+impl<'de> Visitor<'de> for AttributesOrElementVisitor {
+    type Value = AttributesOrElement;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("JsonML attributes or element")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        ElementVisitor
+            .visit_str(v)
+            .map(AttributesOrElement::Element)
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        ElementVisitor
+            .visit_string(v)
+            .map(AttributesOrElement::Element)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        ElementVisitor
+            .visit_seq(seq)
+            .map(AttributesOrElement::Element)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        Attributes::deserialize(de::value::MapAccessDeserializer::new(map))
+            .map(AttributesOrElement::Attributes)
+    }
+}
+
 impl FromStr for Element {
     type Err = Void;
 
@@ -170,105 +487,268 @@ impl FromStr for Element {
     }
 }
 
+impl From<&str> for Element {
+    fn from(s: &str) -> Self {
+        Element::String(s.to_string())
+    }
+}
+
+impl From<String> for Element {
+    fn from(s: String) -> Self {
+        Element::String(s)
+    }
+}
+
+impl From<Tag> for Element {
+    fn from(tag: Tag) -> Self {
+        Element::Tag(tag)
+    }
+}
+
 /// Display in HTML
 ///
+/// Text is escaped, except in raw text elements such as `script` and `style`.
+/// Void elements such as `br` and `img` are written without end tags.
+///
 /// Panics when
 ///
 /// * the element is a tag and the tag name contains an invalid character
 /// * at least one attribute name contains an invalid character
+/// * the text of a raw text element contains its end tag
+/// * a void element has children
+///
+/// Use [`Element::try_to_html`] to handle these errors.
+///
+/// See [`xml::to_string`] for XML.
 impl Display for Element {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Element::Tag(Tag {
-                    name,
-                    attributes,
-                    element_list,
-                }) => {
-                    // HTML tag name must consists of ASCII alphanumerics.
-                    // https://html.spec.whatwg.org/multipage/syntax.html#syntax-tag-name
-                    if !name.chars().all(|c| c.is_ascii_alphanumeric()) {
-                        return Err(fmt::Error);
-                    }
+        self.write_html(f, &html::RenderOptions::default())
+            .map_err(|_| fmt::Error)
+    }
+}
+
+impl Element {
+    /// Render in HTML, returning an error instead of panicking as [`Display`] does
+    pub fn try_to_html(&self) -> Result<String, html::RenderError> {
+        self.try_to_html_with_options(&html::RenderOptions::default())
+    }
+
+    /// Render in HTML with options
+    pub fn try_to_html_with_options(
+        &self,
+        options: &html::RenderOptions,
+    ) -> Result<String, html::RenderError> {
+        let mut html = String::default();
+        match self.write_html(&mut html, options) {
+            Ok(()) => Ok(html),
+            Err(html::WriteError::Render(error)) => Err(error),
+            Err(error) => unreachable!("writing to string failed: {error}"),
+        }
+    }
+
+    /// Write in HTML to a [`fmt::Write`] without building intermediate strings
+    ///
+    /// The output written before an error is left in the writer.
+    pub fn write_html<W>(
+        &self,
+        writer: &mut W,
+        options: &html::RenderOptions,
+    ) -> Result<(), html::WriteError>
+    where
+        W: fmt::Write + ?Sized,
+    {
+        match &options.layout {
+            html::Layout::Compact => self.write_html_at(writer, options, &mut vec![]),
+            html::Layout::Pretty(pretty) => html::pretty::write(self, writer, options, pretty),
+            html::Layout::Minified(minify) => html::minify::write(self, writer, options, minify),
+        }
+    }
+
+    /// Write in HTML to an [`io::Write`] without building intermediate strings
+    ///
+    /// The writer is not buffered, so wrap it in [`io::BufWriter`] as needed.
+    /// The output written before an error is left in the writer.
+    pub fn write_html_io<W>(
+        &self,
+        writer: &mut W,
+        options: &html::RenderOptions,
+    ) -> Result<(), html::WriteError>
+    where
+        W: io::Write + ?Sized,
+    {
+        let mut adapter = html::IoWriter::new(writer);
+        match self.write_html(&mut adapter, options) {
+            Err(html::WriteError::Fmt(error)) => Err(adapter
+                .take_error()
+                .map_or(html::WriteError::Fmt(error), html::WriteError::Io)),
+            result => result,
+        }
+    }
+
+    /// `path` is the indices of the element in its ancestors' element lists.
+    #[allow(deprecated)]
+    pub(crate) fn write_html_at<W>(
+        &self,
+        writer: &mut W,
+        options: &html::RenderOptions,
+        path: &mut Vec<usize>,
+    ) -> Result<(), html::WriteError>
+    where
+        W: fmt::Write + ?Sized,
+    {
+        match self {
+            Element::Tag(tag) => {
+                tag.write_html_start_tag(writer, options, path)?;
+                // https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+                if html::is_void_element(&tag.name) {
+                    // Void elements have no end tag and no children.
+                    return Ok(());
+                }
+                tag.write_html_content(writer, options, path)?;
+                tag.write_html_end_tag(writer)?;
+            }
+            Element::String(s) => writer.write_str(&encode_text(s))?,
+        }
+        Ok(())
+    }
+}
+
+impl Tag {
+    #[allow(deprecated)]
+    pub(crate) fn write_html_start_tag<W>(
+        &self,
+        writer: &mut W,
+        options: &html::RenderOptions,
+        path: &[usize],
+    ) -> Result<(), html::WriteError>
+    where
+        W: fmt::Write + ?Sized,
+    {
+        let Tag {
+            name,
+            attributes,
+            element_list,
+        } = self;
+        if let Err(reason) = html::validate_tag_name(name) {
+            return Err(html::RenderError::new(
+                html::RenderErrorKind::InvalidTagName {
+                    name: name.to_string(),
+                    reason,
+                },
+                path,
+            )
+            .into());
+        }
+        if html::is_void_element(name) && !element_list.is_empty() {
+            return Err(html::RenderError::new(
+                html::RenderErrorKind::ChildrenInVoidElement {
+                    name: name.to_string(),
+                },
+                path,
+            )
+            .into());
+        }
+
+        writer.write_char('<')?;
+        writer.write_str(name)?;
+        let mut attributes: Vec<_> = attributes.iter().collect();
+        if options.sort_attributes {
+            attributes.sort_by_key(|(key, _)| *key);
+        }
+        for (key, value) in attributes {
+            if let Err(reason) = html::validate_attribute_name(key) {
+                return Err(html::RenderError::new(
+                    html::RenderErrorKind::InvalidAttributeName {
+                        name: key.to_string(),
+                        reason,
+                    },
+                    path,
+                )
+                .into());
+            }
+            html::write_attribute(writer, key, value, options)?;
+        }
+        writer.write_char('>')?;
+        Ok(())
+    }
 
-                    let attrs = if attributes.is_empty() {
-                        "".to_string()
-                    } else {
-                        let mut attrs = String::default();
-                        for (key, value) in attributes {
-                            // Validate attribute name
-                            // https://html.spec.whatwg.org/multipage/syntax.html#attributes-2
-                            if !key.chars().all(|c| {
-                                c != '\u{0020}'
-                                    && c != '\u{0022}'
-                                    && c != '\u{0027}'
-                                    && c != '\u{003E}'
-                                    && c != '\u{002F}'
-                                    && c != '\u{003D}'
-                                    && !('\u{FDD0}' <= c && c <= '\u{FDEF}')
-                                    && c != '\u{FFFE}'
-                                    && c != '\u{FFFF}'
-                                    && c != '\u{1FFFE}'
-                                    && c != '\u{1FFFF}'
-                                    && c != '\u{2FFFE}'
-                                    && c != '\u{2FFFF}'
-                                    && c != '\u{3FFFE}'
-                                    && c != '\u{3FFFF}'
-                                    && c != '\u{4FFFE}'
-                                    && c != '\u{4FFFF}'
-                                    && c != '\u{5FFFE}'
-                                    && c != '\u{5FFFF}'
-                                    && c != '\u{6FFFE}'
-                                    && c != '\u{6FFFF}'
-                                    && c != '\u{7FFFE}'
-                                    && c != '\u{7FFFF}'
-                                    && c != '\u{8FFFE}'
-                                    && c != '\u{8FFFF}'
-                                    && c != '\u{9FFFE}'
-                                    && c != '\u{9FFFF}'
-                                    && c != '\u{AFFFE}'
-                                    && c != '\u{AFFFF}'
-                                    && c != '\u{BFFFE}'
-                                    && c != '\u{BFFFF}'
-                                    && c != '\u{CFFFE}'
-                                    && c != '\u{CFFFF}'
-                                    && c != '\u{DFFFE}'
-                                    && c != '\u{DFFFF}'
-                                    && c != '\u{EFFFE}'
-                                    && c != '\u{EFFFF}'
-                                    && c != '\u{FFFFE}'
-                                    && c != '\u{FFFFF}'
-                                    && c != '\u{10FFFE}'
-                                    && c != '\u{10FFFF}'
-                            }) {
-                                return Err(fmt::Error);
-                            }
-                            let pair = format!(r#" {key}="{value}""#);
-                            attrs += &pair;
-                        }
-                        attrs
+    /// Write the children, which are written literally for raw text elements.
+    #[allow(deprecated)]
+    pub(crate) fn write_html_content<W>(
+        &self,
+        writer: &mut W,
+        options: &html::RenderOptions,
+        path: &mut Vec<usize>,
+    ) -> Result<(), html::WriteError>
+    where
+        W: fmt::Write + ?Sized,
+    {
+        let Tag {
+            name, element_list, ..
+        } = self;
+        let raw_text = html::is_raw_text_element(name);
+        let mut index = 0;
+        while index < element_list.len() {
+            if raw_text {
+                // Raw text cannot be escaped, so it must not close the element early.
+                let text_len = element_list[index..]
+                    .iter()
+                    .take_while(|elm| matches!(elm, Element::String(_)))
+                    .count();
+                if text_len > 0 {
+                    let texts = &element_list[index..index + text_len];
+                    let text = match texts {
+                        [Element::String(s)] => Cow::Borrowed(s.as_str()),
+                        _ => Cow::Owned(
+                            texts
+                                .iter()
+                                .filter_map(|elm| match elm {
+                                    Element::String(s) => Some(s.as_str()),
+                                    Element::Tag(_) => None,
+                                })
+                                .collect(),
+                        ),
                     };
-                    let elms = element_list
-                        .iter()
-                        .map(|elm| elm.to_string())
-                        .collect::<Vec<_>>()
-                        .join("");
-                    format!("<{name}{attrs}>{elms}</{name}>")
+                    if html::contains_end_tag(&text, name) {
+                        return Err(html::RenderError::new(
+                            html::RenderErrorKind::EndTagInRawText {
+                                name: name.to_string(),
+                            },
+                            path,
+                        )
+                        .into());
+                    }
+                    writer.write_str(&text)?;
+                    index += text_len;
+                    continue;
                 }
-                Element::String(s) => s.to_owned(),
             }
-        )
+            path.push(index);
+            element_list[index].write_html_at(writer, options, path)?;
+            path.pop();
+            index += 1;
+        }
+        Ok(())
+    }
+
+    #[allow(deprecated)]
+    pub(crate) fn write_html_end_tag<W>(&self, writer: &mut W) -> fmt::Result
+    where
+        W: fmt::Write + ?Sized,
+    {
+        writer.write_str("</")?;
+        writer.write_str(&self.name)?;
+        writer.write_char('>')
     }
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_display_element() {
     assert_eq!(
         Element::Tag(Tag {
             name: "div".to_string(),
-            attributes: HashMap::from([(
+            attributes: Attributes::from([(
                 "id".to_string(),
                 AttributeValue::String("aaa".to_string())
             ),]),
@@ -276,7 +756,7 @@ fn test_display_element() {
                 Element::String("bbb".to_string()),
                 Element::Tag(Tag {
                     name: "span".to_string(),
-                    attributes: HashMap::default(),
+                    attributes: Attributes::default(),
                     element_list: vec![Element::String("ccc".to_string())]
                 })
             ]
@@ -288,34 +768,197 @@ fn test_display_element() {
 
 #[test]
 #[should_panic]
+#[allow(deprecated)]
 fn test_display_element_invalid_tag_name() {
     Element::Tag(Tag {
         name: "あ".to_string(),
-        attributes: HashMap::default(),
+        attributes: Attributes::default(),
         element_list: vec![],
     })
     .to_string();
 }
 
+#[test]
+#[allow(deprecated)]
+fn test_try_to_html_invalid_tag_name() {
+    assert_eq!(
+        Element::Tag(Tag {
+            name: "div".to_string(),
+            attributes: Attributes::default(),
+            element_list: vec![
+                Element::String("a".to_string()),
+                Element::Tag(Tag {
+                    name: "p".to_string(),
+                    attributes: Attributes::default(),
+                    element_list: vec![Element::Tag(Tag {
+                        name: "my-element".to_string(),
+                        attributes: Attributes::default(),
+                        element_list: vec![],
+                    })],
+                })
+            ],
+        })
+        .try_to_html()
+        .map_err(|error| error.to_string()),
+        Err(r#"invalid tag name "my-element": '-' is not allowed at path [1, 0]"#.to_string())
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_try_to_html_invalid_attribute_name() {
+    let error = Element::Tag(Tag {
+        name: "a".to_string(),
+        attributes: Attributes::from([("\u{7}".to_string(), AttributeValue::Null)]),
+        element_list: vec![],
+    })
+    .try_to_html()
+    .unwrap_err();
+    assert_eq!(
+        error.kind(),
+        &html::RenderErrorKind::InvalidAttributeName {
+            name: "\u{7}".to_string(),
+            reason: html::InvalidNameReason::InvalidChar('\u{7}')
+        }
+    );
+    assert!(error.path().is_empty());
+}
+
+#[test]
+fn test_try_to_html_void_element_children() {
+    let element = Element::Tag(
+        Tag::from_parts(
+            "p",
+            Attributes::new(),
+            vec![Element::Tag(
+                Tag::from_parts("br", Attributes::new(), vec!["a".into()]).unwrap(),
+            )],
+        )
+        .unwrap(),
+    );
+    let error = element.try_to_html().unwrap_err();
+    assert_eq!(
+        error.kind(),
+        &html::RenderErrorKind::ChildrenInVoidElement {
+            name: "br".to_string()
+        }
+    );
+    assert_eq!(error.path(), &[0]);
+    for layout in [
+        html::Layout::Pretty(html::PrettyOptions::default()),
+        html::Layout::Minified(html::MinifyOptions::default()),
+    ] {
+        let options = html::RenderOptions {
+            layout,
+            ..html::RenderOptions::default()
+        };
+        assert_eq!(
+            element.try_to_html_with_options(&options),
+            Err(error.clone())
+        );
+    }
+}
+
 #[test]
 #[should_panic]
+#[allow(deprecated)]
 fn test_display_element_invalid_attribute_name() {
     Element::Tag(Tag {
         name: "a".to_string(),
-        attributes: HashMap::from([(" ".to_string(), AttributeValue::Null)]),
+        attributes: Attributes::from([(" ".to_string(), AttributeValue::Null)]),
         element_list: vec![],
     })
     .to_string();
 }
 
 #[test]
+#[allow(deprecated)]
+fn test_display_element_escape_text() {
+    assert_eq!(
+        Element::Tag(Tag {
+            name: "p".to_string(),
+            attributes: Attributes::default(),
+            element_list: vec![Element::String("<script>a && b</script>".to_string())]
+        })
+        .to_string(),
+        "<p>&lt;script&gt;a &amp;&amp; b&lt;/script&gt;</p>".to_string()
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_display_element_raw_text() {
+    assert_eq!(
+        Element::Tag(Tag {
+            name: "script".to_string(),
+            attributes: Attributes::default(),
+            element_list: vec![Element::String("if (a < b && c) {}".to_string())]
+        })
+        .to_string(),
+        "<script>if (a < b && c) {}</script>".to_string()
+    );
+    assert_eq!(
+        Element::Tag(Tag {
+            name: "title".to_string(),
+            attributes: Attributes::default(),
+            element_list: vec![Element::String("a < b".to_string())]
+        })
+        .to_string(),
+        "<title>a &lt; b</title>".to_string()
+    );
+}
+
+#[test]
+#[should_panic]
+#[allow(deprecated)]
+fn test_display_element_raw_text_end_tag() {
+    Element::Tag(Tag {
+        name: "style".to_string(),
+        attributes: Attributes::default(),
+        element_list: vec![Element::String("</STYLE ><script>".to_string())],
+    })
+    .to_string();
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_display_element_void() {
+    assert_eq!(
+        Element::Tag(Tag {
+            name: "p".to_string(),
+            attributes: Attributes::default(),
+            element_list: vec![
+                Element::String("a".to_string()),
+                Element::Tag(Tag {
+                    name: "br".to_string(),
+                    attributes: Attributes::default(),
+                    element_list: vec![]
+                }),
+                Element::Tag(Tag {
+                    name: "img".to_string(),
+                    attributes: Attributes::from([(
+                        "src".to_string(),
+                        AttributeValue::String("b".to_string())
+                    )]),
+                    element_list: vec![]
+                })
+            ]
+        })
+        .to_string(),
+        r#"<p>a<br><img src="b"></p>"#.to_string()
+    );
+}
+
+#[test]
+#[allow(deprecated)]
 fn test_display_element_encode_attribute_value() {
     assert_eq!(
         Element::Tag(Tag {
             name: "a".to_string(),
-            attributes: HashMap::from(
-                [("b".to_string(), AttributeValue::String("=".to_string())),]
-            ),
+            attributes: Attributes::from([(
+                "b".to_string(),
+                AttributeValue::String("=".to_string())
+            ),]),
             element_list: vec![]
         })
         .to_string(),
@@ -324,26 +967,38 @@ fn test_display_element_encode_attribute_value() {
 }
 
 impl<'de> Deserialize<'de> for Element {
+    #[allow(deprecated)]
     fn deserialize<D>(deserializer: D) -> Result<Element, D::Error>
     where
         D: Deserializer<'de>,
     {
+        if !deserializer.is_human_readable() {
+            return Ok(match CompactElement::deserialize(deserializer)? {
+                CompactElement::Tag(name, attributes, element_list) => Element::Tag(Tag {
+                    name,
+                    attributes,
+                    element_list,
+                }),
+                CompactElement::String(string) => Element::String(string),
+            });
+        }
         deserializer.deserialize_any(ElementVisitor)
     }
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_element_tag() {
     let element = Element::Tag(Tag {
         name: "li".to_string(),
-        attributes: HashMap::from([(
+        attributes: Attributes::from([(
             "style".to_string(),
             AttributeValue::String("color:red".to_string()),
         )]),
         element_list: vec![Element::String("First Item".to_string())],
     });
     assert_tokens(
-        &element,
+        &element.readable(),
         &[
             Token::Seq { len: Some(3) },
             Token::Str("li"),
@@ -358,30 +1013,101 @@ fn test_element_tag() {
 }
 
 #[test]
+fn test_tag_accessors() {
+    let mut tag = Tag::new("p").unwrap();
+    assert_eq!(tag.set_attribute("class", "a"), Ok(None));
+    assert_eq!(
+        tag.set_attribute("class", true),
+        Ok(Some(AttributeValue::String("a".to_string())))
+    );
+    tag.push("text");
+    tag.element_list_mut()
+        .push(Element::Tag(Tag::new("br").unwrap()));
+    tag.set_name("div").unwrap();
+    assert_eq!(tag.name(), "div");
+    assert_eq!(tag.attribute("class"), Some(&AttributeValue::Bool(true)));
+    assert_eq!(tag.element_list().len(), 2);
+    assert_eq!(
+        Element::Tag(tag.clone()).to_string(),
+        "<div class>text<br></div>"
+    );
+    assert_eq!(
+        tag.remove_attribute("class"),
+        Some(AttributeValue::Bool(true))
+    );
+    let (name, attributes, element_list) = tag.into_parts();
+    assert_eq!(
+        Tag::from_parts(name, attributes, element_list),
+        Ok(
+            serde_json::from_str::<Element>(r#"["div", "text", ["br"]]"#)
+                .map(|element| match element {
+                    Element::Tag(tag) => tag,
+                    Element::String(_) => unreachable!(),
+                })
+                .unwrap()
+        )
+    );
+}
+
+#[test]
+fn test_tag_invalid_name() {
+    assert_eq!(
+        Tag::new("my-element").map_err(|error| error.to_string()),
+        Err(r#"invalid tag name "my-element": '-' is not allowed"#.to_string())
+    );
+    assert_eq!(
+        Tag::new(""),
+        Err(InvalidNameError::InvalidTagName {
+            name: "".to_string(),
+            reason: html::InvalidNameReason::Empty
+        })
+    );
+    let mut tag = Tag::new("a").unwrap();
+    assert!(tag.set_name("a b").is_err());
+    assert_eq!(tag.name(), "a");
+    assert_eq!(
+        tag.set_attribute("x=y", ""),
+        Err(InvalidNameError::InvalidAttributeName {
+            name: "x=y".to_string(),
+            reason: html::InvalidNameReason::InvalidChar('=')
+        })
+    );
+    assert!(tag.attributes().is_empty());
+    assert!(Tag::from_parts(
+        "a",
+        Attributes::from([("\u{7}".to_string(), AttributeValue::Null)]),
+        vec![]
+    )
+    .is_err());
+}
+
+#[test]
+#[allow(deprecated)]
 fn test_element_tag_name_only() {
     let element = Element::Tag(Tag {
         name: "li".to_string(),
-        attributes: HashMap::default(),
+        attributes: Attributes::default(),
         element_list: vec![],
     });
     assert_tokens(
-        &element,
+        &element.readable(),
         &[Token::Seq { len: Some(1) }, Token::Str("li"), Token::SeqEnd],
     );
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_element_tag_without_element_list() {
     let element = Element::Tag(Tag {
         name: "li".to_string(),
-        attributes: HashMap::from([(
+        attributes: Attributes::from([(
             "style".to_string(),
             AttributeValue::String("color:red".to_string()),
         )]),
         element_list: vec![],
     });
     assert_tokens(
-        &element,
+        &element.readable(),
         &[
             Token::Seq { len: Some(2) },
             Token::Str("li"),
@@ -394,14 +1120,142 @@ fn test_element_tag_without_element_list() {
     );
 }
 
+#[test]
+fn test_attribute_order() {
+    let json = r#"["a",{"href":"h","class":"c","id":"i"},"b"]"#;
+    let mut element: Element = serde_json::from_str(json).unwrap();
+    assert_eq!(serde_json::to_string(&element).unwrap(), json);
+    assert_eq!(element.to_string(), r#"<a href="h" class="c" id="i">b</a>"#);
+
+    element.sort_attributes();
+    assert_eq!(
+        serde_json::to_string(&element).unwrap(),
+        r#"["a",{"class":"c","href":"h","id":"i"},"b"]"#
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_element_tag_child_without_attributes() {
+    let element = Element::Tag(Tag {
+        name: "ul".to_string(),
+        attributes: Attributes::default(),
+        element_list: vec![Element::Tag(Tag {
+            name: "li".to_string(),
+            attributes: Attributes::from([(
+                "class".to_string(),
+                AttributeValue::String("a".to_string()),
+            )]),
+            element_list: vec![Element::String("b".to_string())],
+        })],
+    });
+    assert_de_tokens(
+        &element.readable(),
+        &[
+            Token::Seq { len: Some(2) },
+            Token::Str("ul"),
+            Token::Seq { len: Some(3) },
+            Token::Str("li"),
+            Token::Map { len: Some(1) },
+            Token::Str("class"),
+            Token::Str("a"),
+            Token::MapEnd,
+            Token::String("b"),
+            Token::SeqEnd,
+            Token::SeqEnd,
+        ],
+    );
+}
+
 #[test]
 fn test_element_string() {
     let element = Element::String("First Item".to_string());
-    assert_de_tokens(&element, &[Token::Str("First Item")]);
+    assert_de_tokens(&element.readable(), &[Token::Str("First Item")]);
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
-#[serde(untagged)]
+#[test]
+fn test_element_compact() {
+    let element: Element = serde_json::from_str(r#"["a", {"href": "h", "n": null}, "b"]"#).unwrap();
+    assert_tokens(
+        &element.clone().readable(),
+        &[
+            Token::Seq { len: Some(3) },
+            Token::Str("a"),
+            Token::Map { len: Some(2) },
+            Token::Str("href"),
+            Token::Str("h"),
+            Token::Str("n"),
+            Token::Unit,
+            Token::MapEnd,
+            Token::Str("b"),
+            Token::SeqEnd,
+        ],
+    );
+    assert_tokens(
+        &element.compact(),
+        &[
+            Token::TupleVariant {
+                name: "Element",
+                variant: "Tag",
+                len: 3,
+            },
+            Token::Str("a"),
+            Token::Map { len: Some(2) },
+            Token::Str("href"),
+            Token::NewtypeVariant {
+                name: "AttributeValue",
+                variant: "String",
+            },
+            Token::Str("h"),
+            Token::Str("n"),
+            Token::UnitVariant {
+                name: "AttributeValue",
+                variant: "Null",
+            },
+            Token::MapEnd,
+            Token::Seq { len: Some(1) },
+            Token::NewtypeVariant {
+                name: "Element",
+                variant: "String",
+            },
+            Token::Str("b"),
+            Token::SeqEnd,
+            Token::TupleVariantEnd,
+        ],
+    );
+}
+
+#[test]
+fn test_element_binary_formats() {
+    let element: Element = serde_json::from_str(
+        r#"["ul", {"class": "list", "start": 2, "reversed": true}, ["li", "a"], ["li", {"hidden": null}], "b"]"#,
+    )
+    .unwrap();
+
+    let bytes = bincode::serialize(&element).unwrap();
+    assert_eq!(
+        bincode::serialize(&ElementRef::from(&element)).unwrap(),
+        bytes
+    );
+    assert_eq!(bincode::deserialize::<Element>(&bytes).unwrap(), element);
+    assert_eq!(
+        bincode::deserialize::<ElementRef>(&bytes).unwrap(),
+        ElementRef::from(&element)
+    );
+
+    let bytes = postcard::to_allocvec(&element).unwrap();
+    assert_eq!(postcard::from_bytes::<Element>(&bytes).unwrap(), element);
+    assert_eq!(
+        postcard::from_bytes::<ElementRef>(&bytes).unwrap(),
+        ElementRef::from(&element)
+    );
+}
+
+/// Value of an attribute
+///
+/// Human-readable formats have the value itself, such as a JSON string or `null`, and other
+/// formats have an enum as [`Element`] does.
+#[derive(Debug, PartialEq, Clone, Default)]
 pub enum AttributeValue {
     String(String),
     Number(f32),
@@ -411,13 +1265,178 @@ pub enum AttributeValue {
     Null,
 }
 
+impl Serialize for AttributeValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            match self {
+                AttributeValue::String(s) => serializer.serialize_str(s),
+                AttributeValue::Number(n) => serializer.serialize_f32(*n),
+                AttributeValue::Bool(b) => serializer.serialize_bool(*b),
+                AttributeValue::Null => serializer.serialize_unit(),
+            }
+        } else {
+            match self {
+                AttributeValue::String(s) => {
+                    serializer.serialize_newtype_variant("AttributeValue", 0, "String", s)
+                }
+                AttributeValue::Number(n) => {
+                    serializer.serialize_newtype_variant("AttributeValue", 1, "Number", n)
+                }
+                AttributeValue::Bool(b) => {
+                    serializer.serialize_newtype_variant("AttributeValue", 2, "Bool", b)
+                }
+                AttributeValue::Null => {
+                    serializer.serialize_unit_variant("AttributeValue", 3, "Null")
+                }
+            }
+        }
+    }
+}
+
+/// Representation of [`AttributeValue`] for formats which are not human-readable
+#[derive(Deserialize)]
+#[serde(rename = "AttributeValue")]
+enum CompactAttributeValue {
+    String(String),
+    Number(f32),
+    Bool(bool),
+    Null,
+}
+
+impl<'de> Deserialize<'de> for AttributeValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            return Ok(match CompactAttributeValue::deserialize(deserializer)? {
+                CompactAttributeValue::String(s) => AttributeValue::String(s),
+                CompactAttributeValue::Number(n) => AttributeValue::Number(n),
+                CompactAttributeValue::Bool(b) => AttributeValue::Bool(b),
+                CompactAttributeValue::Null => AttributeValue::Null,
+            });
+        }
+        deserializer.deserialize_any(AttributeValueVisitor)
+    }
+}
+
+struct AttributeValueVisitor;
+
+impl<'de> Visitor<'de> for AttributeValueVisitor {
+    type Value = AttributeValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("attribute value, which is string, number, boolean or null")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValue::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValue::String(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValue::Number(v as f32))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValue::Number(v as f32))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValue::Number(v as f32))
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValue::Bool(v))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(AttributeValue::Null)
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(s: &str) -> Self {
+        AttributeValue::String(s.to_string())
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(s: String) -> Self {
+        AttributeValue::String(s)
+    }
+}
+
+impl From<f32> for AttributeValue {
+    fn from(n: f32) -> Self {
+        AttributeValue::Number(n)
+    }
+}
+
+/// Converted to `f32`, possibly losing precision
+impl From<f64> for AttributeValue {
+    fn from(n: f64) -> Self {
+        AttributeValue::Number(n as f32)
+    }
+}
+
+/// Converted to `f32`, possibly losing precision
+impl From<i32> for AttributeValue {
+    fn from(n: i32) -> Self {
+        AttributeValue::Number(n as f32)
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(b: bool) -> Self {
+        AttributeValue::Bool(b)
+    }
+}
+
 /// Display in HTML
+///
+/// Booleans and null are displayed as JSON literals.
+/// See [`html::AttributeValueStyle`] for rendering them as HTML boolean attributes instead.
 impl Display for AttributeValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             AttributeValue::String(s) => write!(f, "{}", encode_unquoted_attribute(s)),
-            AttributeValue::Number(n) => write!(f, "{}", n.to_string()),
-            AttributeValue::Bool(b) => write!(f, "{}", b.to_string()),
+            AttributeValue::Number(n) => write!(f, "{}", html::format_number(*n)),
+            AttributeValue::Bool(b) => write!(f, "{}", b),
             AttributeValue::Null => write!(f, "null"),
         }
     }
@@ -426,24 +1445,24 @@ impl Display for AttributeValue {
 #[test]
 fn test_attribute_value_string() {
     let value = AttributeValue::String("color:red".to_string());
-    assert_tokens(&value, &[Token::Str("color:red")]);
+    assert_tokens(&value.readable(), &[Token::Str("color:red")]);
 }
 
 #[test]
 #[allow(clippy::approx_constant)]
 fn test_attribute_value_number() {
     let value = AttributeValue::Number(3.14);
-    assert_tokens(&value, &[Token::F32(3.14)]);
+    assert_tokens(&value.readable(), &[Token::F32(3.14)]);
 }
 
 #[test]
 fn test_attribute_value_bool() {
     let value = AttributeValue::Bool(false);
-    assert_tokens(&value, &[Token::Bool(false)]);
+    assert_tokens(&value.readable(), &[Token::Bool(false)]);
 }
 
 #[test]
 fn test_attribute_value_null() {
     let value = AttributeValue::Null;
-    assert_tokens(&value, &[Token::Unit]);
+    assert_tokens(&value.readable(), &[Token::Unit]);
 }
@@ -0,0 +1,174 @@
+/// Build an [`Element`](crate::Element) with JsonML syntax
+///
+/// Tag names and attribute names are literals or parenthesized expressions, and attribute values
+/// and children are expressions, converted with [`Into`].
+/// `..expression` inserts the items of an iterator as children.
+///
+/// # Panics
+///
+/// Panics if a tag or attribute name cannot be rendered, as checked by
+/// [`Tag::from_parts`](crate::Tag::from_parts).
+///
+/// ```
+/// use jsonml::{jsonml, Element};
+///
+/// let color = "red";
+/// let items = ["Second Item", "Third Item"];
+/// let element = jsonml!(["ul",
+///     ["li", {"style": format!("color:{color}")}, "First Item"],
+///     ..items.iter().map(|item| jsonml!(["li", *item]))
+/// ]);
+/// assert_eq!(
+///     element,
+///     serde_json::from_str::<Element>(
+///         r#"["ul",
+///             ["li", {"style": "color:red"}, "First Item"],
+///             ["li", "Second Item"],
+///             ["li", "Third Item"]]"#
+///     )
+///     .unwrap()
+/// );
+/// ```
+#[macro_export]
+macro_rules! jsonml {
+    ([$name:tt $(,)?]) => {
+        $crate::jsonml!(@tag $name, $crate::Attributes::new(), ::std::vec::Vec::new())
+    };
+    ([$name:tt, {$($attributes:tt)*} $(, $($children:tt)*)?]) => {
+        $crate::jsonml!(
+            @tag $name,
+            $crate::jsonml!(@attributes [$($attributes)*]),
+            $crate::jsonml!(@element_list [$($($children)*)?])
+        )
+    };
+    ([$name:tt, $($children:tt)*]) => {
+        $crate::jsonml!(
+            @tag $name,
+            $crate::Attributes::new(),
+            $crate::jsonml!(@element_list [$($children)*])
+        )
+    };
+
+    (@tag $name:tt, $attributes:expr, $element_list:expr) => {
+        match $crate::Tag::from_parts($crate::jsonml!(@into $name), $attributes, $element_list) {
+            ::std::result::Result::Ok(tag) => $crate::Element::Tag(tag),
+            ::std::result::Result::Err(error) => ::std::panic!("{}", error),
+        }
+    };
+
+    (@attributes [$($key:tt : $value:expr),* $(,)?]) => {{
+        #[allow(unused_mut)]
+        let mut attributes = $crate::Attributes::new();
+        $(
+            attributes.insert(
+                $crate::jsonml!(@into $key),
+                ::std::convert::Into::<$crate::AttributeValue>::into($value),
+            );
+        )*
+        attributes
+    }};
+
+    (@element_list [$($children:tt)*]) => {{
+        #[allow(unused_mut)]
+        let mut element_list = ::std::vec::Vec::<$crate::Element>::new();
+        $crate::jsonml!(@children element_list [$($children)*]);
+        element_list
+    }};
+
+    (@children $element_list:ident []) => {};
+    (@children $element_list:ident [.. $children:expr $(, $($rest:tt)*)?]) => {
+        $element_list.extend(
+            ::std::iter::IntoIterator::into_iter($children)
+                .map(::std::convert::Into::<$crate::Element>::into),
+        );
+        $crate::jsonml!(@children $element_list [$($($rest)*)?]);
+    };
+    (@children $element_list:ident [[$($tag:tt)*] $(, $($rest:tt)*)?]) => {
+        ::std::vec::Vec::push(&mut $element_list, $crate::jsonml!([$($tag)*]));
+        $crate::jsonml!(@children $element_list [$($($rest)*)?]);
+    };
+    (@children $element_list:ident [$child:expr $(, $($rest:tt)*)?]) => {
+        ::std::vec::Vec::push(
+            &mut $element_list,
+            ::std::convert::Into::<$crate::Element>::into($child),
+        );
+        $crate::jsonml!(@children $element_list [$($($rest)*)?]);
+    };
+
+    (@into ($value:expr)) => {
+        ::std::convert::Into::<::std::string::String>::into($value)
+    };
+    (@into $value:literal) => {
+        ::std::convert::Into::<::std::string::String>::into($value)
+    };
+
+    ($element:expr) => {
+        ::std::convert::Into::<$crate::Element>::into($element)
+    };
+}
+
+#[cfg(test)]
+use crate::{AttributeValue, Attributes, Element, Tag};
+
+#[test]
+#[allow(deprecated)]
+fn test_jsonml_literal() {
+    assert_eq!(
+        jsonml!(["li", {"style": "color:red"}, "First Item"]),
+        Element::Tag(Tag {
+            name: "li".to_string(),
+            attributes: Attributes::from([(
+                "style".to_string(),
+                AttributeValue::String("color:red".to_string())
+            )]),
+            element_list: vec![Element::String("First Item".to_string())]
+        })
+    );
+    assert_eq!(
+        jsonml!(["img", {"width": 100, "hidden": true, "opacity": 0.5,}]),
+        Element::Tag(Tag {
+            name: "img".to_string(),
+            attributes: Attributes::from([
+                ("width".to_string(), AttributeValue::Number(100.)),
+                ("hidden".to_string(), AttributeValue::Bool(true)),
+                ("opacity".to_string(), AttributeValue::Number(0.5)),
+            ]),
+            element_list: vec![]
+        })
+    );
+    assert_eq!(jsonml!(["br"]), jsonml!(["br", {}]));
+    assert_eq!(jsonml!("text"), Element::String("text".to_string()));
+    assert_eq!(
+        jsonml!(["p", "a", ["b", "b"], "c",]),
+        serde_json::from_str::<Element>(r#"["p", "a", ["b", "b"], "c"]"#).unwrap()
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_jsonml_interpolation() {
+    let name = "h1";
+    let key = String::from("id");
+    let id = String::from("title");
+    let children = vec![jsonml!(["span", "a"]), jsonml!("b")];
+    assert_eq!(
+        jsonml!([(name), {(key.clone()): id.clone()}, format!("{id}!"), ..children.clone(), ..["c", "d"]]),
+        serde_json::from_str::<Element>(
+            r#"["h1", {"id": "title"}, "title!", ["span", "a"], "b", "c", "d"]"#
+        )
+        .unwrap()
+    );
+    let tag = Tag {
+        name: "em".to_string(),
+        attributes: Attributes::default(),
+        element_list: vec![],
+    };
+    assert_eq!(jsonml!(["p", tag.clone()]), jsonml!(["p", ["em"]]));
+    assert_eq!(jsonml!(tag.clone()), jsonml!(["em"]));
+}
+
+#[test]
+#[should_panic(expected = "invalid tag name \"my-element\": '-' is not allowed")]
+fn test_jsonml_invalid_name() {
+    jsonml!(["my-element"]);
+}
@@ -0,0 +1,505 @@
+//! Three-way merge of JsonML elements
+//!
+//! Children are matched between the base and each side as in [`diff`](crate::diff), so changes in
+//! attributes, text and children, removals, insertions and reordering are merged when the sides
+//! do not change the same thing differently.
+//! Inserted children follow the child which precedes them on their side.
+
+use std::{collections::HashMap, error::Error, fmt, fmt::Display};
+
+use indexmap::IndexSet;
+
+use crate::{diff::match_children, AttributeValue, Attributes, Element, Tag};
+
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// Attributes which identify children, such as `key` or `id`, as in
+    /// [`DiffOptions::keys`](crate::diff::DiffOptions::keys)
+    pub keys: Vec<String>,
+}
+
+/// Conflicting changes with the path of the element in the base
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    kind: ConflictKind,
+    path: Vec<usize>,
+}
+
+impl Conflict {
+    pub fn kind(&self) -> &ConflictKind {
+        &self.kind
+    }
+
+    /// Indices of children from the base root to the element
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at path {:?}", self.kind, self.path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ConflictKind {
+    /// Both sides changed the attribute differently, where none is a removal.
+    Attribute {
+        name: String,
+        ours: Option<AttributeValue>,
+        theirs: Option<AttributeValue>,
+    },
+
+    /// Both sides changed the string differently.
+    Text { ours: String, theirs: String },
+
+    /// Both sides changed the element differently, or one side removed it and the other changed
+    /// it.
+    Element {
+        ours: Option<Element>,
+        theirs: Option<Element>,
+    },
+
+    /// Both sides inserted different children at the same place, after the base child at `after`
+    /// or at the start.
+    Insert {
+        after: Option<usize>,
+        ours: Vec<Element>,
+        theirs: Vec<Element>,
+    },
+
+    /// Both sides reordered the children differently.
+    Order,
+}
+
+impl Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictKind::Attribute { name, .. } => {
+                write!(f, "conflicting changes to attribute {name:?}")
+            }
+            ConflictKind::Text { .. } => write!(f, "conflicting changes to text"),
+            ConflictKind::Element { .. } => write!(f, "conflicting changes to element"),
+            ConflictKind::Insert { .. } => write!(f, "conflicting insertions of children"),
+            ConflictKind::Order => write!(f, "conflicting reordering of children"),
+        }
+    }
+}
+
+/// Error of a merge with conflicts
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeError {
+    conflicts: Vec<Conflict>,
+}
+
+impl MergeError {
+    /// Conflicts in document order
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, conflict) in self.conflicts.iter().enumerate() {
+            if index > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{conflict}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for MergeError {}
+
+/// Merge the changes from `base` to `ours` and to `theirs`
+///
+/// ```
+/// use jsonml::{merge, Element};
+///
+/// let parse = |s| serde_json::from_str::<Element>(s).unwrap();
+/// let base = parse(r#"["ul", {"class": "list"}, ["li", "a"]]"#);
+/// let ours = parse(r#"["ul", {"class": "items"}, ["li", "a"]]"#);
+/// let theirs = parse(r#"["ul", {"class": "list"}, ["li", "a"], ["li", "b"]]"#);
+/// assert_eq!(
+///     merge::merge(&base, &ours, &theirs).unwrap(),
+///     parse(r#"["ul", {"class": "items"}, ["li", "a"], ["li", "b"]]"#)
+/// );
+/// ```
+pub fn merge(base: &Element, ours: &Element, theirs: &Element) -> Result<Element, MergeError> {
+    merge_with_options(base, ours, theirs, &MergeOptions::default())
+}
+
+pub fn merge_with_options(
+    base: &Element,
+    ours: &Element,
+    theirs: &Element,
+    options: &MergeOptions,
+) -> Result<Element, MergeError> {
+    let mut merger = Merger {
+        options,
+        conflicts: vec![],
+        path: vec![],
+    };
+    let element = merger.element(base, ours, theirs);
+    if merger.conflicts.is_empty() {
+        Ok(element)
+    } else {
+        Err(MergeError {
+            conflicts: merger.conflicts,
+        })
+    }
+}
+
+struct Merger<'a> {
+    options: &'a MergeOptions,
+    conflicts: Vec<Conflict>,
+    path: Vec<usize>,
+}
+
+impl Merger<'_> {
+    fn conflict(&mut self, kind: ConflictKind) {
+        self.conflicts.push(Conflict {
+            kind,
+            path: self.path.clone(),
+        });
+    }
+
+    /// Merged element, or the base element if it conflicts
+    #[allow(deprecated)]
+    fn element(&mut self, base: &Element, ours: &Element, theirs: &Element) -> Element {
+        if ours == theirs || theirs == base {
+            return ours.clone();
+        }
+        if ours == base {
+            return theirs.clone();
+        }
+        match (base, ours, theirs) {
+            (Element::String(_), Element::String(ours), Element::String(theirs)) => {
+                self.conflict(ConflictKind::Text {
+                    ours: ours.clone(),
+                    theirs: theirs.clone(),
+                });
+            }
+            (Element::Tag(base), Element::Tag(ours), Element::Tag(theirs))
+                if base.name == ours.name && base.name == theirs.name =>
+            {
+                return Element::Tag(Tag {
+                    name: base.name.clone(),
+                    attributes: self.attributes(
+                        &base.attributes,
+                        &ours.attributes,
+                        &theirs.attributes,
+                    ),
+                    element_list: self.children(
+                        &base.element_list,
+                        &ours.element_list,
+                        &theirs.element_list,
+                    ),
+                });
+            }
+            _ => self.conflict(ConflictKind::Element {
+                ours: Some(ours.clone()),
+                theirs: Some(theirs.clone()),
+            }),
+        }
+        base.clone()
+    }
+
+    fn attributes(
+        &mut self,
+        base: &Attributes,
+        ours: &Attributes,
+        theirs: &Attributes,
+    ) -> Attributes {
+        // Our order comes first, followed by the attributes which only they or the base have.
+        let names: IndexSet<_> = ours
+            .keys()
+            .chain(theirs.keys())
+            .chain(base.keys())
+            .collect();
+        let mut attributes = Attributes::default();
+        for name in names {
+            let (base, ours, theirs) = (base.get(name), ours.get(name), theirs.get(name));
+            let value = if ours == theirs || theirs == base {
+                ours
+            } else if ours == base {
+                theirs
+            } else {
+                self.conflict(ConflictKind::Attribute {
+                    name: name.clone(),
+                    ours: ours.cloned(),
+                    theirs: theirs.cloned(),
+                });
+                base
+            };
+            if let Some(value) = value {
+                attributes.insert(name.clone(), value.clone());
+            }
+        }
+        attributes
+    }
+
+    fn children(&mut self, base: &[Element], ours: &[Element], theirs: &[Element]) -> Vec<Element> {
+        let ours_matches = match_children(base, ours, &self.options.keys);
+        let theirs_matches = match_children(base, theirs, &self.options.keys);
+        let index_in = |matches: &[Option<usize>]| {
+            let mut indices = vec![None; base.len()];
+            for (index, base_index) in matches.iter().enumerate() {
+                if let Some(base_index) = base_index {
+                    indices[*base_index] = Some(index);
+                }
+            }
+            indices
+        };
+        let (in_ours, in_theirs) = (index_in(&ours_matches), index_in(&theirs_matches));
+
+        // Base children kept by both sides
+        let mut kept = vec![false; base.len()];
+        for (index, base_element) in base.iter().enumerate() {
+            let removed = match (in_ours[index], in_theirs[index]) {
+                (Some(_), Some(_)) => {
+                    kept[index] = true;
+                    continue;
+                }
+                (None, None) => continue,
+                (Some(ours_index), None) => (Some(&ours[ours_index]), None),
+                (None, Some(theirs_index)) => (None, Some(&theirs[theirs_index])),
+            };
+            // A removal conflicts with a change on the other side.
+            if let (Some(changed), _) | (_, Some(changed)) = removed {
+                if changed != base_element {
+                    self.path.push(index);
+                    self.conflict(ConflictKind::Element {
+                        ours: removed.0.cloned(),
+                        theirs: removed.1.cloned(),
+                    });
+                    self.path.pop();
+                }
+            }
+        }
+
+        let order_in = |matches: &[Option<usize>]| -> Vec<usize> {
+            matches
+                .iter()
+                .flatten()
+                .copied()
+                .filter(|index| kept[*index])
+                .collect()
+        };
+        let base_order: Vec<_> = (0..base.len()).filter(|index| kept[*index]).collect();
+        let ours_order = order_in(&ours_matches);
+        let theirs_order = order_in(&theirs_matches);
+        let order = if ours_order == base_order {
+            theirs_order
+        } else if theirs_order == base_order || theirs_order == ours_order {
+            ours_order
+        } else {
+            self.conflict(ConflictKind::Order);
+            base_order
+        };
+
+        // Inserted children after each kept base child, or at the start
+        let insertions_in = |side: &'_ [Element], matches: &[Option<usize>]| {
+            let mut insertions: HashMap<Option<usize>, Vec<Element>> = HashMap::default();
+            let mut after = None;
+            for (element, base_index) in side.iter().zip(matches) {
+                match base_index {
+                    Some(base_index) if kept[*base_index] => after = Some(*base_index),
+                    Some(_) => {}
+                    None => insertions.entry(after).or_default().push(element.clone()),
+                }
+            }
+            insertions
+        };
+        let mut ours_insertions = insertions_in(ours, &ours_matches);
+        let mut theirs_insertions = insertions_in(theirs, &theirs_matches);
+
+        let mut children = vec![];
+        let mut insert =
+            |merger: &mut Self, children: &mut Vec<Element>, after: Option<usize>| match (
+                ours_insertions.remove(&after),
+                theirs_insertions.remove(&after),
+            ) {
+                (Some(ours), Some(theirs)) if ours != theirs => {
+                    merger.conflict(ConflictKind::Insert {
+                        after,
+                        ours,
+                        theirs,
+                    })
+                }
+                (Some(inserted), _) | (None, Some(inserted)) => children.extend(inserted),
+                (None, None) => {}
+            };
+        insert(self, &mut children, None);
+        for index in order {
+            self.path.push(index);
+            let element = self.element(
+                &base[index],
+                &ours[in_ours[index].unwrap_or_default()],
+                &theirs[in_theirs[index].unwrap_or_default()],
+            );
+            self.path.pop();
+            children.push(element);
+            insert(self, &mut children, Some(index));
+        }
+        children
+    }
+}
+
+#[cfg(test)]
+use crate::test_helpers::from_json;
+
+#[cfg(test)]
+fn merge_strings(base: &str, ours: &str, theirs: &str) -> Result<Element, MergeError> {
+    let options = MergeOptions {
+        keys: vec!["key".to_string()],
+    };
+    merge_with_options(
+        &from_json(base),
+        &from_json(ours),
+        &from_json(theirs),
+        &options,
+    )
+}
+
+#[test]
+fn test_merge_attributes() {
+    let merged = merge_strings(
+        r#"["p", {"a": "1", "b": "2", "c": "3"}]"#,
+        r#"["p", {"a": "x", "b": "2", "c": "3", "d": "4"}]"#,
+        r#"["p", {"a": "1", "c": "3", "e": true}]"#,
+    );
+    assert_eq!(
+        merged,
+        Ok(from_json(
+            r#"["p", {"a": "x", "c": "3", "d": "4", "e": true}]"#
+        ))
+    );
+
+    let error = merge_strings(
+        r#"["p", {"a": "1", "b": "2"}]"#,
+        r#"["p", {"a": "x"}]"#,
+        r#"["p", {"a": "y", "b": "3"}]"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.conflicts(),
+        [
+            Conflict {
+                kind: ConflictKind::Attribute {
+                    name: "a".to_string(),
+                    ours: Some(AttributeValue::String("x".to_string())),
+                    theirs: Some(AttributeValue::String("y".to_string())),
+                },
+                path: vec![],
+            },
+            Conflict {
+                kind: ConflictKind::Attribute {
+                    name: "b".to_string(),
+                    ours: None,
+                    theirs: Some(AttributeValue::String("3".to_string())),
+                },
+                path: vec![],
+            },
+        ]
+    );
+    assert_eq!(
+        error.to_string(),
+        r#"conflicting changes to attribute "a" at path []; conflicting changes to attribute "b" at path []"#
+    );
+}
+
+#[test]
+fn test_merge_children() {
+    let merged = merge_strings(
+        r#"["ul", ["li", "a"], ["li", "b"], ["li", "c"]]"#,
+        r#"["ul", ["li", "first"], ["li", "a"], ["li", "b"], ["li", "C"]]"#,
+        r#"["ul", ["li", "a"], ["li", "c"], ["li", "last"]]"#,
+    );
+    assert_eq!(
+        merged,
+        Ok(from_json(
+            r#"["ul", ["li", "first"], ["li", "a"], ["li", "C"], ["li", "last"]]"#
+        ))
+    );
+
+    let merged = merge_strings(
+        r#"["ul", ["li", {"key": 1}, "a"], ["li", {"key": 2}, "b"], ["li", {"key": 3}, "c"]]"#,
+        r#"["ul", ["li", {"key": 3}, "c"], ["li", {"key": 1}, "a"], ["li", {"key": 2}, "b"]]"#,
+        r#"["ul", ["li", {"key": 1}, "A"], ["li", {"key": 2}, "b"], ["li", {"key": 3}, "c"], "d"]"#,
+    );
+    assert_eq!(
+        merged,
+        Ok(from_json(
+            r#"["ul", ["li", {"key": 3}, "c"], "d", ["li", {"key": 1}, "A"], ["li", {"key": 2}, "b"]]"#
+        ))
+    );
+}
+
+#[test]
+fn test_merge_conflicts() {
+    let error = merge_strings(r#"["p", "a"]"#, r#"["p", "b"]"#, r#"["p", "c"]"#).unwrap_err();
+    assert_eq!(
+        error.conflicts(),
+        [Conflict {
+            kind: ConflictKind::Text {
+                ours: "b".to_string(),
+                theirs: "c".to_string()
+            },
+            path: vec![0],
+        }]
+    );
+
+    let error = merge_strings(
+        r#"["div", ["p", "a"], ["p", "b"]]"#,
+        r#"["div", ["p", "b"]]"#,
+        r#"["div", ["p", "A"], ["p", "b"]]"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.conflicts(),
+        [Conflict {
+            kind: ConflictKind::Element {
+                ours: None,
+                theirs: Some(from_json(r#"["p", "A"]"#))
+            },
+            path: vec![0],
+        }]
+    );
+
+    let error = merge_strings(
+        r#"["ul", ["li", "a"]]"#,
+        r#"["ul", ["li", "a"], ["li", "b"]]"#,
+        r#"["ul", ["li", "a"], ["li", "c"]]"#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.conflicts(),
+        [Conflict {
+            kind: ConflictKind::Insert {
+                after: Some(0),
+                ours: vec![from_json(r#"["li", "b"]"#)],
+                theirs: vec![from_json(r#"["li", "c"]"#)]
+            },
+            path: vec![],
+        }]
+    );
+
+    let error = merge_strings(
+        r#"["ul", ["li", {"key": 1}], ["li", {"key": 2}], ["li", {"key": 3}]]"#,
+        r#"["ul", ["li", {"key": 2}], ["li", {"key": 1}], ["li", {"key": 3}]]"#,
+        r#"["ul", ["li", {"key": 1}], ["li", {"key": 3}], ["li", {"key": 2}]]"#,
+    )
+    .unwrap_err();
+    assert_eq!(error.conflicts()[0].kind(), &ConflictKind::Order);
+
+    let error = merge_strings(r#"["p"]"#, r#"["div"]"#, r#""p""#).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "conflicting changes to element at path []"
+    );
+}
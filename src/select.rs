@@ -0,0 +1,914 @@
+//! CSS selector querying over JsonML elements
+//!
+//! Supported selectors are
+//!
+//! * type selectors and the universal selector `*`
+//! * ID selectors `#id` and class selectors `.class`
+//! * attribute selectors with `=`, `~=`, `|=`, `^=`, `$=` and `*=`
+//! * descendant, child (`>`), next-sibling (`+`) and subsequent-sibling (`~`) combinators
+//! * `:first-child`, `:last-child`, `:only-child`, `:nth-child()`, `:nth-last-child()`
+//! * `:first-of-type`, `:last-of-type`, `:only-of-type`, `:nth-of-type()`, `:nth-last-of-type()`
+//! * `:not()` with a selector list
+//!
+//! Names are compared exactly.
+//! Attribute values are matched as they are rendered in HTML:
+//! `true` and null are empty values, and `false` is an absent attribute.
+
+use std::{borrow::Cow, error::Error, fmt, fmt::Display, str::FromStr};
+
+use crate::{html, AttributeValue, Element, Tag};
+
+/// Parsed selector list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    complexes: Vec<Complex>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Complex {
+    /// Compound selectors from left to right
+    compounds: Vec<Compound>,
+    /// Combinators between the compound selectors
+    combinators: Vec<Combinator>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+    NextSibling,
+    SubsequentSibling,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Compound {
+    /// Type name, or none for the universal selector
+    name: Option<String>,
+    simples: Vec<Simple>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Simple {
+    Id(String),
+    Class(String),
+    Attribute {
+        name: String,
+        operator: Option<(AttributeOperator, String)>,
+    },
+    Nth {
+        nth: Nth,
+        of_type: bool,
+        from_end: bool,
+    },
+    Only {
+        of_type: bool,
+    },
+    Not(Selector),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttributeOperator {
+    Equal,
+    Includes,
+    DashMatch,
+    Prefix,
+    Suffix,
+    Substring,
+}
+
+/// `An+B`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Nth {
+    a: i64,
+    b: i64,
+}
+
+impl Nth {
+    fn matches(self, position: i64) -> bool {
+        let Nth { a, b } = self;
+        if a == 0 {
+            position == b
+        } else {
+            // Wide enough that `A` and `B` anywhere in `i64` cannot overflow.
+            let (a, n) = (i128::from(a), i128::from(position) - i128::from(b));
+            n % a == 0 && n / a >= 0
+        }
+    }
+}
+
+/// Selector syntax error with its position in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    position: usize,
+}
+
+impl ParseError {
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// Byte offset in the input
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.position)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    UnknownPseudoClass(String),
+    InvalidNth(String),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            ParseErrorKind::UnknownPseudoClass(name) => write!(f, "unknown pseudo-class :{name}"),
+            ParseErrorKind::InvalidNth(s) => write!(f, "invalid An+B expression {s:?}"),
+        }
+    }
+}
+
+impl Selector {
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser { input, pos: 0 };
+        let selector = parser.selector_list()?;
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => Ok(selector),
+            Some(_) => Err(parser.unexpected()),
+        }
+    }
+
+    /// Whether the element itself matches, as the root without a parent or siblings
+    ///
+    /// Text never matches. Use [`Selector::first`] to look for matching descendants.
+    pub fn matches(&self, element: &Element) -> bool {
+        match element {
+            Element::Tag(tag) => self.matches_node(&Node {
+                tag,
+                index: 0,
+                parent: None,
+            }),
+            Element::String(_) => false,
+        }
+    }
+
+    /// Matching tags in the element, including itself, in document order
+    pub fn all<'a>(&self, element: &'a Element) -> Vec<&'a Tag> {
+        let mut tags = vec![];
+        self.visit(element, &mut |tag| {
+            tags.push(tag);
+            false
+        });
+        tags
+    }
+
+    /// First matching tag in the element, including itself, in document order
+    pub fn first<'a>(&self, element: &'a Element) -> Option<&'a Tag> {
+        let mut first = None;
+        self.visit(element, &mut |tag| {
+            first = Some(tag);
+            true
+        });
+        first
+    }
+
+    /// Call `found` with matching tags until it returns true.
+    fn visit<'a>(&self, element: &'a Element, found: &mut dyn FnMut(&'a Tag) -> bool) {
+        if let Element::Tag(tag) = element {
+            self.visit_tag(tag, 0, None, found);
+        }
+    }
+
+    #[allow(deprecated)]
+    fn visit_tag<'a>(
+        &self,
+        tag: &'a Tag,
+        index: usize,
+        parent: Option<&Node>,
+        found: &mut dyn FnMut(&'a Tag) -> bool,
+    ) -> bool {
+        let node = Node { tag, index, parent };
+        if self.matches_node(&node) && found(tag) {
+            return true;
+        }
+        for (index, child) in tag.element_list.iter().enumerate() {
+            if let Element::Tag(child) = child {
+                if self.visit_tag(child, index, Some(&node), found) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn matches_node(&self, node: &Node) -> bool {
+        self.complexes
+            .iter()
+            .any(|complex| complex.matches(node, complex.compounds.len() - 1))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Selector::parse(s)
+    }
+}
+
+/// Tag with its position in the tree
+struct Node<'a> {
+    tag: &'a Tag,
+    /// Index in the parent's element list
+    index: usize,
+    parent: Option<&'a Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    #[allow(deprecated)]
+    fn siblings(&self) -> &'a [Element] {
+        match self.parent {
+            Some(parent) => &parent.tag.element_list,
+            None => &[],
+        }
+    }
+
+    /// Tag siblings before this tag, nearest first
+    fn previous_siblings(&self) -> impl Iterator<Item = (usize, &'a Tag)> {
+        let siblings = self.siblings();
+        siblings[..self.index.min(siblings.len())]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(index, element)| match element {
+                Element::Tag(tag) => Some((index, tag)),
+                Element::String(_) => None,
+            })
+    }
+
+    /// Tag siblings after this tag
+    fn next_siblings(&self) -> impl Iterator<Item = &'a Tag> {
+        let siblings = self.siblings();
+        siblings
+            .get(self.index + 1..)
+            .unwrap_or(&[])
+            .iter()
+            .filter_map(|element| match element {
+                Element::Tag(tag) => Some(tag),
+                Element::String(_) => None,
+            })
+    }
+
+    /// Position among tag siblings, starting from 1
+    #[allow(deprecated)]
+    fn position(&self, of_type: bool, from_end: bool) -> i64 {
+        let same = |tag: &Tag| !of_type || tag.name == self.tag.name;
+        let count = if from_end {
+            self.next_siblings().filter(|tag| same(tag)).count()
+        } else {
+            self.previous_siblings()
+                .filter(|(_, tag)| same(tag))
+                .count()
+        };
+        count as i64 + 1
+    }
+}
+
+impl Complex {
+    fn matches(&self, node: &Node, index: usize) -> bool {
+        if !self.compounds[index].matches(node) {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+        let index = index - 1;
+        match self.combinators[index] {
+            Combinator::Child => node
+                .parent
+                .map_or(false, |parent| self.matches(parent, index)),
+            Combinator::Descendant => {
+                let mut ancestor = node.parent;
+                while let Some(node) = ancestor {
+                    if self.matches(node, index) {
+                        return true;
+                    }
+                    ancestor = node.parent;
+                }
+                false
+            }
+            Combinator::NextSibling => node
+                .previous_siblings()
+                .next()
+                .map_or(false, |(i, tag)| self.matches(&node.sibling(i, tag), index)),
+            Combinator::SubsequentSibling => node
+                .previous_siblings()
+                .any(|(i, tag)| self.matches(&node.sibling(i, tag), index)),
+        }
+    }
+}
+
+impl<'a> Node<'a> {
+    fn sibling(&self, index: usize, tag: &'a Tag) -> Node<'a> {
+        Node {
+            tag,
+            index,
+            parent: self.parent,
+        }
+    }
+}
+
+impl Compound {
+    #[allow(deprecated)]
+    fn matches(&self, node: &Node) -> bool {
+        if let Some(name) = &self.name {
+            if &node.tag.name != name {
+                return false;
+            }
+        }
+        self.simples.iter().all(|simple| simple.matches(node))
+    }
+}
+
+impl Simple {
+    fn matches(&self, node: &Node) -> bool {
+        match self {
+            Simple::Id(id) => attribute(node.tag, "id").map_or(false, |value| value == *id),
+            Simple::Class(class) => attribute(node.tag, "class").map_or(false, |value| {
+                value.split(html::is_html_whitespace).any(|c| c == class)
+            }),
+            Simple::Attribute { name, operator } => {
+                let value = match attribute(node.tag, name) {
+                    Some(value) => value,
+                    None => return false,
+                };
+                let (operator, expected) = match operator {
+                    Some(operator) => operator,
+                    None => return true,
+                };
+                let expected = expected.as_str();
+                match operator {
+                    AttributeOperator::Equal => value == expected,
+                    AttributeOperator::Includes => {
+                        !expected.is_empty()
+                            && value.split(html::is_html_whitespace).any(|v| v == expected)
+                    }
+                    AttributeOperator::DashMatch => {
+                        value == expected
+                            || value
+                                .strip_prefix(expected)
+                                .map_or(false, |rest| rest.starts_with('-'))
+                    }
+                    AttributeOperator::Prefix => {
+                        !expected.is_empty() && value.starts_with(expected)
+                    }
+                    AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(expected),
+                    AttributeOperator::Substring => {
+                        !expected.is_empty() && value.contains(expected)
+                    }
+                }
+            }
+            Simple::Nth {
+                nth,
+                of_type,
+                from_end,
+            } => nth.matches(node.position(*of_type, *from_end)),
+            Simple::Only { of_type } => {
+                node.position(*of_type, false) == 1 && node.position(*of_type, true) == 1
+            }
+            Simple::Not(selector) => !selector.matches_node(node),
+        }
+    }
+}
+
+/// Attribute value as rendered in HTML, or none if it is absent
+#[allow(deprecated)]
+fn attribute<'a>(tag: &'a Tag, name: &str) -> Option<Cow<'a, str>> {
+    match tag.attributes.get(name)? {
+        AttributeValue::String(s) => Some(Cow::Borrowed(s)),
+        AttributeValue::Number(n) => Some(Cow::Owned(html::format_number(*n))),
+        AttributeValue::Bool(true) | AttributeValue::Null => Some(Cow::Borrowed("")),
+        AttributeValue::Bool(false) => None,
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error_at(&self, position: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { kind, position }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(c) => self.error_at(self.pos, ParseErrorKind::UnexpectedChar(c)),
+            None => self.error_at(self.pos, ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Skip whitespace and return whether there was any.
+    fn skip_whitespace(&mut self) -> bool {
+        let trimmed = self.rest().trim_start_matches(html::is_html_whitespace);
+        let skipped = self.rest().len() - trimmed.len();
+        self.pos += skipped;
+        skipped > 0
+    }
+
+    fn selector_list(&mut self) -> Result<Selector, ParseError> {
+        let mut complexes = vec![];
+        loop {
+            self.skip_whitespace();
+            complexes.push(self.complex()?);
+            self.skip_whitespace();
+            if !self.eat(',') {
+                return Ok(Selector { complexes });
+            }
+        }
+    }
+
+    fn complex(&mut self) -> Result<Complex, ParseError> {
+        let mut compounds = vec![self.compound()?];
+        let mut combinators = vec![];
+        loop {
+            let whitespace = self.skip_whitespace();
+            let combinator = match self.peek() {
+                Some('>') => Combinator::Child,
+                Some('+') => Combinator::NextSibling,
+                Some('~') => Combinator::SubsequentSibling,
+                Some(',' | ')') | None => {
+                    return Ok(Complex {
+                        compounds,
+                        combinators,
+                    })
+                }
+                Some(_) if whitespace => Combinator::Descendant,
+                Some(_) => return Err(self.unexpected()),
+            };
+            if combinator != Combinator::Descendant {
+                self.pos += 1;
+                self.skip_whitespace();
+            }
+            combinators.push(combinator);
+            compounds.push(self.compound()?);
+        }
+    }
+
+    fn compound(&mut self) -> Result<Compound, ParseError> {
+        let mut compound = Compound::default();
+        let mut empty = true;
+        if self.eat('*') {
+            empty = false;
+        } else if self.starts_ident() {
+            compound.name = Some(self.ident()?);
+            empty = false;
+        }
+        loop {
+            let simple = match self.peek() {
+                Some('#') => {
+                    self.pos += 1;
+                    Simple::Id(self.ident()?)
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    Simple::Class(self.ident()?)
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    self.attribute()?
+                }
+                Some(':') => {
+                    self.pos += 1;
+                    self.pseudo_class()?
+                }
+                _ if empty => return Err(self.unexpected()),
+                _ => return Ok(compound),
+            };
+            compound.simples.push(simple);
+            empty = false;
+        }
+    }
+
+    fn attribute(&mut self) -> Result<Simple, ParseError> {
+        self.skip_whitespace();
+        let name = self.ident()?;
+        self.skip_whitespace();
+        let operator = match self.peek() {
+            Some(']') => None,
+            Some('=') => Some(AttributeOperator::Equal),
+            Some('~') => Some(AttributeOperator::Includes),
+            Some('|') => Some(AttributeOperator::DashMatch),
+            Some('^') => Some(AttributeOperator::Prefix),
+            Some('$') => Some(AttributeOperator::Suffix),
+            Some('*') => Some(AttributeOperator::Substring),
+            _ => return Err(self.unexpected()),
+        };
+        let operator = match operator {
+            Some(operator) => {
+                self.pos += 1;
+                if operator != AttributeOperator::Equal {
+                    self.expect('=')?;
+                }
+                self.skip_whitespace();
+                let value = match self.peek() {
+                    Some(quote @ ('"' | '\'')) => {
+                        self.pos += 1;
+                        self.string(quote)?
+                    }
+                    _ => self.ident()?,
+                };
+                self.skip_whitespace();
+                Some((operator, value))
+            }
+            None => None,
+        };
+        self.expect(']')?;
+        Ok(Simple::Attribute { name, operator })
+    }
+
+    fn pseudo_class(&mut self) -> Result<Simple, ParseError> {
+        let start = self.pos;
+        let name = self.ident()?.to_ascii_lowercase();
+        let nth = |a, b, of_type, from_end| Simple::Nth {
+            nth: Nth { a, b },
+            of_type,
+            from_end,
+        };
+        let simple = match name.as_str() {
+            "first-child" => nth(0, 1, false, false),
+            "last-child" => nth(0, 1, false, true),
+            "first-of-type" => nth(0, 1, true, false),
+            "last-of-type" => nth(0, 1, true, true),
+            "only-child" => Simple::Only { of_type: false },
+            "only-of-type" => Simple::Only { of_type: true },
+            "nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type" => {
+                self.expect('(')?;
+                let argument_start = self.pos;
+                let argument = match self.rest().find(')') {
+                    Some(end) => &self.rest()[..end],
+                    None => {
+                        return Err(self.error_at(self.input.len(), ParseErrorKind::UnexpectedEof))
+                    }
+                };
+                let Nth { a, b } = parse_nth(argument).ok_or_else(|| {
+                    self.error_at(
+                        argument_start,
+                        ParseErrorKind::InvalidNth(argument.trim().to_string()),
+                    )
+                })?;
+                self.pos += argument.len() + 1;
+                nth(a, b, name.ends_with("of-type"), name.contains("last"))
+            }
+            "not" => {
+                self.expect('(')?;
+                let selector = self.selector_list()?;
+                self.expect(')')?;
+                Simple::Not(selector)
+            }
+            _ => return Err(self.error_at(start, ParseErrorKind::UnknownPseudoClass(name))),
+        };
+        Ok(simple)
+    }
+
+    fn starts_ident(&self) -> bool {
+        let mut chars = self.rest().chars();
+        match chars.next() {
+            Some('-') => {
+                matches!(chars.next(), Some(c) if is_name_start_char(c) || c == '-' || c == '\\')
+            }
+            Some(c) => is_name_start_char(c) || c == '\\',
+            None => false,
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        if !self.starts_ident() {
+            return Err(self.unexpected());
+        }
+        let mut ident = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\\' {
+                self.pos += 1;
+                ident.push(self.escape()?);
+            } else if is_name_start_char(c) || c.is_ascii_digit() || c == '-' {
+                self.pos += c.len_utf8();
+                ident.push(c);
+            } else {
+                break;
+            }
+        }
+        Ok(ident)
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, ParseError> {
+        let mut string = String::new();
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    self.pos += 1;
+                    string.push(self.escape()?);
+                }
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(string);
+                }
+                Some('\n') | None => return Err(self.unexpected()),
+                Some(c) => {
+                    self.pos += c.len_utf8();
+                    string.push(c);
+                }
+            }
+        }
+    }
+
+    /// Escape after a backslash: up to 6 hex digits followed by an optional space, or a
+    /// character
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let hex_len = self
+            .rest()
+            .chars()
+            .take(6)
+            .take_while(char::is_ascii_hexdigit)
+            .count();
+        if hex_len == 0 {
+            return match self.peek() {
+                Some('\n') | None => Err(self.unexpected()),
+                Some(c) => {
+                    self.pos += c.len_utf8();
+                    Ok(c)
+                }
+            };
+        }
+        let code = u32::from_str_radix(&self.rest()[..hex_len], 16).unwrap_or(0);
+        self.pos += hex_len;
+        self.eat(' ');
+        Ok(match char::from_u32(code) {
+            Some('\0') | None => char::REPLACEMENT_CHARACTER,
+            Some(c) => c,
+        })
+    }
+}
+
+fn is_name_start_char(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+/// Parse `An+B`, `odd` or `even`.
+///
+/// Whitespace is allowed at the ends and around the sign of `B`, as in `2n + 1`.
+// https://www.w3.org/TR/css-syntax-3/#anb-microsyntax
+fn parse_nth(s: &str) -> Option<Nth> {
+    let s = s
+        .trim_matches(html::is_html_whitespace)
+        .to_ascii_lowercase();
+    match s.as_str() {
+        "odd" => return Some(Nth { a: 2, b: 1 }),
+        "even" => return Some(Nth { a: 2, b: 0 }),
+        _ => {}
+    }
+    let integer = |s: &str| {
+        let digits = s.strip_prefix(&['+', '-'][..]).unwrap_or(s);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        s.parse::<i64>().ok()
+    };
+    match s.split_once('n') {
+        Some((a, b)) => {
+            let a = match a {
+                "" | "+" => 1,
+                "-" => -1,
+                a => integer(a)?,
+            };
+            let b = b.trim_start_matches(html::is_html_whitespace);
+            let b = match b.chars().next() {
+                None => 0,
+                Some(sign @ ('+' | '-')) => {
+                    let digits = b[1..].trim_start_matches(html::is_html_whitespace);
+                    integer(&format!("{sign}{digits}"))?
+                }
+                Some(_) => return None,
+            };
+            Some(Nth { a, b })
+        }
+        None => Some(Nth {
+            a: 0,
+            b: integer(&s)?,
+        }),
+    }
+}
+
+impl Element {
+    /// Tags matching the CSS selector list in the element, including itself, in document order
+    ///
+    /// ```
+    /// use jsonml::Element;
+    ///
+    /// let element: Element =
+    ///     serde_json::from_str(r#"["ul", ["li", {"class": "a b"}, "1"], ["li", "2"]]"#).unwrap();
+    /// let tags = element.select("ul > li:not(.a)").unwrap();
+    /// assert_eq!(tags.len(), 1);
+    /// assert_eq!(tags[0].element_list(), [Element::String("2".to_string())]);
+    /// ```
+    pub fn select(&self, selectors: &str) -> Result<Vec<&Tag>, ParseError> {
+        Ok(Selector::parse(selectors)?.all(self))
+    }
+
+    /// First tag matching the CSS selector list in the element, including itself, in document
+    /// order
+    pub fn select_first(&self, selectors: &str) -> Result<Option<&Tag>, ParseError> {
+        Ok(Selector::parse(selectors)?.first(self))
+    }
+}
+
+#[cfg(test)]
+use crate::test_helpers::{from_json, text};
+
+#[cfg(test)]
+fn sample() -> Element {
+    from_json(
+        r##"["div", {"id": "main"},
+            ["h1", "Title"],
+            ["p", {"class": "lead intro"}, "a ", ["a", {"href": "https://example.com"}, "link"]],
+            "text",
+            ["p", ["a", {"href": "#top", "hreflang": "en-US"}, "top"]],
+            ["ul",
+                ["li", "1"], ["li", {"data-x": true}, "2"], ["li", "3"], ["li", "4"]]]"##,
+    )
+}
+
+#[cfg(test)]
+fn select_texts(element: &Element, selectors: &str) -> Vec<String> {
+    element
+        .select(selectors)
+        .unwrap()
+        .into_iter()
+        .map(|tag| Element::Tag(tag.clone()).texts().collect::<String>())
+        .collect()
+}
+
+#[test]
+fn test_select_simple() {
+    let element = sample();
+    assert_eq!(select_texts(&element, "h1"), ["Title"]);
+    assert_eq!(select_texts(&element, "#main").len(), 1);
+    assert_eq!(select_texts(&element, ".intro"), ["a link"]);
+    assert_eq!(select_texts(&element, "p.lead.intro"), ["a link"]);
+    assert_eq!(select_texts(&element, "[href^=http]"), ["link"]);
+    assert_eq!(select_texts(&element, "a[href$='top']"), ["top"]);
+    assert_eq!(select_texts(&element, "[href*=\"example\"]"), ["link"]);
+    assert_eq!(select_texts(&element, "[hreflang|=en]"), ["top"]);
+    assert_eq!(select_texts(&element, "[class~=lead]"), ["a link"]);
+    assert_eq!(select_texts(&element, "li[data-x]"), ["2"]);
+    assert_eq!(select_texts(&element, "li[data-x='']"), ["2"]);
+    assert_eq!(select_texts(&element, "*").len(), 11);
+}
+
+#[test]
+fn test_select_combinators() {
+    let element = sample();
+    assert_eq!(select_texts(&element, "div a"), ["link", "top"]);
+    assert_eq!(select_texts(&element, "div > a"), Vec::<String>::new());
+    assert_eq!(select_texts(&element, "p > a"), ["link", "top"]);
+    assert_eq!(select_texts(&element, "h1 + p"), ["a link"]);
+    assert_eq!(select_texts(&element, "h1 ~ p"), ["a link", "top"]);
+    assert_eq!(select_texts(&element, "li + li + li"), ["3", "4"]);
+    assert_eq!(
+        select_texts(&element, "h1, ul > li:first-child"),
+        ["Title", "1"]
+    );
+}
+
+#[test]
+fn test_select_pseudo_classes() {
+    let element = sample();
+    assert_eq!(select_texts(&element, "li:nth-child(odd)"), ["1", "3"]);
+    assert_eq!(select_texts(&element, "li:nth-child(2n)"), ["2", "4"]);
+    assert_eq!(select_texts(&element, "li:nth-child(-n + 2)"), ["1", "2"]);
+    assert_eq!(select_texts(&element, "li:nth-child( 2n- 1 )"), ["1", "3"]);
+    assert_eq!(select_texts(&element, "li:nth-child(2N -1)"), ["1", "3"]);
+    assert_eq!(
+        select_texts(&element, "li:nth-child(n-9223372036854775808)"),
+        ["1", "2", "3", "4"]
+    );
+    assert_eq!(
+        select_texts(&element, "li:nth-child(-n-9223372036854775807)"),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        select_texts(&element, "li:nth-child(-9223372036854775808n+3)"),
+        ["3"]
+    );
+    assert_eq!(select_texts(&element, "li:nth-last-child(1)"), ["4"]);
+    assert_eq!(select_texts(&element, "li:last-child"), ["4"]);
+    assert_eq!(select_texts(&element, "p:first-of-type"), ["a link"]);
+    assert_eq!(select_texts(&element, "p:last-of-type"), ["top"]);
+    assert_eq!(select_texts(&element, "div > :nth-of-type(2)"), ["top"]);
+    assert_eq!(select_texts(&element, "a:only-child"), ["link", "top"]);
+    assert_eq!(select_texts(&element, "h1:only-of-type"), ["Title"]);
+    assert_eq!(
+        select_texts(&element, "li:not([data-x], :first-child)"),
+        ["3", "4"]
+    );
+    assert_eq!(select_texts(&element, "p:not(h1 + p)"), ["top"]);
+    assert_eq!(
+        select_texts(&element, "div:first-child"),
+        ["Titlea linktexttop1234"]
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_select_first() {
+    let element = sample();
+    let tag = element.select_first("li:nth-child(n+3)").unwrap().unwrap();
+    assert_eq!(tag.element_list, [Element::String("3".to_string())]);
+    assert_eq!(element.select_first("table").unwrap(), None);
+    assert_eq!(
+        Element::String("p".to_string()).select("p").unwrap(),
+        Vec::<&Tag>::new()
+    );
+}
+
+#[test]
+fn test_selector_matches() {
+    let element = sample();
+    assert!(Selector::parse("div#main:first-child")
+        .unwrap()
+        .matches(&element));
+    assert!(!Selector::parse("li").unwrap().matches(&element));
+    assert!(!Selector::parse("p > div").unwrap().matches(&element));
+    assert!(!Selector::parse("*").unwrap().matches(&text("div")));
+}
+
+#[test]
+fn test_select_parse_errors() {
+    let error = |s: &str| Selector::parse(s).unwrap_err();
+    assert_eq!(error("").kind(), &ParseErrorKind::UnexpectedEof);
+    assert_eq!(error("p >").kind(), &ParseErrorKind::UnexpectedEof);
+    assert_eq!(error("p, ,a").kind(), &ParseErrorKind::UnexpectedChar(','));
+    assert_eq!(error("[href").kind(), &ParseErrorKind::UnexpectedEof);
+    assert_eq!(error("a[href=]").position(), 7);
+    assert_eq!(
+        error("p:hover").to_string(),
+        "unknown pseudo-class :hover at position 2"
+    );
+    assert_eq!(
+        error("li:nth-child(2x)").kind(),
+        &ParseErrorKind::InvalidNth("2x".to_string())
+    );
+    for nth in [
+        "- n+1", "+ n", "2 n", "2n+-1", "2n + +1", "2n 1", "+ 1", "1 0",
+    ] {
+        assert_eq!(
+            error(&format!("li:nth-child({nth})")).kind(),
+            &ParseErrorKind::InvalidNth(nth.to_string())
+        );
+    }
+    assert_eq!(
+        Selector::parse(r"#a\:b").unwrap(),
+        "#a\\3A b".parse().unwrap()
+    );
+}
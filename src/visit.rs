@@ -0,0 +1,443 @@
+//! Traversal of elements with closures
+//!
+//! The callbacks receive a [`Context`] with the depth and the ancestors of the visited element.
+//! The mutable traversals let the callbacks edit the tree with [`Edit`], which removes an
+//! element, replaces it with several elements or unwraps it into its children.
+
+use std::collections::VecDeque;
+
+use void::{ResultVoidExt, Void};
+
+use crate::{Attributes, Element, Tag};
+
+/// Order of visiting elements
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Visit an element before its children (pre-order).
+    TopDown,
+
+    /// Visit an element after its children (post-order).
+    BottomUp,
+}
+
+/// What to do with the visited element
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum Edit {
+    /// Keep the element, including changes made through the mutable reference.
+    #[default]
+    Keep,
+
+    Remove,
+
+    /// Replace the element with elements.
+    ///
+    /// In [`Order::TopDown`], the children of the new elements are visited but the new elements
+    /// themselves are not.
+    Replace(Vec<Element>),
+
+    /// Replace the element with its children, or remove it if it is a string.
+    ///
+    /// In [`Order::TopDown`], the children are visited in place of the element.
+    Unwrap,
+}
+
+/// Information on the visited element's position in the tree
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    depth: usize,
+    parent: Option<&'a Ancestor<'a>>,
+}
+
+impl<'a> Context<'a> {
+    /// Depth of the element, 0 for the elements of the visited list
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn parent(&self) -> Option<&'a Ancestor<'a>> {
+        self.parent
+    }
+
+    /// Ancestors from the parent up to the root
+    pub fn ancestors(&self) -> Ancestors<'a> {
+        Ancestors { next: self.parent }
+    }
+}
+
+/// Ancestor of the visited element
+#[derive(Debug, Clone, Copy)]
+pub struct Ancestor<'a> {
+    name: &'a str,
+    attributes: &'a Attributes,
+    parent: Option<&'a Ancestor<'a>>,
+}
+
+impl<'a> Ancestor<'a> {
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    pub fn attributes(&self) -> &'a Attributes {
+        self.attributes
+    }
+}
+
+/// Iterator over ancestors, nearest first
+#[derive(Debug, Clone)]
+pub struct Ancestors<'a> {
+    next: Option<&'a Ancestor<'a>>,
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = &'a Ancestor<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ancestor = self.next?;
+        self.next = ancestor.parent;
+        Some(ancestor)
+    }
+}
+
+/// Visit the elements and their descendants
+pub fn visit<F>(elements: &[Element], order: Order, mut function: F)
+where
+    F: FnMut(&Element, &Context),
+{
+    try_visit(elements, order, |element, context| {
+        function(element, context);
+        Ok::<_, Void>(())
+    })
+    .void_unwrap()
+}
+
+/// Visit the elements and their descendants, stopping at the first error
+pub fn try_visit<F, E>(elements: &[Element], order: Order, mut function: F) -> Result<(), E>
+where
+    F: FnMut(&Element, &Context) -> Result<(), E>,
+{
+    try_visit_list(elements, order, &mut function, 0, None)
+}
+
+#[allow(deprecated)]
+fn try_visit_list<F, E>(
+    elements: &[Element],
+    order: Order,
+    function: &mut F,
+    depth: usize,
+    parent: Option<&Ancestor>,
+) -> Result<(), E>
+where
+    F: FnMut(&Element, &Context) -> Result<(), E>,
+{
+    let context = Context { depth, parent };
+    for element in elements {
+        if order == Order::TopDown {
+            function(element, &context)?;
+        }
+        if let Element::Tag(Tag {
+            name,
+            attributes,
+            element_list,
+        }) = element
+        {
+            let ancestor = Ancestor {
+                name,
+                attributes,
+                parent,
+            };
+            try_visit_list(element_list, order, function, depth + 1, Some(&ancestor))?;
+        }
+        if order == Order::BottomUp {
+            function(element, &context)?;
+        }
+    }
+    Ok(())
+}
+
+/// Visit the elements and their descendants mutably, applying the returned edits
+pub fn visit_mut<F>(elements: &mut Vec<Element>, order: Order, mut function: F)
+where
+    F: FnMut(&mut Element, &Context) -> Edit,
+{
+    try_visit_mut(elements, order, |element, context| {
+        Ok::<_, Void>(function(element, context))
+    })
+    .void_unwrap()
+}
+
+/// Visit the elements and their descendants mutably, applying the returned edits and stopping at
+/// the first error
+///
+/// The edits made before the error are kept.
+pub fn try_visit_mut<F, E>(
+    elements: &mut Vec<Element>,
+    order: Order,
+    mut function: F,
+) -> Result<(), E>
+where
+    F: FnMut(&mut Element, &Context) -> Result<Edit, E>,
+{
+    try_visit_list_mut(elements, order, &mut function, 0, None)
+}
+
+#[allow(deprecated)]
+fn try_visit_list_mut<F, E>(
+    elements: &mut Vec<Element>,
+    order: Order,
+    function: &mut F,
+    depth: usize,
+    parent: Option<&Ancestor>,
+) -> Result<(), E>
+where
+    F: FnMut(&mut Element, &Context) -> Result<Edit, E>,
+{
+    let context = Context { depth, parent };
+    // Rebuild the list so that edits take linear time.
+    let mut pending = VecDeque::from(std::mem::take(elements));
+    while let Some(mut element) = pending.pop_front() {
+        let edit = match order {
+            Order::TopDown => function(&mut element, &context),
+            Order::BottomUp => try_visit_children_mut(&mut element, order, function, depth, parent)
+                .and_then(|()| function(&mut element, &context)),
+        };
+        let edit = match edit {
+            Ok(edit) => edit,
+            Err(error) => {
+                elements.push(element);
+                elements.extend(pending);
+                return Err(error);
+            }
+        };
+        let mut replacement = match edit {
+            Edit::Keep => vec![element],
+            Edit::Remove => vec![],
+            Edit::Replace(replacement) => replacement,
+            Edit::Unwrap => {
+                let children = match element {
+                    Element::Tag(tag) => tag.element_list,
+                    Element::String(_) => vec![],
+                };
+                if order == Order::TopDown {
+                    for child in children.into_iter().rev() {
+                        pending.push_front(child);
+                    }
+                    continue;
+                }
+                children
+            }
+        };
+        if order == Order::TopDown {
+            for index in 0..replacement.len() {
+                if let Err(error) =
+                    try_visit_children_mut(&mut replacement[index], order, function, depth, parent)
+                {
+                    elements.extend(replacement);
+                    elements.extend(pending);
+                    return Err(error);
+                }
+            }
+        }
+        elements.extend(replacement);
+    }
+    Ok(())
+}
+
+#[allow(deprecated)]
+fn try_visit_children_mut<F, E>(
+    element: &mut Element,
+    order: Order,
+    function: &mut F,
+    depth: usize,
+    parent: Option<&Ancestor>,
+) -> Result<(), E>
+where
+    F: FnMut(&mut Element, &Context) -> Result<Edit, E>,
+{
+    match element {
+        Element::Tag(Tag {
+            name,
+            attributes,
+            element_list,
+        }) => {
+            let ancestor = Ancestor {
+                name,
+                attributes,
+                parent,
+            };
+            try_visit_list_mut(element_list, order, function, depth + 1, Some(&ancestor))
+        }
+        Element::String(_) => Ok(()),
+    }
+}
+
+impl Element {
+    /// Transform the element and its descendants, returning the elements which the element
+    /// became
+    ///
+    /// ```
+    /// use jsonml::{visit::{Edit, Order}, Element};
+    ///
+    /// let element: Element = serde_json::from_str(r#"["p", ["span", "text"]]"#).unwrap();
+    /// let elements = element.transform(Order::BottomUp, |element, _context| match element {
+    ///     Element::Tag(tag) if tag.name() == "span" => Edit::Unwrap,
+    ///     _ => Edit::Keep,
+    /// });
+    /// assert_eq!(elements, vec![serde_json::from_str(r#"["p", "text"]"#).unwrap()]);
+    /// ```
+    pub fn transform<F>(self, order: Order, function: F) -> Vec<Element>
+    where
+        F: FnMut(&mut Element, &Context) -> Edit,
+    {
+        let mut elements = vec![self];
+        visit_mut(&mut elements, order, function);
+        elements
+    }
+
+    /// Transform the element and its descendants, stopping at the first error
+    pub fn try_transform<F, E>(self, order: Order, function: F) -> Result<Vec<Element>, E>
+    where
+        F: FnMut(&mut Element, &Context) -> Result<Edit, E>,
+    {
+        let mut elements = vec![self];
+        try_visit_mut(&mut elements, order, function)?;
+        Ok(elements)
+    }
+}
+
+#[cfg(test)]
+use crate::test_helpers::{tag, text};
+
+#[test]
+#[allow(deprecated)]
+fn test_visit_order_and_context() {
+    let elements = vec![tag(
+        "a",
+        &[],
+        vec![tag("b", &[], vec![text("c")]), text("d")],
+    )];
+    let mut visited = vec![];
+    visit(&elements, Order::TopDown, |element, context| {
+        let name = match element {
+            Element::Tag(tag) => tag.name.clone(),
+            Element::String(s) => s.clone(),
+        };
+        let ancestors = context
+            .ancestors()
+            .map(|ancestor| ancestor.name())
+            .collect::<Vec<_>>()
+            .join("<");
+        visited.push(format!("{name}:{}:{ancestors}", context.depth()));
+    });
+    assert_eq!(visited, ["a:0:", "b:1:a", "c:2:b<a", "d:1:a"]);
+
+    let mut visited = vec![];
+    visit(&elements, Order::BottomUp, |element, _| {
+        if let Element::String(s) = element {
+            visited.push(s.clone());
+        } else if let Element::Tag(tag) = element {
+            visited.push(tag.name.clone());
+        }
+    });
+    assert_eq!(visited, ["c", "b", "d", "a"]);
+}
+
+#[test]
+fn test_try_visit() {
+    let elements = vec![tag("a", &[], vec![text("b"), text("c")])];
+    let mut count = 0;
+    let result = try_visit(&elements, Order::TopDown, |element, _| {
+        count += 1;
+        match element {
+            Element::String(s) => Err(s.clone()),
+            Element::Tag(_) => Ok(()),
+        }
+    });
+    assert_eq!(result, Err("b".to_string()));
+    assert_eq!(count, 2);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_visit_mut_edits() {
+    let mut elements = vec![tag(
+        "div",
+        &[],
+        vec![
+            tag("script", &[], vec![text("x")]),
+            tag("span", &[], vec![text("a"), tag("b", &[], vec![text("b")])]),
+            text("c"),
+        ],
+    )];
+    visit_mut(
+        &mut elements,
+        Order::TopDown,
+        |element, context| match element {
+            Element::Tag(tag) if tag.name == "script" => Edit::Remove,
+            Element::Tag(tag) if tag.name == "span" => Edit::Unwrap,
+            Element::Tag(tag) if tag.name == "b" => {
+                assert_eq!(context.parent().map(|parent| parent.name()), Some("div"));
+                Edit::Replace(vec![tag.element_list[0].clone(), text("!")])
+            }
+            Element::String(s) => {
+                s.make_ascii_uppercase();
+                Edit::Keep
+            }
+            Element::Tag(_) => Edit::Keep,
+        },
+    );
+    assert_eq!(
+        elements,
+        vec![tag(
+            "div",
+            &[],
+            vec![text("A"), text("b"), text("!"), text("C")]
+        )]
+    );
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_transform_bottom_up() {
+    let element = tag(
+        "ul",
+        &[],
+        vec![tag("li", &[], vec![text("a")]), tag("li", &[], vec![])],
+    );
+    let mut depths = vec![];
+    let elements = element.transform(Order::BottomUp, |element, context| {
+        depths.push(context.depth());
+        match element {
+            Element::Tag(tag) if tag.element_list.is_empty() => Edit::Remove,
+            Element::Tag(tag) if tag.name == "ul" => Edit::Unwrap,
+            _ => Edit::Keep,
+        }
+    });
+    assert_eq!(elements, vec![tag("li", &[], vec![text("a")])]);
+    assert_eq!(depths, [2, 1, 1, 0]);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_try_transform_keeps_edits_before_error() {
+    let element = tag("a", &[], vec![text("b"), tag("c", &[], vec![]), text("d")]);
+    let result = element
+        .clone()
+        .try_transform(Order::TopDown, |element, _| match element {
+            Element::String(s) if s == "b" => Ok(Edit::Remove),
+            Element::Tag(tag) if tag.name == "c" => Err(()),
+            _ => Ok(Edit::Keep),
+        });
+    assert_eq!(result, Err(()));
+
+    let mut elements = vec![element];
+    let result = try_visit_mut(&mut elements, Order::TopDown, |element, _| match element {
+        Element::String(s) if s == "b" => Ok(Edit::Remove),
+        Element::Tag(tag) if tag.name == "c" => Err(()),
+        _ => Ok(Edit::Keep),
+    });
+    assert_eq!(result, Err(()));
+    assert_eq!(
+        elements,
+        vec![tag("a", &[], vec![tag("c", &[], vec![]), text("d")])]
+    );
+}
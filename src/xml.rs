@@ -0,0 +1,1075 @@
+//! XML parsing into and serialization from JsonML elements
+//!
+//! The reader checks that the input is a well-formed
+//! [XML 1.0](https://www.w3.org/TR/xml/) document which is also namespace-well-formed as
+//! [Namespaces in XML 1.0](https://www.w3.org/TR/xml-names/) specifies.
+//! Names keep their prefixes as written, so `svg:rect` stays `svg:rect` and `xlink:href`
+//! stays `xlink:href`, and namespace declarations are kept as ordinary attributes.
+//!
+//! Comments, processing instructions and the document type declaration have no JsonML
+//! representation and are dropped.
+//! Entities declared in a document type definition are not supported;
+//! only the predefined entities and character references are decoded.
+
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::{AttributeValue, Attributes, Element, Tag};
+
+/// Options for the XML parser
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Drop text nodes which consist only of XML whitespace.
+    ///
+    /// All character data is significant in XML, which is what the parser assumes by default.
+    pub drop_whitespace_text: bool,
+}
+
+/// Well-formedness error with its position in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    line: usize,
+    column: usize,
+}
+
+impl ParseError {
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// Line number, starting from 1
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Column number in characters, starting from 1
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.line, self.column
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    InvalidChar(char),
+    MissingRootElement,
+    MultipleRootElements,
+    TextOutsideRootElement,
+    MismatchedEndTag { expected: String, found: String },
+    DuplicateAttribute(String),
+    UndefinedEntity(String),
+    InvalidCharacterReference(String),
+    InvalidQualifiedName(String),
+    UnboundPrefix(String),
+    InvalidNamespaceDeclaration(String),
+    InvalidComment,
+    ReservedProcessingInstruction,
+    CdataSectionEndInText,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            ParseErrorKind::InvalidChar(c) => write!(f, "invalid character {c:?}"),
+            ParseErrorKind::MissingRootElement => write!(f, "missing root element"),
+            ParseErrorKind::MultipleRootElements => write!(f, "multiple root elements"),
+            ParseErrorKind::TextOutsideRootElement => write!(f, "text outside root element"),
+            ParseErrorKind::MismatchedEndTag { expected, found } => {
+                write!(f, "expected end tag of {expected:?}, found {found:?}")
+            }
+            ParseErrorKind::DuplicateAttribute(name) => {
+                write!(f, "duplicate attribute {name:?}")
+            }
+            ParseErrorKind::UndefinedEntity(name) => write!(f, "undefined entity {name:?}"),
+            ParseErrorKind::InvalidCharacterReference(reference) => {
+                write!(f, "invalid character reference {reference:?}")
+            }
+            ParseErrorKind::InvalidQualifiedName(name) => {
+                write!(f, "invalid qualified name {name:?}")
+            }
+            ParseErrorKind::UnboundPrefix(prefix) => {
+                write!(f, "namespace prefix {prefix:?} is not declared")
+            }
+            ParseErrorKind::InvalidNamespaceDeclaration(name) => {
+                write!(f, "invalid namespace declaration {name:?}")
+            }
+            ParseErrorKind::InvalidComment => write!(f, "\"--\" is not allowed in comments"),
+            ParseErrorKind::ReservedProcessingInstruction => {
+                write!(f, "processing instruction target \"xml\" is reserved")
+            }
+            ParseErrorKind::CdataSectionEndInText => {
+                write!(f, "\"]]>\" is not allowed in text")
+            }
+        }
+    }
+}
+
+/// Parse an XML document into its root element
+pub fn parse(input: &str) -> Result<Element, ParseError> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+/// Parse an XML document into its root element, with options
+pub fn parse_with_options(input: &str, options: &ParseOptions) -> Result<Element, ParseError> {
+    // https://www.w3.org/TR/xml/#sec-line-ends
+    let input = if input.contains('\r') {
+        Cow::Owned(input.replace("\r\n", "\n").replace('\r', "\n"))
+    } else {
+        Cow::Borrowed(input)
+    };
+    let input = input.strip_prefix('\u{FEFF}').unwrap_or(&input);
+    Parser {
+        input,
+        pos: 0,
+        options,
+        namespaces: vec![
+            ("xml".to_string(), XML_NAMESPACE.to_string()),
+            ("xmlns".to_string(), XMLNS_NAMESPACE.to_string()),
+        ],
+    }
+    .parse_document()
+}
+
+pub(crate) const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+pub(crate) const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+// https://www.w3.org/TR/xml/#NT-Char
+pub(crate) fn is_char(c: char) -> bool {
+    matches!(c,
+        '\u{9}' | '\u{A}' | '\u{D}' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{10FFFF}')
+}
+
+// https://www.w3.org/TR/xml/#NT-NameStartChar
+pub(crate) fn is_name_start_char(c: char) -> bool {
+    matches!(c,
+        ':' | 'A'..='Z' | '_' | 'a'..='z' | '\u{C0}'..='\u{D6}' | '\u{D8}'..='\u{F6}'
+        | '\u{F8}'..='\u{2FF}' | '\u{370}'..='\u{37D}' | '\u{37F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}' | '\u{2070}'..='\u{218F}' | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}' | '\u{F900}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}')
+}
+
+// https://www.w3.org/TR/xml/#NT-NameChar
+pub(crate) fn is_name_char(c: char) -> bool {
+    is_name_start_char(c)
+        || matches!(c,
+            '-' | '.' | '0'..='9' | '\u{B7}' | '\u{300}'..='\u{36F}' | '\u{203F}'..='\u{2040}')
+}
+
+// https://www.w3.org/TR/xml/#NT-S
+pub(crate) fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r')
+}
+
+// https://www.w3.org/TR/xml-names/#NT-NCName
+pub(crate) fn is_ncname(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c != ':' && is_name_start_char(c))
+        && chars.all(|c| c != ':' && is_name_char(c))
+}
+
+// https://www.w3.org/TR/xml-names/#NT-QName
+pub(crate) fn is_qname(name: &str) -> bool {
+    match name.split_once(':') {
+        Some((prefix, local)) => is_ncname(prefix) && is_ncname(local),
+        None => is_ncname(name),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    options: &'a ParseOptions,
+    /// In-scope namespace bindings; later entries shadow earlier ones.
+    namespaces: Vec<(String, String)>,
+}
+
+struct OpenTag {
+    tag: Tag,
+    namespaces_len: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error_at(&self, pos: usize, kind: ParseErrorKind) -> ParseError {
+        let before = &self.input[..pos];
+        ParseError {
+            kind,
+            line: before.matches('\n').count() + 1,
+            column: before.rsplit('\n').next().unwrap_or("").chars().count() + 1,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        self.error_at(self.pos, kind)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        self.rest().starts_with(s)
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(c) => self.error(ParseErrorKind::UnexpectedChar(c)),
+            None => self.error(ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    fn expect(&mut self, s: &str) -> Result<(), ParseError> {
+        if self.starts_with(s) {
+            self.pos += s.len();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    /// Advance until `end` and return the text before it.
+    fn take_until(&mut self, end: &str) -> Result<&'a str, ParseError> {
+        match self.rest().find(end) {
+            Some(index) => {
+                let taken = &self.rest()[..index];
+                self.pos += index + end.len();
+                Ok(taken)
+            }
+            None => {
+                self.pos = self.input.len();
+                Err(self.error(ParseErrorKind::UnexpectedEof))
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let trimmed = self.rest().trim_start_matches(is_whitespace);
+        let skipped = self.rest().len() - trimmed.len();
+        self.pos += skipped;
+        skipped > 0
+    }
+
+    fn parse_document(mut self) -> Result<Element, ParseError> {
+        if let Some((pos, c)) = self.input.char_indices().find(|&(_, c)| !is_char(c)) {
+            return Err(self.error_at(pos, ParseErrorKind::InvalidChar(c)));
+        }
+        if self.starts_with("<?xml")
+            && matches!(self.input[5..].chars().next(), Some(c) if is_whitespace(c) || c == '?')
+        {
+            self.take_until("?>")?;
+        }
+        self.parse_misc(true)?;
+        if self.peek().is_none() {
+            return Err(self.error(ParseErrorKind::MissingRootElement));
+        }
+        if !self.starts_with("<") {
+            return Err(self.error(ParseErrorKind::TextOutsideRootElement));
+        }
+        let root = self.parse_element()?;
+        self.parse_misc(false)?;
+        match self.peek() {
+            None => Ok(root),
+            Some('<') => Err(self.error(ParseErrorKind::MultipleRootElements)),
+            Some(_) => Err(self.error(ParseErrorKind::TextOutsideRootElement)),
+        }
+    }
+
+    /// Skip comments, processing instructions, whitespace and, if allowed, a document type
+    /// declaration.
+    fn parse_misc(&mut self, mut allow_doctype: bool) -> Result<(), ParseError> {
+        loop {
+            self.skip_whitespace();
+            if self.starts_with("<!--") {
+                self.parse_comment()?;
+            } else if self.starts_with("<?") {
+                self.parse_processing_instruction()?;
+            } else if allow_doctype && self.starts_with("<!DOCTYPE") {
+                self.parse_doctype()?;
+                allow_doctype = false;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_comment(&mut self) -> Result<(), ParseError> {
+        self.expect("<!--")?;
+        let start = self.pos;
+        let comment = self.take_until("--")?;
+        if !self.starts_with(">") {
+            return Err(self.error_at(start + comment.len(), ParseErrorKind::InvalidComment));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_processing_instruction(&mut self) -> Result<(), ParseError> {
+        self.expect("<?")?;
+        let start = self.pos;
+        let target = self.parse_name()?;
+        if target.eq_ignore_ascii_case("xml") {
+            return Err(self.error_at(start, ParseErrorKind::ReservedProcessingInstruction));
+        }
+        if !self.starts_with("?>") && !self.skip_whitespace() {
+            return Err(self.unexpected());
+        }
+        self.take_until("?>")?;
+        Ok(())
+    }
+
+    fn parse_doctype(&mut self) -> Result<(), ParseError> {
+        self.expect("<!DOCTYPE")?;
+        let mut quote = None;
+        let mut depth = 0usize;
+        while let Some(c) = self.peek() {
+            self.pos += c.len_utf8();
+            match (quote, c) {
+                (Some(q), _) if q == c => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '[') => depth += 1,
+                (None, ']') => depth = depth.saturating_sub(1),
+                (None, '>') if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+        Err(self.error(ParseErrorKind::UnexpectedEof))
+    }
+
+    fn parse_name(&mut self) -> Result<&'a str, ParseError> {
+        let rest = self.rest();
+        match rest.chars().next() {
+            Some(c) if is_name_start_char(c) => {}
+            _ => return Err(self.unexpected()),
+        }
+        let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+        self.pos += end;
+        Ok(&rest[..end])
+    }
+
+    fn parse_reference(&mut self, text: &mut String) -> Result<(), ParseError> {
+        let start = self.pos;
+        self.expect("&")?;
+        if self.starts_with("#") {
+            let reference = self.take_until(";")?;
+            let code = match reference.strip_prefix("#x") {
+                Some(hex) if !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+                    u32::from_str_radix(hex, 16).ok()
+                }
+                Some(_) => None,
+                None => {
+                    let decimal = &reference[1..];
+                    if !decimal.is_empty() && decimal.chars().all(|c| c.is_ascii_digit()) {
+                        decimal.parse().ok()
+                    } else {
+                        None
+                    }
+                }
+            };
+            match code.and_then(char::from_u32).filter(|&c| is_char(c)) {
+                Some(c) => text.push(c),
+                None => {
+                    return Err(self.error_at(
+                        start,
+                        ParseErrorKind::InvalidCharacterReference(format!("&{reference};")),
+                    ))
+                }
+            }
+        } else {
+            let name = self.parse_name()?;
+            self.expect(";")?;
+            text.push(match name {
+                "lt" => '<',
+                "gt" => '>',
+                "amp" => '&',
+                "apos" => '\'',
+                "quot" => '"',
+                _ => {
+                    return Err(
+                        self.error_at(start, ParseErrorKind::UndefinedEntity(name.to_string()))
+                    )
+                }
+            });
+        }
+        Ok(())
+    }
+
+    // https://www.w3.org/TR/xml/#AVNormalize
+    fn parse_attribute_value(&mut self) -> Result<String, ParseError> {
+        let quote = match self.peek() {
+            Some(c @ ('"' | '\'')) => c,
+            _ => return Err(self.unexpected()),
+        };
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(value);
+                }
+                Some('<') => return Err(self.unexpected()),
+                Some('&') => self.parse_reference(&mut value)?,
+                Some(c) => {
+                    value.push(if is_whitespace(c) { ' ' } else { c });
+                    self.pos += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    /// Parse a start tag, returning the tag and whether it was an empty-element tag.
+    #[allow(deprecated)]
+    fn parse_start_tag(&mut self) -> Result<(Tag, usize, bool), ParseError> {
+        self.expect("<")?;
+        let name_pos = self.pos;
+        let name = self.parse_name()?;
+        let mut attributes = vec![];
+        let empty = loop {
+            let had_whitespace = self.skip_whitespace();
+            if self.starts_with("/>") {
+                self.pos += 2;
+                break true;
+            }
+            if self.starts_with(">") {
+                self.pos += 1;
+                break false;
+            }
+            if !had_whitespace {
+                return Err(self.unexpected());
+            }
+            let attribute_pos = self.pos;
+            let attribute_name = self.parse_name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let value = self.parse_attribute_value()?;
+            if attributes
+                .iter()
+                .any(|(existing, _, _)| *existing == attribute_name)
+            {
+                return Err(self.error_at(
+                    attribute_pos,
+                    ParseErrorKind::DuplicateAttribute(attribute_name.to_string()),
+                ));
+            }
+            attributes.push((attribute_name, value, attribute_pos));
+        };
+
+        let namespaces_len = self.namespaces.len();
+        for (attribute_name, value, attribute_pos) in &attributes {
+            let prefix = if *attribute_name == "xmlns" {
+                ""
+            } else if let Some(prefix) = attribute_name.strip_prefix("xmlns:") {
+                prefix
+            } else {
+                continue;
+            };
+            let invalid = || {
+                self.error_at(
+                    *attribute_pos,
+                    ParseErrorKind::InvalidNamespaceDeclaration(attribute_name.to_string()),
+                )
+            };
+            if prefix == "xmlns"
+                || value == XMLNS_NAMESPACE
+                || (prefix == "xml") != (value == XML_NAMESPACE)
+                || (!prefix.is_empty() && value.is_empty())
+            {
+                return Err(invalid());
+            }
+            self.namespaces.push((prefix.to_string(), value.clone()));
+        }
+
+        self.check_qualified_name(name, name_pos, true)?;
+        for (attribute_name, _, attribute_pos) in &attributes {
+            self.check_qualified_name(attribute_name, *attribute_pos, false)?;
+        }
+
+        let tag = Tag {
+            name: name.to_string(),
+            attributes: attributes
+                .into_iter()
+                .map(|(name, value, _)| (name.to_string(), AttributeValue::String(value)))
+                .collect::<Attributes>(),
+            element_list: vec![],
+        };
+        Ok((tag, namespaces_len, empty))
+    }
+
+    fn check_qualified_name(
+        &self,
+        name: &str,
+        pos: usize,
+        is_element: bool,
+    ) -> Result<(), ParseError> {
+        if !is_qname(name) {
+            return Err(self.error_at(pos, ParseErrorKind::InvalidQualifiedName(name.to_string())));
+        }
+        if let Some((prefix, _)) = name.split_once(':') {
+            if (is_element && prefix == "xmlns")
+                || !self.namespaces.iter().any(|(bound, _)| bound == prefix)
+            {
+                return Err(self.error_at(pos, ParseErrorKind::UnboundPrefix(prefix.to_string())));
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(deprecated)]
+    fn push_text(&self, text: &mut String, tag: &mut Tag) {
+        if text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(text);
+        if !(self.options.drop_whitespace_text && text.chars().all(is_whitespace)) {
+            tag.element_list.push(Element::String(text));
+        }
+    }
+
+    #[allow(deprecated)]
+    fn parse_element(&mut self) -> Result<Element, ParseError> {
+        let (tag, namespaces_len, empty) = self.parse_start_tag()?;
+        if empty {
+            self.namespaces.truncate(namespaces_len);
+            return Ok(Element::Tag(tag));
+        }
+        let mut stack = vec![OpenTag {
+            tag,
+            namespaces_len,
+        }];
+        let mut text = String::new();
+        loop {
+            let current = &mut stack.last_mut().expect("stack is not empty").tag;
+            if self.starts_with("</") {
+                self.push_text(&mut text, current);
+                self.pos += 2;
+                let name_pos = self.pos;
+                let name = self.parse_name()?;
+                self.skip_whitespace();
+                self.expect(">")?;
+                let open = stack.pop().expect("stack is not empty");
+                if name != open.tag.name {
+                    return Err(self.error_at(
+                        name_pos,
+                        ParseErrorKind::MismatchedEndTag {
+                            expected: open.tag.name,
+                            found: name.to_string(),
+                        },
+                    ));
+                }
+                self.namespaces.truncate(open.namespaces_len);
+                let element = Element::Tag(open.tag);
+                match stack.last_mut() {
+                    Some(parent) => parent.tag.element_list.push(element),
+                    None => return Ok(element),
+                }
+            } else if self.starts_with("<!--") {
+                self.parse_comment()?;
+            } else if self.starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                text.push_str(self.take_until("]]>")?);
+            } else if self.starts_with("<?") {
+                self.parse_processing_instruction()?;
+            } else if self.starts_with("<") {
+                self.push_text(&mut text, current);
+                let (tag, namespaces_len, empty) = self.parse_start_tag()?;
+                if empty {
+                    self.namespaces.truncate(namespaces_len);
+                    stack
+                        .last_mut()
+                        .expect("stack is not empty")
+                        .tag
+                        .element_list
+                        .push(Element::Tag(tag));
+                } else {
+                    stack.push(OpenTag {
+                        tag,
+                        namespaces_len,
+                    });
+                }
+            } else if self.starts_with("&") {
+                self.parse_reference(&mut text)?;
+            } else if self.peek().is_none() {
+                return Err(self.error(ParseErrorKind::UnexpectedEof));
+            } else {
+                let rest = self.rest();
+                let data = &rest[..rest.find(['<', '&']).unwrap_or(rest.len())];
+                if let Some(index) = data.find("]]>") {
+                    return Err(
+                        self.error_at(self.pos + index, ParseErrorKind::CdataSectionEndInText)
+                    );
+                }
+                text.push_str(data);
+                self.pos += data.len();
+            }
+        }
+    }
+}
+
+/// Options for XML serialization
+#[derive(Debug, Clone, Default)]
+pub struct SerializeOptions {
+    /// Write the XML declaration `<?xml version="1.0" encoding="UTF-8"?>` before the root
+    /// element.
+    pub xml_declaration: bool,
+
+    /// Write `<a></a>` instead of the empty-element tag `<a/>` for elements without content.
+    pub expand_empty_elements: bool,
+
+    /// Namespace declarations to add to the root element as pairs of prefix and namespace name.
+    /// The empty prefix declares the default namespace.
+    ///
+    /// Declarations already present in the root element attributes take precedence.
+    pub namespaces: Vec<(String, String)>,
+
+    /// Write attributes sorted by name instead of in the order of the tag.
+    /// Namespace declarations from [`namespaces`](Self::namespaces) are written first.
+    pub sort_attributes: bool,
+}
+
+/// Error on serializing an element which cannot be written as namespace-well-formed XML
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SerializeError {
+    InvalidTagName(String),
+    InvalidAttributeName(String),
+    InvalidChar(char),
+    UnboundPrefix(String),
+    /// The root element is text, which a document cannot consist of.
+    TextRoot,
+}
+
+impl Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::InvalidTagName(name) => write!(f, "invalid tag name {name:?}"),
+            SerializeError::InvalidAttributeName(name) => {
+                write!(f, "invalid attribute name {name:?}")
+            }
+            SerializeError::InvalidChar(c) => write!(f, "invalid character {c:?}"),
+            SerializeError::UnboundPrefix(prefix) => {
+                write!(f, "namespace prefix {prefix:?} is not declared")
+            }
+            SerializeError::TextRoot => write!(f, "root element is text instead of a tag"),
+        }
+    }
+}
+
+impl Error for SerializeError {}
+
+/// Serialize an element as XML
+///
+/// The element must be a tag, as the root element of a document.
+pub fn to_string(element: &Element) -> Result<String, SerializeError> {
+    to_string_with_options(element, &SerializeOptions::default())
+}
+
+/// Serialize an element as XML, with options
+///
+/// Attributes with [`AttributeValue::Null`] have no XML representation and are omitted.
+pub fn to_string_with_options(
+    element: &Element,
+    options: &SerializeOptions,
+) -> Result<String, SerializeError> {
+    let mut output = String::new();
+    if options.xml_declaration {
+        output.push_str(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    }
+    let mut serializer = Serializer {
+        output,
+        options,
+        namespaces: vec!["xml".to_string(), "xmlns".to_string()],
+    };
+    serializer.write_element(element, true)?;
+    Ok(serializer.output)
+}
+
+struct Serializer<'a> {
+    output: String,
+    options: &'a SerializeOptions,
+    /// Prefixes in scope; the empty prefix is always usable.
+    namespaces: Vec<String>,
+}
+
+impl<'a> Serializer<'a> {
+    #[allow(deprecated)]
+    fn write_element(&mut self, element: &Element, is_root: bool) -> Result<(), SerializeError> {
+        let Tag {
+            name,
+            attributes,
+            element_list,
+        } = match element {
+            Element::Tag(tag) => tag,
+            Element::String(_) if is_root => return Err(SerializeError::TextRoot),
+            Element::String(s) => return self.write_text(s),
+        };
+        // The `xmlns` prefix is reserved for namespace declarations and cannot be declared.
+        if !is_qname(name) || name.starts_with("xmlns:") {
+            return Err(SerializeError::InvalidTagName(name.to_string()));
+        }
+        let namespaces_len = self.namespaces.len();
+
+        let mut declarations = vec![];
+        if is_root {
+            for (prefix, namespace) in &self.options.namespaces {
+                let key = if prefix.is_empty() {
+                    "xmlns".to_string()
+                } else {
+                    format!("xmlns:{prefix}")
+                };
+                if !attributes.contains_key(&key) {
+                    declarations.push((key, namespace.as_str()));
+                }
+            }
+        }
+        for key in attributes
+            .keys()
+            .chain(declarations.iter().map(|(key, _)| key))
+        {
+            if !is_qname(key) || key == "xmlns:xmlns" {
+                return Err(SerializeError::InvalidAttributeName(key.to_string()));
+            }
+            if let Some(prefix) = key.strip_prefix("xmlns:") {
+                self.namespaces.push(prefix.to_string());
+            }
+        }
+        self.check_prefix(name)?;
+        for key in attributes.keys() {
+            self.check_prefix(key)?;
+        }
+
+        self.output.push('<');
+        self.output.push_str(name);
+        for (key, namespace) in &declarations {
+            self.write_attribute(key, namespace)?;
+        }
+        let mut attributes: Vec<_> = attributes.iter().collect();
+        if self.options.sort_attributes {
+            attributes.sort_by_key(|(key, _)| *key);
+        }
+        for (key, value) in attributes {
+            match value {
+                AttributeValue::String(s) => self.write_attribute(key, s)?,
+                AttributeValue::Number(n) => self.write_attribute(key, &n.to_string())?,
+                AttributeValue::Bool(b) => self.write_attribute(key, &b.to_string())?,
+                AttributeValue::Null => {}
+            }
+        }
+        if element_list.is_empty() && !self.options.expand_empty_elements {
+            self.output.push_str("/>");
+        } else {
+            self.output.push('>');
+            for element in element_list {
+                self.write_element(element, false)?;
+            }
+            self.output.push_str("</");
+            self.output.push_str(name);
+            self.output.push('>');
+        }
+        self.namespaces.truncate(namespaces_len);
+        Ok(())
+    }
+
+    fn check_prefix(&self, name: &str) -> Result<(), SerializeError> {
+        match name.split_once(':') {
+            Some((prefix, _)) if !self.namespaces.iter().any(|bound| bound == prefix) => {
+                Err(SerializeError::UnboundPrefix(prefix.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn write_attribute(&mut self, key: &str, value: &str) -> Result<(), SerializeError> {
+        self.output.push(' ');
+        self.output.push_str(key);
+        self.output.push_str("=\"");
+        for c in value.chars() {
+            match c {
+                '&' => self.output.push_str("&amp;"),
+                '<' => self.output.push_str("&lt;"),
+                '"' => self.output.push_str("&quot;"),
+                // Keep whitespace from attribute value normalization.
+                '\t' => self.output.push_str("&#x9;"),
+                '\n' => self.output.push_str("&#xA;"),
+                '\r' => self.output.push_str("&#xD;"),
+                c if is_char(c) => self.output.push(c),
+                c => return Err(SerializeError::InvalidChar(c)),
+            }
+        }
+        self.output.push('"');
+        Ok(())
+    }
+
+    fn write_text(&mut self, text: &str) -> Result<(), SerializeError> {
+        for c in text.chars() {
+            match c {
+                '&' => self.output.push_str("&amp;"),
+                '<' => self.output.push_str("&lt;"),
+                // Also prevents `]]>` in text.
+                '>' => self.output.push_str("&gt;"),
+                // Keep carriage returns from end-of-line handling.
+                '\r' => self.output.push_str("&#xD;"),
+                c if is_char(c) => self.output.push(c),
+                c => return Err(SerializeError::InvalidChar(c)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+use crate::test_helpers::{tag, text};
+
+#[test]
+fn test_parse_prefixed_names() {
+    assert_eq!(
+        parse(
+            r##"<?xml version="1.0"?>
+<!-- drawing -->
+<svg:svg xmlns:svg="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"><svg:rect width="1"/><svg:use xlink:href="#a"></svg:use></svg:svg>
+"##
+        ),
+        Ok(tag(
+            "svg:svg",
+            &[
+                ("xmlns:svg", "http://www.w3.org/2000/svg"),
+                ("xmlns:xlink", "http://www.w3.org/1999/xlink")
+            ],
+            vec![
+                tag("svg:rect", &[("width", "1")], vec![]),
+                tag("svg:use", &[("xlink:href", "#a")], vec![]),
+            ]
+        ))
+    );
+}
+
+#[test]
+fn test_parse_references() {
+    assert_eq!(
+        parse(r#"<a b="&lt;&#x41;&#66;&apos;">&amp;&quot;<![CDATA[<&>]]>&#xA9;</a>"#),
+        Ok(tag("a", &[("b", "<AB'")], vec![text("&\"<&>\u{A9}")]))
+    );
+}
+
+#[test]
+fn test_parse_whitespace_text() {
+    let input = "<feed>\n  <title>a</title>\n</feed>";
+    assert_eq!(
+        parse(input),
+        Ok(tag(
+            "feed",
+            &[],
+            vec![text("\n  "), tag("title", &[], vec![text("a")]), text("\n")]
+        ))
+    );
+    assert_eq!(
+        parse_with_options(
+            input,
+            &ParseOptions {
+                drop_whitespace_text: true
+            }
+        ),
+        Ok(tag("feed", &[], vec![tag("title", &[], vec![text("a")])]))
+    );
+}
+
+#[test]
+fn test_parse_error_position() {
+    let error = parse("<a>\n  <b></c>\n</a>").unwrap_err();
+    assert_eq!(
+        error.kind(),
+        &ParseErrorKind::MismatchedEndTag {
+            expected: "b".to_string(),
+            found: "c".to_string()
+        }
+    );
+    assert_eq!((error.line(), error.column()), (2, 8));
+    assert_eq!(
+        error.to_string(),
+        r#"expected end tag of "b", found "c" at line 2, column 8"#
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    let kind = |input| parse(input).unwrap_err().kind().clone();
+    assert_eq!(kind(""), ParseErrorKind::MissingRootElement);
+    assert_eq!(kind("<a>"), ParseErrorKind::UnexpectedEof);
+    assert_eq!(kind("<a/><b/>"), ParseErrorKind::MultipleRootElements);
+    assert_eq!(kind("<a/>b"), ParseErrorKind::TextOutsideRootElement);
+    assert_eq!(
+        kind(r#"<a b="1" b="2"/>"#),
+        ParseErrorKind::DuplicateAttribute("b".to_string())
+    );
+    assert_eq!(
+        kind("<a>&nbsp;</a>"),
+        ParseErrorKind::UndefinedEntity("nbsp".to_string())
+    );
+    assert_eq!(
+        kind("<a>&#0;</a>"),
+        ParseErrorKind::InvalidCharacterReference("&#0;".to_string())
+    );
+    assert_eq!(
+        kind("<svg:a/>"),
+        ParseErrorKind::UnboundPrefix("svg".to_string())
+    );
+    assert_eq!(
+        kind("<a:b:c/>"),
+        ParseErrorKind::InvalidQualifiedName("a:b:c".to_string())
+    );
+    assert_eq!(kind("<a><!-- - -- --></a>"), ParseErrorKind::InvalidComment);
+    assert_eq!(kind("<a>]]></a>"), ParseErrorKind::CdataSectionEndInText);
+    assert_eq!(kind("<a b=\"<\"/>"), ParseErrorKind::UnexpectedChar('<'));
+    assert_eq!(kind("<a>\u{1}</a>"), ParseErrorKind::InvalidChar('\u{1}'));
+}
+
+#[test]
+fn test_parse_namespace_scope() {
+    assert!(parse(r#"<a xmlns:p="urn:p"><p:b/></a>"#).is_ok());
+    assert_eq!(
+        parse(r#"<a><b xmlns:p="urn:p"/><p:c/></a>"#)
+            .unwrap_err()
+            .kind(),
+        &ParseErrorKind::UnboundPrefix("p".to_string())
+    );
+    assert!(parse(r#"<a xml:lang="en"/>"#).is_ok());
+}
+
+#[test]
+fn test_to_string() {
+    let element = tag(
+        "dc:title",
+        &[("xml:lang", "en")],
+        vec![text("a < b & c"), tag("my-element", &[], vec![])],
+    );
+    assert_eq!(
+        to_string_with_options(
+            &element,
+            &SerializeOptions {
+                xml_declaration: true,
+                namespaces: vec![("dc".to_string(), "http://purl.org/dc/elements/1.1/".to_string())],
+                ..SerializeOptions::default()
+            }
+        ),
+        Ok(r#"<?xml version="1.0" encoding="UTF-8"?><dc:title xmlns:dc="http://purl.org/dc/elements/1.1/" xml:lang="en">a &lt; b &amp; c<my-element/></dc:title>"#.to_string())
+    );
+}
+
+#[test]
+fn test_to_string_expand_empty_elements() {
+    assert_eq!(
+        to_string_with_options(
+            &tag("a", &[], vec![]),
+            &SerializeOptions {
+                expand_empty_elements: true,
+                ..SerializeOptions::default()
+            }
+        ),
+        Ok("<a></a>".to_string())
+    );
+}
+
+#[test]
+fn test_to_string_sort_attributes() {
+    let element = tag("a", &[("c", "1"), ("b", "2")], vec![]);
+    assert_eq!(to_string(&element), Ok(r#"<a c="1" b="2"/>"#.to_string()));
+    assert_eq!(
+        to_string_with_options(
+            &element,
+            &SerializeOptions {
+                sort_attributes: true,
+                ..SerializeOptions::default()
+            }
+        ),
+        Ok(r#"<a b="2" c="1"/>"#.to_string())
+    );
+}
+
+#[test]
+fn test_to_string_escape_attribute() {
+    assert_eq!(
+        to_string(&tag("a", &[("b", "<\"&'\n>")], vec![])),
+        Ok(r#"<a b="&lt;&quot;&amp;'&#xA;>"/>"#.to_string())
+    );
+}
+
+#[test]
+fn test_to_string_errors() {
+    assert_eq!(
+        to_string(&tag("1a", &[], vec![])),
+        Err(SerializeError::InvalidTagName("1a".to_string()))
+    );
+    assert_eq!(
+        to_string(&tag("a", &[("b c", "")], vec![])),
+        Err(SerializeError::InvalidAttributeName("b c".to_string()))
+    );
+    assert_eq!(
+        to_string(&tag("a", &[], vec![text("\u{0}")])),
+        Err(SerializeError::InvalidChar('\u{0}'))
+    );
+    assert_eq!(
+        to_string(&tag("svg:a", &[], vec![])),
+        Err(SerializeError::UnboundPrefix("svg".to_string()))
+    );
+    assert_eq!(to_string(&text("a")), Err(SerializeError::TextRoot));
+    assert_eq!(
+        to_string(&tag("xmlns:a", &[], vec![])),
+        Err(SerializeError::InvalidTagName("xmlns:a".to_string()))
+    );
+    assert_eq!(
+        to_string(&tag("a", &[("xmlns:xmlns", "urn:a")], vec![])),
+        Err(SerializeError::InvalidAttributeName(
+            "xmlns:xmlns".to_string()
+        ))
+    );
+    assert_eq!(
+        to_string_with_options(
+            &tag("a", &[], vec![]),
+            &SerializeOptions {
+                namespaces: vec![("xmlns".to_string(), "urn:a".to_string())],
+                ..SerializeOptions::default()
+            }
+        ),
+        Err(SerializeError::InvalidAttributeName(
+            "xmlns:xmlns".to_string()
+        ))
+    );
+    assert_eq!(
+        to_string(&tag("a", &[("xmlns:b", "urn:b")], vec![])),
+        Ok(r#"<a xmlns:b="urn:b"/>"#.to_string())
+    );
+}
+
+#[test]
+fn test_to_string_round_trip() {
+    let input = r##"<svg:svg xmlns:svg="http://www.w3.org/2000/svg"><svg:title>&lt;&amp;&gt;</svg:title><svg:g xmlns:xlink="http://www.w3.org/1999/xlink"><svg:use xlink:href="#a"/></svg:g></svg:svg>"##;
+    let element = parse(input).unwrap();
+    assert_eq!(to_string(&element).as_deref(), Ok(input));
+}
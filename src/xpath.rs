@@ -0,0 +1,1636 @@
+//! XPath 1.0 evaluation over JsonML elements
+//!
+//! The element is the document element under a root node, so `/feed` selects the element if it is
+//! named `feed`, and relative paths start at the element.
+//!
+//! Supported are all axes except `namespace`, node tests, predicates, operators and the core
+//! function library except `id`, `lang` and `namespace-uri`.
+//! Variables are not supported.
+//!
+//! Names are compared exactly, including prefixes, as elements do not keep namespace URIs.
+//! Attributes are strings as serialized in XML, and null attributes are absent.
+//! Adjacent strings in an element are separate text nodes.
+
+use std::{error::Error, fmt, fmt::Display, str::FromStr};
+
+use crate::{xml, AttributeValue, Element, Tag};
+
+/// Compiled XPath expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct XPath {
+    expr: Expr,
+}
+
+/// Result of an XPath expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    /// Nodes in document order
+    Nodes(Vec<Node<'a>>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+/// Node in the XPath data model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Node<'a> {
+    /// Root node, whose child is the element
+    Root(&'a Element),
+    Tag(&'a Tag),
+    Text(&'a str),
+    Attribute(&'a str, &'a AttributeValue),
+}
+
+impl Node<'_> {
+    /// String value as defined by XPath
+    #[allow(deprecated)]
+    pub fn string_value(&self) -> String {
+        match self {
+            Node::Root(element) => element.texts().collect(),
+            Node::Tag(tag) => tag
+                .element_list
+                .iter()
+                .flat_map(|element| element.texts())
+                .collect(),
+            Node::Text(text) => text.to_string(),
+            Node::Attribute(_, value) => attribute_string(value),
+        }
+    }
+}
+
+/// XPath syntax error with its position in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    position: usize,
+}
+
+impl ParseError {
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// Byte offset in the input
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.position)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    UnexpectedEof,
+    UnexpectedChar(char),
+    UnexpectedToken(String),
+    UnknownAxis(String),
+    UnknownFunction(String),
+    WrongArgumentCount(String),
+    /// An operand of `|`, a path or a function requires a node-set.
+    ExpectedNodeSet,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedEof => write!(f, "unexpected end of input"),
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {c:?}"),
+            ParseErrorKind::UnexpectedToken(token) => write!(f, "unexpected token {token:?}"),
+            ParseErrorKind::UnknownAxis(name) => write!(f, "unknown axis {name:?}"),
+            ParseErrorKind::UnknownFunction(name) => write!(f, "unknown function {name}()"),
+            ParseErrorKind::WrongArgumentCount(name) => {
+                write!(f, "wrong number of arguments to {name}()")
+            }
+            ParseErrorKind::ExpectedNodeSet => write!(f, "expected a node-set"),
+        }
+    }
+}
+
+impl XPath {
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            input,
+            tokens,
+            index: 0,
+        };
+        let expr = parser.or_expr()?;
+        match parser.tokens.get(parser.index) {
+            None => Ok(XPath { expr }),
+            Some(_) => Err(parser.unexpected()),
+        }
+    }
+
+    /// Evaluate with the element as the document element and the context node
+    pub fn evaluate<'a>(&self, element: &'a Element) -> Value<'a> {
+        let document = Document::new(element);
+        let context = Context {
+            node: 1,
+            position: 1,
+            size: 1,
+        };
+        match document.evaluate(&self.expr, &context) {
+            Object::Nodes(nodes) => {
+                Value::Nodes(nodes.into_iter().map(|id| document.node(id)).collect())
+            }
+            Object::String(s) => Value::String(s),
+            Object::Number(n) => Value::Number(n),
+            Object::Boolean(b) => Value::Boolean(b),
+        }
+    }
+}
+
+impl FromStr for XPath {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        XPath::parse(s)
+    }
+}
+
+impl Element {
+    /// Evaluate an XPath expression with the element as the document element
+    ///
+    /// ```
+    /// use jsonml::{xpath::Value, Element};
+    ///
+    /// let element: Element = serde_json::from_str(
+    ///     r#"["feed", ["entry", ["title", "a"]], ["entry", ["title", "b"]]]"#,
+    /// )
+    /// .unwrap();
+    /// assert_eq!(
+    ///     element.xpath("string(/feed/entry[last()]/title)").unwrap(),
+    ///     Value::String("b".to_string())
+    /// );
+    /// assert_eq!(element.xpath("count(//title)").unwrap(), Value::Number(2.));
+    /// ```
+    pub fn xpath(&self, expression: &str) -> Result<Value<'_>, ParseError> {
+        Ok(XPath::parse(expression)?.evaluate(self))
+    }
+}
+
+// Syntax
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Compare(CompareOperator, Box<Expr>, Box<Expr>),
+    Arithmetic(ArithmeticOperator, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Path {
+        absolute: bool,
+        steps: Vec<Step>,
+    },
+    /// Primary expression with predicates, followed by a relative path
+    Filter {
+        primary: Box<Expr>,
+        predicates: Vec<Expr>,
+        steps: Vec<Step>,
+    },
+    Literal(String),
+    Number(f64),
+    Function(Function, Vec<Expr>),
+}
+
+impl Expr {
+    fn is_node_set(&self) -> bool {
+        match self {
+            Expr::Union(..) | Expr::Path { .. } => true,
+            Expr::Filter { primary, .. } => primary.is_node_set(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    axis: Axis,
+    test: NodeTest,
+    predicates: Vec<Expr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Ancestor,
+    AncestorOrSelf,
+    Attribute,
+    Child,
+    Descendant,
+    DescendantOrSelf,
+    Following,
+    FollowingSibling,
+    Parent,
+    Preceding,
+    PrecedingSibling,
+    /// `self`
+    Itself,
+}
+
+const AXES: &[(&str, Axis)] = &[
+    ("ancestor", Axis::Ancestor),
+    ("ancestor-or-self", Axis::AncestorOrSelf),
+    ("attribute", Axis::Attribute),
+    ("child", Axis::Child),
+    ("descendant", Axis::Descendant),
+    ("descendant-or-self", Axis::DescendantOrSelf),
+    ("following", Axis::Following),
+    ("following-sibling", Axis::FollowingSibling),
+    ("parent", Axis::Parent),
+    ("preceding", Axis::Preceding),
+    ("preceding-sibling", Axis::PrecedingSibling),
+    ("self", Axis::Itself),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NodeTest {
+    /// `*`
+    Any,
+    /// `prefix:*`
+    Prefix(String),
+    Name(String),
+    Text,
+    Node,
+    /// `comment()` and `processing-instruction()`, which match nothing
+    Nothing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Last,
+    Position,
+    Count,
+    LocalName,
+    Name,
+    String,
+    Concat,
+    StartsWith,
+    Contains,
+    SubstringBefore,
+    SubstringAfter,
+    Substring,
+    StringLength,
+    NormalizeSpace,
+    Translate,
+    Boolean,
+    Not,
+    True,
+    False,
+    Number,
+    Sum,
+    Floor,
+    Ceiling,
+    Round,
+}
+
+/// Name, function, and minimum and maximum numbers of arguments
+const FUNCTIONS: &[(&str, Function, usize, usize)] = &[
+    ("last", Function::Last, 0, 0),
+    ("position", Function::Position, 0, 0),
+    ("count", Function::Count, 1, 1),
+    ("local-name", Function::LocalName, 0, 1),
+    ("name", Function::Name, 0, 1),
+    ("string", Function::String, 0, 1),
+    ("concat", Function::Concat, 2, usize::MAX),
+    ("starts-with", Function::StartsWith, 2, 2),
+    ("contains", Function::Contains, 2, 2),
+    ("substring-before", Function::SubstringBefore, 2, 2),
+    ("substring-after", Function::SubstringAfter, 2, 2),
+    ("substring", Function::Substring, 2, 3),
+    ("string-length", Function::StringLength, 0, 1),
+    ("normalize-space", Function::NormalizeSpace, 0, 1),
+    ("translate", Function::Translate, 3, 3),
+    ("boolean", Function::Boolean, 1, 1),
+    ("not", Function::Not, 1, 1),
+    ("true", Function::True, 0, 0),
+    ("false", Function::False, 0, 0),
+    ("number", Function::Number, 0, 1),
+    ("sum", Function::Sum, 1, 1),
+    ("floor", Function::Floor, 1, 1),
+    ("ceiling", Function::Ceiling, 1, 1),
+    ("round", Function::Round, 1, 1),
+];
+
+impl Function {
+    fn takes_node_set(self) -> bool {
+        matches!(
+            self,
+            Function::Count | Function::LocalName | Function::Name | Function::Sum
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Slash,
+    DoubleSlash,
+    Dot,
+    DotDot,
+    At,
+    Comma,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    ColonColon,
+    Pipe,
+    Plus,
+    Minus,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// `*` as a name test
+    Star,
+    /// `*` as an operator
+    Multiply,
+    And,
+    Or,
+    Div,
+    Mod,
+    Literal(String),
+    Number(f64),
+    Name(String),
+    /// `prefix:*`
+    PrefixStar(String),
+}
+
+impl Token {
+    /// Whether a following `*` or operator name is an operator
+    fn precedes_operator(&self) -> bool {
+        matches!(
+            self,
+            Token::RightParen
+                | Token::RightBracket
+                | Token::Dot
+                | Token::DotDot
+                | Token::Star
+                | Token::Literal(_)
+                | Token::Number(_)
+                | Token::Name(_)
+                | Token::PrefixStar(_)
+        )
+    }
+}
+
+fn is_ncname_start_char(c: char) -> bool {
+    c != ':' && xml::is_name_start_char(c)
+}
+
+fn is_ncname_char(c: char) -> bool {
+    c != ':' && xml::is_name_char(c)
+}
+
+/// Tokens with their start and end positions
+fn tokenize(input: &str) -> Result<Vec<(Token, usize, usize)>, ParseError> {
+    let mut tokens: Vec<(Token, usize, usize)> = vec![];
+    let mut pos = 0;
+    loop {
+        let rest = &input[pos..];
+        let trimmed = rest.trim_start_matches(xml::is_whitespace);
+        pos += rest.len() - trimmed.len();
+        let rest = trimmed;
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return Ok(tokens),
+        };
+        let operator_context = tokens
+            .last()
+            .map_or(false, |(token, _, _)| token.precedes_operator());
+        let symbol = |s: &str| rest.starts_with(s);
+        let (token, len) = if symbol("//") {
+            (Token::DoubleSlash, 2)
+        } else if symbol("::") {
+            (Token::ColonColon, 2)
+        } else if symbol("..") {
+            (Token::DotDot, 2)
+        } else if symbol("!=") {
+            (Token::NotEqual, 2)
+        } else if symbol("<=") {
+            (Token::LessEqual, 2)
+        } else if symbol(">=") {
+            (Token::GreaterEqual, 2)
+        } else if c == '.' && !rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            (Token::Dot, 1)
+        } else if c.is_ascii_digit() || c == '.' {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            let number = &rest[..len];
+            if number.matches('.').count() > 1 {
+                let position = pos + number.rfind('.').unwrap_or(0);
+                return Err(ParseError {
+                    kind: ParseErrorKind::UnexpectedChar('.'),
+                    position,
+                });
+            }
+            (Token::Number(number.parse().unwrap_or(f64::NAN)), len)
+        } else if c == '"' || c == '\'' {
+            match rest[1..].find(c) {
+                Some(end) => (Token::Literal(rest[1..end + 1].to_string()), end + 2),
+                None => {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::UnexpectedEof,
+                        position: input.len(),
+                    })
+                }
+            }
+        } else if c == '*' {
+            if operator_context {
+                (Token::Multiply, 1)
+            } else {
+                (Token::Star, 1)
+            }
+        } else if is_ncname_start_char(c) {
+            let len = rest.find(|c| !is_ncname_char(c)).unwrap_or(rest.len());
+            let name = &rest[..len];
+            let after = &rest[len..];
+            let operator = match name {
+                "and" => Some(Token::And),
+                "or" => Some(Token::Or),
+                "div" => Some(Token::Div),
+                "mod" => Some(Token::Mod),
+                _ => None,
+            };
+            match operator {
+                Some(operator) if operator_context => (operator, len),
+                _ if after.starts_with(":*") => (Token::PrefixStar(name.to_string()), len + 2),
+                _ if after.starts_with(':') && after[1..].starts_with(is_ncname_start_char) => {
+                    let local_len = after[1..]
+                        .find(|c| !is_ncname_char(c))
+                        .unwrap_or(after.len() - 1);
+                    let len = len + 1 + local_len;
+                    (Token::Name(rest[..len].to_string()), len)
+                }
+                _ => (Token::Name(name.to_string()), len),
+            }
+        } else {
+            let token = match c {
+                '/' => Token::Slash,
+                '@' => Token::At,
+                ',' => Token::Comma,
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                '[' => Token::LeftBracket,
+                ']' => Token::RightBracket,
+                '|' => Token::Pipe,
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                '=' => Token::Equal,
+                '<' => Token::Less,
+                '>' => Token::Greater,
+                _ => {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::UnexpectedChar(c),
+                        position: pos,
+                    })
+                }
+            };
+            (token, 1)
+        };
+        tokens.push((token, pos, pos + len));
+        pos += len;
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<(Token, usize, usize)>,
+    index: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(token, _, _)| token)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.index + offset)
+            .map(|(token, _, _)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map_or(self.input.len(), |(_, start, _)| *start)
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            position: self.position(),
+        }
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.tokens.get(self.index) {
+            Some((_, start, end)) => self.error(ParseErrorKind::UnexpectedToken(
+                self.input[*start..*end].to_string(),
+            )),
+            None => self.error(ParseErrorKind::UnexpectedEof),
+        }
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), ParseError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn node_set(&self, expr: Expr, position: usize) -> Result<Expr, ParseError> {
+        if expr.is_node_set() {
+            Ok(expr)
+        } else {
+            Err(ParseError {
+                kind: ParseErrorKind::ExpectedNodeSet,
+                position,
+            })
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and_expr()?;
+        while self.eat(&Token::Or) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.equality_expr()?;
+        while self.eat(&Token::And) {
+            expr = Expr::And(Box::new(expr), Box::new(self.equality_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn equality_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.relational_expr()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Equal) => CompareOperator::Equal,
+                Some(Token::NotEqual) => CompareOperator::NotEqual,
+                _ => return Ok(expr),
+            };
+            self.index += 1;
+            expr = Expr::Compare(operator, Box::new(expr), Box::new(self.relational_expr()?));
+        }
+    }
+
+    fn relational_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.additive_expr()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Less) => CompareOperator::Less,
+                Some(Token::LessEqual) => CompareOperator::LessEqual,
+                Some(Token::Greater) => CompareOperator::Greater,
+                Some(Token::GreaterEqual) => CompareOperator::GreaterEqual,
+                _ => return Ok(expr),
+            };
+            self.index += 1;
+            expr = Expr::Compare(operator, Box::new(expr), Box::new(self.additive_expr()?));
+        }
+    }
+
+    fn additive_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.multiplicative_expr()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Plus) => ArithmeticOperator::Add,
+                Some(Token::Minus) => ArithmeticOperator::Subtract,
+                _ => return Ok(expr),
+            };
+            self.index += 1;
+            expr = Expr::Arithmetic(
+                operator,
+                Box::new(expr),
+                Box::new(self.multiplicative_expr()?),
+            );
+        }
+    }
+
+    fn multiplicative_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary_expr()?;
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Multiply) => ArithmeticOperator::Multiply,
+                Some(Token::Div) => ArithmeticOperator::Divide,
+                Some(Token::Mod) => ArithmeticOperator::Modulo,
+                _ => return Ok(expr),
+            };
+            self.index += 1;
+            expr = Expr::Arithmetic(operator, Box::new(expr), Box::new(self.unary_expr()?));
+        }
+    }
+
+    fn unary_expr(&mut self) -> Result<Expr, ParseError> {
+        if self.eat(&Token::Minus) {
+            Ok(Expr::Negate(Box::new(self.unary_expr()?)))
+        } else {
+            self.union_expr()
+        }
+    }
+
+    fn union_expr(&mut self) -> Result<Expr, ParseError> {
+        let position = self.position();
+        let mut expr = self.path_expr()?;
+        if self.peek() == Some(&Token::Pipe) {
+            expr = self.node_set(expr, position)?;
+        }
+        while self.eat(&Token::Pipe) {
+            let position = self.position();
+            let right = self.path_expr()?;
+            expr = Expr::Union(Box::new(expr), Box::new(self.node_set(right, position)?));
+        }
+        Ok(expr)
+    }
+
+    fn path_expr(&mut self) -> Result<Expr, ParseError> {
+        let filter = match (self.peek(), self.peek_at(1)) {
+            (Some(Token::Literal(_) | Token::Number(_) | Token::LeftParen), _) => true,
+            (Some(Token::Name(name)), Some(Token::LeftParen)) => !is_node_type(name),
+            _ => false,
+        };
+        if !filter {
+            return self.location_path();
+        }
+
+        let position = self.position();
+        let primary = self.primary_expr()?;
+        let mut predicates = vec![];
+        if self.peek() == Some(&Token::LeftBracket) {
+            predicates = self.predicates()?;
+        }
+        let mut steps = vec![];
+        match self.peek() {
+            Some(Token::Slash) => {
+                self.index += 1;
+                self.relative_location_path(&mut steps)?;
+            }
+            Some(Token::DoubleSlash) => {
+                self.index += 1;
+                steps.push(descendant_or_self());
+                self.relative_location_path(&mut steps)?;
+            }
+            _ if predicates.is_empty() => return Ok(primary),
+            _ => {}
+        }
+        Ok(Expr::Filter {
+            primary: Box::new(self.node_set(primary, position)?),
+            predicates,
+            steps,
+        })
+    }
+
+    fn primary_expr(&mut self) -> Result<Expr, ParseError> {
+        let position = self.position();
+        match self.peek().cloned() {
+            Some(Token::Literal(s)) => {
+                self.index += 1;
+                Ok(Expr::Literal(s))
+            }
+            Some(Token::Number(n)) => {
+                self.index += 1;
+                Ok(Expr::Number(n))
+            }
+            Some(Token::LeftParen) => {
+                self.index += 1;
+                let expr = self.or_expr()?;
+                self.expect(&Token::RightParen)?;
+                Ok(expr)
+            }
+            Some(Token::Name(name)) => {
+                let (_, function, min, max) = *FUNCTIONS
+                    .iter()
+                    .find(|(function_name, ..)| *function_name == name)
+                    .ok_or_else(|| self.error(ParseErrorKind::UnknownFunction(name.clone())))?;
+                self.index += 2;
+                let mut arguments = vec![];
+                if !self.eat(&Token::RightParen) {
+                    loop {
+                        let argument_position = self.position();
+                        let argument = self.or_expr()?;
+                        arguments.push(if function.takes_node_set() {
+                            self.node_set(argument, argument_position)?
+                        } else {
+                            argument
+                        });
+                        if !self.eat(&Token::Comma) {
+                            break;
+                        }
+                    }
+                    self.expect(&Token::RightParen)?;
+                }
+                if arguments.len() < min || arguments.len() > max {
+                    return Err(ParseError {
+                        kind: ParseErrorKind::WrongArgumentCount(name),
+                        position,
+                    });
+                }
+                Ok(Expr::Function(function, arguments))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    fn location_path(&mut self) -> Result<Expr, ParseError> {
+        let mut steps = vec![];
+        let absolute = match self.peek() {
+            Some(Token::Slash) => {
+                self.index += 1;
+                if self.starts_step() {
+                    self.relative_location_path(&mut steps)?;
+                }
+                true
+            }
+            Some(Token::DoubleSlash) => {
+                self.index += 1;
+                steps.push(descendant_or_self());
+                self.relative_location_path(&mut steps)?;
+                true
+            }
+            _ => {
+                self.relative_location_path(&mut steps)?;
+                false
+            }
+        };
+        Ok(Expr::Path { absolute, steps })
+    }
+
+    fn starts_step(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(
+                Token::Dot
+                    | Token::DotDot
+                    | Token::At
+                    | Token::Star
+                    | Token::Name(_)
+                    | Token::PrefixStar(_)
+            )
+        )
+    }
+
+    fn relative_location_path(&mut self, steps: &mut Vec<Step>) -> Result<(), ParseError> {
+        loop {
+            steps.push(self.step()?);
+            match self.peek() {
+                Some(Token::Slash) => self.index += 1,
+                Some(Token::DoubleSlash) => {
+                    self.index += 1;
+                    steps.push(descendant_or_self());
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn step(&mut self) -> Result<Step, ParseError> {
+        if self.eat(&Token::Dot) {
+            return Ok(Step {
+                axis: Axis::Itself,
+                test: NodeTest::Node,
+                predicates: vec![],
+            });
+        }
+        if self.eat(&Token::DotDot) {
+            return Ok(Step {
+                axis: Axis::Parent,
+                test: NodeTest::Node,
+                predicates: vec![],
+            });
+        }
+
+        let axis = match (self.peek().cloned(), self.peek_at(1)) {
+            (Some(Token::At), _) => {
+                self.index += 1;
+                Axis::Attribute
+            }
+            (Some(Token::Name(name)), Some(Token::ColonColon)) => {
+                let axis = AXES
+                    .iter()
+                    .find(|(axis_name, _)| *axis_name == name)
+                    .map(|(_, axis)| *axis)
+                    .ok_or_else(|| self.error(ParseErrorKind::UnknownAxis(name)))?;
+                self.index += 2;
+                axis
+            }
+            _ => Axis::Child,
+        };
+        let test = match self.peek().cloned() {
+            Some(Token::Star) => NodeTest::Any,
+            Some(Token::PrefixStar(prefix)) => NodeTest::Prefix(prefix),
+            Some(Token::Name(name)) if self.peek_at(1) == Some(&Token::LeftParen) => {
+                if !is_node_type(&name) {
+                    return Err(self.unexpected());
+                }
+                self.index += 2;
+                if name == "processing-instruction" {
+                    if let Some(Token::Literal(_)) = self.peek() {
+                        self.index += 1;
+                    }
+                }
+                if self.peek() != Some(&Token::RightParen) {
+                    return Err(self.unexpected());
+                }
+                match name.as_str() {
+                    "text" => NodeTest::Text,
+                    "node" => NodeTest::Node,
+                    _ => NodeTest::Nothing,
+                }
+            }
+            Some(Token::Name(name)) => NodeTest::Name(name),
+            _ => return Err(self.unexpected()),
+        };
+        self.index += 1;
+        let predicates = self.predicates()?;
+        Ok(Step {
+            axis,
+            test,
+            predicates,
+        })
+    }
+
+    fn predicates(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut predicates = vec![];
+        while self.eat(&Token::LeftBracket) {
+            predicates.push(self.or_expr()?);
+            self.expect(&Token::RightBracket)?;
+        }
+        Ok(predicates)
+    }
+}
+
+fn is_node_type(name: &str) -> bool {
+    matches!(name, "comment" | "text" | "processing-instruction" | "node")
+}
+
+/// `descendant-or-self::node()`, which `//` abbreviates
+fn descendant_or_self() -> Step {
+    Step {
+        axis: Axis::DescendantOrSelf,
+        test: NodeTest::Node,
+        predicates: vec![],
+    }
+}
+
+// Evaluation
+
+/// Nodes in document order, so that node IDs are positions in document order
+struct Document<'a> {
+    nodes: Vec<NodeData<'a>>,
+}
+
+struct NodeData<'a> {
+    kind: Kind<'a>,
+    parent: Option<usize>,
+    /// Index in the parent's children
+    index: usize,
+    children: Vec<usize>,
+    attributes: Vec<usize>,
+    /// ID after the last descendant
+    end: usize,
+}
+
+#[derive(Clone, Copy)]
+enum Kind<'a> {
+    Root(&'a Element),
+    Tag(&'a Tag),
+    Text(&'a str),
+    Attribute(&'a str, &'a AttributeValue),
+}
+
+/// Value during evaluation, with nodes as IDs
+#[derive(Debug, Clone)]
+enum Object {
+    Nodes(Vec<usize>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+}
+
+struct Context {
+    node: usize,
+    position: usize,
+    size: usize,
+}
+
+impl<'a> Document<'a> {
+    fn new(element: &'a Element) -> Self {
+        let mut document = Document {
+            nodes: vec![NodeData {
+                kind: Kind::Root(element),
+                parent: None,
+                index: 0,
+                children: vec![],
+                attributes: vec![],
+                end: 0,
+            }],
+        };
+        let child = document.add(element, 0, 0);
+        document.nodes[0].children.push(child);
+        document.nodes[0].end = document.nodes.len();
+        document
+    }
+
+    #[allow(deprecated)]
+    fn add(&mut self, element: &'a Element, parent: usize, index: usize) -> usize {
+        let id = self.nodes.len();
+        let kind = match element {
+            Element::Tag(tag) => Kind::Tag(tag),
+            Element::String(s) => Kind::Text(s),
+        };
+        self.nodes.push(NodeData {
+            kind,
+            parent: Some(parent),
+            index,
+            children: vec![],
+            attributes: vec![],
+            end: id + 1,
+        });
+        if let Element::Tag(tag) = element {
+            let mut attributes: Vec<_> = tag
+                .attributes
+                .iter()
+                .filter(|(_, value)| !matches!(value, AttributeValue::Null))
+                .collect();
+            // Attribute order is implementation-dependent; sort it to be deterministic.
+            attributes.sort_by_key(|(name, _)| name.as_str());
+            for (name, value) in attributes {
+                let attribute = self.nodes.len();
+                self.nodes.push(NodeData {
+                    kind: Kind::Attribute(name, value),
+                    parent: Some(id),
+                    index: 0,
+                    children: vec![],
+                    attributes: vec![],
+                    end: attribute + 1,
+                });
+                self.nodes[id].attributes.push(attribute);
+            }
+            for (index, child) in tag.element_list.iter().enumerate() {
+                let child = self.add(child, id, index);
+                self.nodes[id].children.push(child);
+            }
+            self.nodes[id].end = self.nodes.len();
+        }
+        id
+    }
+
+    fn node(&self, id: usize) -> Node<'a> {
+        match self.nodes[id].kind {
+            Kind::Root(element) => Node::Root(element),
+            Kind::Tag(tag) => Node::Tag(tag),
+            Kind::Text(text) => Node::Text(text),
+            Kind::Attribute(name, value) => Node::Attribute(name, value),
+        }
+    }
+
+    fn is_attribute(&self, id: usize) -> bool {
+        matches!(self.nodes[id].kind, Kind::Attribute(..))
+    }
+
+    fn string_value(&self, id: usize) -> String {
+        match self.nodes[id].kind {
+            Kind::Root(_) | Kind::Tag(_) => (id + 1..self.nodes[id].end)
+                .filter_map(|id| match self.nodes[id].kind {
+                    Kind::Text(text) => Some(text),
+                    _ => None,
+                })
+                .collect(),
+            Kind::Text(text) => text.to_string(),
+            Kind::Attribute(_, value) => attribute_string(value),
+        }
+    }
+
+    #[allow(deprecated)]
+    fn name(&self, id: usize) -> &'a str {
+        match self.nodes[id].kind {
+            Kind::Tag(tag) => &tag.name,
+            Kind::Attribute(name, _) => name,
+            Kind::Root(_) | Kind::Text(_) => "",
+        }
+    }
+
+    /// Nodes on the axis, in reverse document order for reverse axes
+    fn axis(&self, id: usize, axis: Axis) -> Vec<usize> {
+        let node = &self.nodes[id];
+        let ancestors = || {
+            let mut ancestors = vec![];
+            let mut parent = node.parent;
+            while let Some(id) = parent {
+                ancestors.push(id);
+                parent = self.nodes[id].parent;
+            }
+            ancestors
+        };
+        let siblings = || match node.parent {
+            Some(parent) if !self.is_attribute(id) => &self.nodes[parent].children[..],
+            _ => &[],
+        };
+        match axis {
+            Axis::Ancestor => ancestors(),
+            Axis::AncestorOrSelf => {
+                let mut nodes = vec![id];
+                nodes.extend(ancestors());
+                nodes
+            }
+            Axis::Attribute => node.attributes.clone(),
+            Axis::Child => node.children.clone(),
+            Axis::Descendant => (id + 1..node.end)
+                .filter(|id| !self.is_attribute(*id))
+                .collect(),
+            Axis::DescendantOrSelf => {
+                let mut nodes = vec![id];
+                nodes.extend((id + 1..node.end).filter(|id| !self.is_attribute(*id)));
+                nodes
+            }
+            Axis::Following => (node.end..self.nodes.len())
+                .filter(|id| !self.is_attribute(*id))
+                .collect(),
+            Axis::FollowingSibling => siblings().get(node.index + 1..).unwrap_or(&[]).to_vec(),
+            Axis::Parent => node.parent.into_iter().collect(),
+            Axis::Preceding => {
+                let ancestors = ancestors();
+                (0..id)
+                    .rev()
+                    .filter(|id| !self.is_attribute(*id) && !ancestors.contains(id))
+                    .collect()
+            }
+            Axis::PrecedingSibling => {
+                let siblings = siblings();
+                siblings[..node.index.min(siblings.len())]
+                    .iter()
+                    .rev()
+                    .copied()
+                    .collect()
+            }
+            Axis::Itself => vec![id],
+        }
+    }
+
+    #[allow(deprecated)]
+    fn test(&self, id: usize, axis: Axis, test: &NodeTest) -> bool {
+        let kind = self.nodes[id].kind;
+        // Names match the principal node type of the axis.
+        let principal = match (axis, kind) {
+            (Axis::Attribute, Kind::Attribute(name, _)) => Some(name),
+            (Axis::Attribute, _) => None,
+            (_, Kind::Tag(tag)) => Some(tag.name.as_str()),
+            _ => None,
+        };
+        match test {
+            NodeTest::Any => principal.is_some(),
+            NodeTest::Prefix(prefix) => principal.map_or(false, |name| {
+                name.strip_prefix(prefix.as_str())
+                    .map_or(false, |rest| rest.starts_with(':'))
+            }),
+            NodeTest::Name(expected) => principal == Some(expected.as_str()),
+            NodeTest::Text => matches!(kind, Kind::Text(_)),
+            NodeTest::Node => true,
+            NodeTest::Nothing => false,
+        }
+    }
+
+    fn evaluate(&self, expr: &Expr, context: &Context) -> Object {
+        match expr {
+            Expr::Or(left, right) => Object::Boolean(
+                self.boolean(self.evaluate(left, context))
+                    || self.boolean(self.evaluate(right, context)),
+            ),
+            Expr::And(left, right) => Object::Boolean(
+                self.boolean(self.evaluate(left, context))
+                    && self.boolean(self.evaluate(right, context)),
+            ),
+            Expr::Compare(operator, left, right) => Object::Boolean(self.compare(
+                *operator,
+                self.evaluate(left, context),
+                self.evaluate(right, context),
+            )),
+            Expr::Arithmetic(operator, left, right) => {
+                let left = self.number(self.evaluate(left, context));
+                let right = self.number(self.evaluate(right, context));
+                Object::Number(match operator {
+                    ArithmeticOperator::Add => left + right,
+                    ArithmeticOperator::Subtract => left - right,
+                    ArithmeticOperator::Multiply => left * right,
+                    ArithmeticOperator::Divide => left / right,
+                    ArithmeticOperator::Modulo => left % right,
+                })
+            }
+            Expr::Negate(expr) => Object::Number(-self.number(self.evaluate(expr, context))),
+            Expr::Union(left, right) => {
+                let mut nodes = self.nodes(self.evaluate(left, context));
+                nodes.extend(self.nodes(self.evaluate(right, context)));
+                nodes.sort_unstable();
+                nodes.dedup();
+                Object::Nodes(nodes)
+            }
+            Expr::Path { absolute, steps } => {
+                let start = if *absolute { 0 } else { context.node };
+                Object::Nodes(self.steps(vec![start], steps))
+            }
+            Expr::Filter {
+                primary,
+                predicates,
+                steps,
+            } => {
+                let nodes = self.nodes(self.evaluate(primary, context));
+                let nodes = self.filter(nodes, predicates);
+                Object::Nodes(self.steps(nodes, steps))
+            }
+            Expr::Literal(s) => Object::String(s.clone()),
+            Expr::Number(n) => Object::Number(*n),
+            Expr::Function(function, arguments) => self.call(*function, arguments, context),
+        }
+    }
+
+    fn steps(&self, mut nodes: Vec<usize>, steps: &[Step]) -> Vec<usize> {
+        for step in steps {
+            let mut next = vec![];
+            for id in nodes {
+                let candidates = self
+                    .axis(id, step.axis)
+                    .into_iter()
+                    .filter(|id| self.test(*id, step.axis, &step.test))
+                    .collect();
+                next.extend(self.filter(candidates, &step.predicates));
+            }
+            next.sort_unstable();
+            next.dedup();
+            nodes = next;
+        }
+        nodes
+    }
+
+    /// Filter nodes in axis order by predicates.
+    fn filter(&self, mut nodes: Vec<usize>, predicates: &[Expr]) -> Vec<usize> {
+        for predicate in predicates {
+            let size = nodes.len();
+            nodes = nodes
+                .into_iter()
+                .enumerate()
+                .filter(|(index, id)| {
+                    let context = Context {
+                        node: *id,
+                        position: index + 1,
+                        size,
+                    };
+                    match self.evaluate(predicate, &context) {
+                        Object::Number(n) => n == context.position as f64,
+                        object => self.boolean(object),
+                    }
+                })
+                .map(|(_, id)| id)
+                .collect();
+        }
+        nodes
+    }
+
+    fn call(&self, function: Function, arguments: &[Expr], context: &Context) -> Object {
+        let argument = |index: usize| self.evaluate(&arguments[index], context);
+        let string = |index: usize| self.string(argument(index));
+        let number = |index: usize| self.number(argument(index));
+        // String of the argument, or the string value of the context node
+        let string_or_context = || match arguments.first() {
+            Some(_) => string(0),
+            None => self.string_value(context.node),
+        };
+        // First node of the argument, or the context node
+        let node_or_context = || match arguments.first() {
+            Some(_) => self.nodes(argument(0)).first().copied(),
+            None => Some(context.node),
+        };
+        match function {
+            Function::Last => Object::Number(context.size as f64),
+            Function::Position => Object::Number(context.position as f64),
+            Function::Count => Object::Number(self.nodes(argument(0)).len() as f64),
+            Function::LocalName => {
+                Object::String(node_or_context().map_or_else(String::new, |id| {
+                    let name = self.name(id);
+                    name.split_once(':')
+                        .map_or(name, |(_, local)| local)
+                        .to_string()
+                }))
+            }
+            Function::Name => Object::String(
+                node_or_context().map_or_else(String::new, |id| self.name(id).to_string()),
+            ),
+            Function::String => Object::String(string_or_context()),
+            Function::Concat => Object::String((0..arguments.len()).map(string).collect()),
+            Function::StartsWith => Object::Boolean(string(0).starts_with(&string(1))),
+            Function::Contains => Object::Boolean(string(0).contains(&string(1))),
+            Function::SubstringBefore => {
+                let s = string(0);
+                Object::String(
+                    s.split_once(&string(1))
+                        .map_or("", |(before, _)| before)
+                        .to_string(),
+                )
+            }
+            Function::SubstringAfter => {
+                let s = string(0);
+                Object::String(
+                    s.split_once(&string(1))
+                        .map_or("", |(_, after)| after)
+                        .to_string(),
+                )
+            }
+            Function::Substring => {
+                let s = string(0);
+                let start = round(number(1));
+                let end = match arguments.get(2) {
+                    Some(_) => start + round(number(2)),
+                    None => f64::INFINITY,
+                };
+                Object::String(
+                    s.chars()
+                        .enumerate()
+                        .filter(|(index, _)| {
+                            let position = (index + 1) as f64;
+                            position >= start && position < end
+                        })
+                        .map(|(_, c)| c)
+                        .collect(),
+                )
+            }
+            Function::StringLength => Object::Number(string_or_context().chars().count() as f64),
+            Function::NormalizeSpace => Object::String(
+                string_or_context()
+                    .split(xml::is_whitespace)
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            Function::Translate => {
+                let from: Vec<char> = string(1).chars().collect();
+                let to: Vec<char> = string(2).chars().collect();
+                Object::String(
+                    string(0)
+                        .chars()
+                        .filter_map(|c| match from.iter().position(|f| *f == c) {
+                            Some(index) => to.get(index).copied(),
+                            None => Some(c),
+                        })
+                        .collect(),
+                )
+            }
+            Function::Boolean => Object::Boolean(self.boolean(argument(0))),
+            Function::Not => Object::Boolean(!self.boolean(argument(0))),
+            Function::True => Object::Boolean(true),
+            Function::False => Object::Boolean(false),
+            Function::Number => Object::Number(match arguments.first() {
+                Some(_) => number(0),
+                None => string_to_number(&self.string_value(context.node)),
+            }),
+            Function::Sum => Object::Number(
+                self.nodes(argument(0))
+                    .into_iter()
+                    .map(|id| string_to_number(&self.string_value(id)))
+                    .sum(),
+            ),
+            Function::Floor => Object::Number(number(0).floor()),
+            Function::Ceiling => Object::Number(number(0).ceil()),
+            Function::Round => Object::Number(round(number(0))),
+        }
+    }
+
+    /// Nodes of a node-set, which the parser guarantees
+    fn nodes(&self, object: Object) -> Vec<usize> {
+        match object {
+            Object::Nodes(nodes) => nodes,
+            _ => vec![],
+        }
+    }
+
+    fn boolean(&self, object: Object) -> bool {
+        match object {
+            Object::Nodes(nodes) => !nodes.is_empty(),
+            Object::String(s) => !s.is_empty(),
+            Object::Number(n) => n != 0. && !n.is_nan(),
+            Object::Boolean(b) => b,
+        }
+    }
+
+    fn number(&self, object: Object) -> f64 {
+        match object {
+            Object::Nodes(_) | Object::String(_) => string_to_number(&self.string(object)),
+            Object::Number(n) => n,
+            Object::Boolean(b) => f64::from(u8::from(b)),
+        }
+    }
+
+    fn string(&self, object: Object) -> String {
+        match object {
+            Object::Nodes(nodes) => nodes
+                .first()
+                .map_or_else(String::new, |id| self.string_value(*id)),
+            Object::String(s) => s,
+            Object::Number(n) => number_to_string(n),
+            Object::Boolean(b) => b.to_string(),
+        }
+    }
+
+    fn compare(&self, operator: CompareOperator, left: Object, right: Object) -> bool {
+        match (left, right) {
+            (Object::Nodes(left), Object::Nodes(right)) => left.iter().any(|left| {
+                let left = self.string_value(*left);
+                right.iter().any(|right| {
+                    compare_atoms(
+                        operator,
+                        Object::String(left.clone()),
+                        Object::String(self.string_value(*right)),
+                    )
+                })
+            }),
+            (Object::Nodes(nodes), Object::Boolean(b)) => compare_atoms(
+                operator,
+                Object::Boolean(!nodes.is_empty()),
+                Object::Boolean(b),
+            ),
+            (Object::Boolean(b), Object::Nodes(nodes)) => compare_atoms(
+                operator,
+                Object::Boolean(b),
+                Object::Boolean(!nodes.is_empty()),
+            ),
+            (Object::Nodes(nodes), other) => nodes
+                .iter()
+                .any(|id| compare_atoms(operator, self.atom(*id, &other), other.clone())),
+            (other, Object::Nodes(nodes)) => nodes
+                .iter()
+                .any(|id| compare_atoms(operator, other.clone(), self.atom(*id, &other))),
+            (left, right) => compare_atoms(operator, left, right),
+        }
+    }
+
+    /// String value of a node, as a number if it is compared with a number
+    fn atom(&self, id: usize, other: &Object) -> Object {
+        let string = self.string_value(id);
+        match other {
+            Object::Number(_) => Object::Number(string_to_number(&string)),
+            _ => Object::String(string),
+        }
+    }
+}
+
+/// Compare values which are not node-sets.
+fn compare_atoms(operator: CompareOperator, left: Object, right: Object) -> bool {
+    let number = |object: &Object| match object {
+        Object::String(s) => string_to_number(s),
+        Object::Number(n) => *n,
+        Object::Boolean(b) => f64::from(u8::from(*b)),
+        Object::Nodes(_) => f64::NAN,
+    };
+    let equal = match operator {
+        CompareOperator::Equal => true,
+        CompareOperator::NotEqual => false,
+        CompareOperator::Less => return number(&left) < number(&right),
+        CompareOperator::LessEqual => return number(&left) <= number(&right),
+        CompareOperator::Greater => return number(&left) > number(&right),
+        CompareOperator::GreaterEqual => return number(&left) >= number(&right),
+    };
+    let is_equal = match (&left, &right) {
+        (Object::Boolean(_), _) | (_, Object::Boolean(_)) => {
+            let boolean = |object: &Object| match object {
+                Object::String(s) => !s.is_empty(),
+                Object::Number(n) => *n != 0. && !n.is_nan(),
+                Object::Boolean(b) => *b,
+                Object::Nodes(nodes) => !nodes.is_empty(),
+            };
+            boolean(&left) == boolean(&right)
+        }
+        (Object::Number(_), _) | (_, Object::Number(_)) => number(&left) == number(&right),
+        (Object::String(left), Object::String(right)) => left == right,
+        _ => false,
+    };
+    is_equal == equal
+}
+
+fn attribute_string(value: &AttributeValue) -> String {
+    match value {
+        AttributeValue::String(s) => s.clone(),
+        AttributeValue::Number(n) => n.to_string(),
+        AttributeValue::Bool(b) => b.to_string(),
+        AttributeValue::Null => String::new(),
+    }
+}
+
+fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(xml::is_whitespace);
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    let valid = !digits.is_empty()
+        && digits != "."
+        && digits.matches('.').count() <= 1
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.');
+    if valid {
+        s.parse().unwrap_or(f64::NAN)
+    } else {
+        f64::NAN
+    }
+}
+
+fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0. { "Infinity" } else { "-Infinity" }.to_string()
+    } else if n == 0. {
+        "0".to_string()
+    } else {
+        n.to_string()
+    }
+}
+
+fn round(n: f64) -> f64 {
+    if !n.is_finite() || n == 0. {
+        n
+    } else if (-0.5..0.).contains(&n) {
+        -0.
+    } else {
+        (n + 0.5).floor()
+    }
+}
+
+#[cfg(test)]
+use crate::test_helpers::from_json;
+
+#[cfg(test)]
+fn sample() -> Element {
+    from_json(
+        r#"["feed", {"xmlns:media": "http://search.yahoo.com/mrss/"},
+            ["title", "Example  feed "],
+            ["entry", {"id": "1", "rank": 3}, ["title", "First"], ["media:content", {"url": "a.png"}]],
+            ["entry", {"id": "2", "draft": true}, ["title", "Second"]],
+            ["entry", {"id": "3", "rank": 1.5}, ["title", "Third"], "note"]]"#,
+    )
+}
+
+#[cfg(test)]
+fn strings(value: Value) -> Vec<String> {
+    match value {
+        Value::Nodes(nodes) => nodes.iter().map(Node::string_value).collect(),
+        value => panic!("not a node-set: {value:?}"),
+    }
+}
+
+#[test]
+fn test_xpath_paths() {
+    let element = sample();
+    let query = |expression: &str| strings(element.xpath(expression).unwrap());
+    assert_eq!(query("/feed/entry/title"), ["First", "Second", "Third"]);
+    assert_eq!(query("entry/title"), ["First", "Second", "Third"]);
+    assert_eq!(
+        query("//title[1]"),
+        ["Example  feed ", "First", "Second", "Third"]
+    );
+    assert_eq!(query("(//title)[1]"), ["Example  feed "]);
+    assert_eq!(query("entry[2]/title"), ["Second"]);
+    assert_eq!(query("entry[last()]/@id"), ["3"]);
+    assert_eq!(query("entry[@draft]/title"), ["Second"]);
+    assert_eq!(query("entry[@rank > 2]/title"), ["First"]);
+    assert_eq!(query("entry[title = 'Third']/text()"), ["note"]);
+    assert_eq!(query("//media:content/@url"), ["a.png"]);
+    assert_eq!(query("//media:*/../title"), ["First"]);
+    assert_eq!(query("entry/@*[name() = 'rank']"), ["3", "1.5"]);
+    assert_eq!(query("/"), ["Example  feed FirstSecondThirdnote"]);
+    assert_eq!(
+        query("//entry | //feed/title"),
+        ["Example  feed ", "First", "Second", "Thirdnote"]
+    );
+    assert_eq!(query("//*[@id = 2]/title"), ["Second"]);
+}
+
+#[test]
+fn test_xpath_axes() {
+    let element = sample();
+    let query = |expression: &str| strings(element.xpath(expression).unwrap());
+    assert_eq!(query("//entry[1]/following-sibling::entry/@id"), ["2", "3"]);
+    assert_eq!(query("//entry[3]/preceding-sibling::entry[1]/@id"), ["2"]);
+    assert_eq!(query("//media:content/ancestor::*/@id"), ["1"]);
+    assert_eq!(
+        element
+            .xpath("name(//media:content/ancestor-or-self::*[2])")
+            .unwrap(),
+        Value::String("entry".to_string())
+    );
+    assert_eq!(query("//title[. = 'Second']/following::title"), ["Third"]);
+    assert_eq!(
+        query("//title[. = 'Second']/preceding::title"),
+        ["Example  feed ", "First"]
+    );
+    assert_eq!(
+        query("//entry[@id = 3]/descendant::text()"),
+        ["Third", "note"]
+    );
+    assert_eq!(query("//entry[@id = 3]/child::node()[last()]"), ["note"]);
+    assert_eq!(query("(//entry/self::entry[@rank])[2]/@id"), ["3"]);
+}
+
+#[test]
+fn test_xpath_values() {
+    let element = sample();
+    let query = |expression: &str| element.xpath(expression).unwrap();
+    let string = |s: &str| Value::String(s.to_string());
+    assert_eq!(query("count(entry)"), Value::Number(3.));
+    assert_eq!(query("sum(entry/@rank)"), Value::Number(4.5));
+    assert_eq!(query("normalize-space(title)"), string("Example feed"));
+    assert_eq!(query("string-length(entry[1]/title)"), Value::Number(5.));
+    assert_eq!(
+        query("concat(entry[1]/title, '-', 1 div 2)"),
+        string("First-0.5")
+    );
+    assert_eq!(query("substring('12345', 1.5, 2.6)"), string("234"));
+    assert_eq!(query("substring('12345', 0, 3)"), string("12"));
+    assert_eq!(query("substring-before('2022-09-21', '-')"), string("2022"));
+    assert_eq!(query("substring-after('2022-09-21', '-')"), string("09-21"));
+    assert_eq!(query("translate('bar', 'abc', 'AB')"), string("BAr"));
+    assert_eq!(query("local-name(//media:content)"), string("content"));
+    assert_eq!(query("name(//media:content)"), string("media:content"));
+    assert_eq!(
+        query("starts-with(title, 'Ex') and contains(title, 'feed')"),
+        Value::Boolean(true)
+    );
+    assert_eq!(query("not(entry[@id = 4])"), Value::Boolean(true));
+    assert_eq!(query("entry/@id = 2"), Value::Boolean(true));
+    assert_eq!(query("entry/@id != 2"), Value::Boolean(true));
+    assert_eq!(query("entry/@missing = ''"), Value::Boolean(false));
+    assert_eq!(query("7 mod 3 * -2 + 1"), Value::Number(-1.));
+    assert_eq!(
+        query("round(-0.5) = 0 and round(2.5) = 3"),
+        Value::Boolean(true)
+    );
+    assert_eq!(query("string(1 div 0)"), string("Infinity"));
+    assert_eq!(query("string(0 div 0)"), string("NaN"));
+    assert_eq!(query("number(' 12.5 ')"), Value::Number(12.5));
+    assert!(matches!(query("number('1e3')"), Value::Number(n) if n.is_nan()));
+    assert_eq!(query("floor(2.5) + ceiling(2.5)"), Value::Number(5.));
+    assert_eq!(query("string(1000000 * 1000000)"), string("1000000000000"));
+}
+
+#[test]
+fn test_xpath_parse_errors() {
+    let error = |s: &str| XPath::parse(s).unwrap_err();
+    assert_eq!(error("").kind(), &ParseErrorKind::UnexpectedEof);
+    assert_eq!(error("entry[").kind(), &ParseErrorKind::UnexpectedEof);
+    assert_eq!(
+        error("entry]").kind(),
+        &ParseErrorKind::UnexpectedToken("]".to_string())
+    );
+    assert_eq!(error("$x").kind(), &ParseErrorKind::UnexpectedChar('$'));
+    assert_eq!(
+        error("sideways::a").kind(),
+        &ParseErrorKind::UnknownAxis("sideways".to_string())
+    );
+    assert_eq!(
+        error("lower-case('A')").to_string(),
+        "unknown function lower-case() at position 0"
+    );
+    assert_eq!(
+        error("count()").kind(),
+        &ParseErrorKind::WrongArgumentCount("count".to_string())
+    );
+    assert_eq!(error("count('a')").position(), 6);
+    assert_eq!(error("a | 'b'").kind(), &ParseErrorKind::ExpectedNodeSet);
+    assert_eq!(error("'a'/b").kind(), &ParseErrorKind::ExpectedNodeSet);
+}
@@ -2,11 +2,10 @@
  * are cited from [the JsonML site](http://www.jsonml.org/).
  */
 
-use std::collections::HashMap;
-
-use jsonml::{AttributeValue, Element, Tag};
+use jsonml::{AttributeValue, Attributes, Element, Tag};
 
 #[test]
+#[allow(deprecated)]
 fn test_bullet_list_example() {
     let element: Element =
         serde_json::from_str(include_str!("fixtures/bullet-list.json")).expect("deserialize JSON");
@@ -14,11 +13,11 @@ fn test_bullet_list_example() {
         element,
         Element::Tag(Tag {
             name: "ul".to_string(),
-            attributes: HashMap::default(),
+            attributes: Attributes::default(),
             element_list: vec![
                 Element::Tag(Tag {
                     name: "li".to_string(),
-                    attributes: HashMap::from([(
+                    attributes: Attributes::from([(
                         "style".to_string(),
                         AttributeValue::String("color:red".to_string())
                     )]),
@@ -26,7 +25,7 @@ fn test_bullet_list_example() {
                 }),
                 Element::Tag(Tag {
                     name: "li".to_string(),
-                    attributes: HashMap::from([
+                    attributes: Attributes::from([
                         (
                             "title".to_string(),
                             AttributeValue::String("Some hover text.".to_string())
@@ -40,11 +39,11 @@ fn test_bullet_list_example() {
                 }),
                 Element::Tag(Tag {
                     name: "li".to_string(),
-                    attributes: HashMap::default(),
+                    attributes: Attributes::default(),
                     element_list: vec![
                         Element::Tag(Tag {
                             name: "span".to_string(),
-                            attributes: HashMap::from([(
+                            attributes: Attributes::from([(
                                 "class".to_string(),
                                 AttributeValue::String("code-example-third".to_string())
                             )]),
@@ -59,6 +58,7 @@ fn test_bullet_list_example() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn test_colorful_table_example() {
     let element: Element = serde_json::from_str(include_str!("fixtures/colorful-table.json"))
         .expect("deserialize JSON");
@@ -66,7 +66,7 @@ fn test_colorful_table_example() {
         element,
         Element::Tag(Tag {
             name: "table".to_string(),
-            attributes: HashMap::from([
+            attributes: Attributes::from([
                 (
                     "class".to_string(),
                     AttributeValue::String("MyTable".to_string())
@@ -79,11 +79,11 @@ fn test_colorful_table_example() {
             element_list: vec![
                 Element::Tag(Tag {
                     name: "tr".to_string(),
-                    attributes: HashMap::default(),
+                    attributes: Attributes::default(),
                     element_list: vec![
                         Element::Tag(Tag {
                             name: "td".to_string(),
-                            attributes: HashMap::from([
+                            attributes: Attributes::from([
                                 (
                                     "class".to_string(),
                                     AttributeValue::String("MyTD".to_string())
@@ -97,7 +97,7 @@ fn test_colorful_table_example() {
                         }),
                         Element::Tag(Tag {
                             name: "td".to_string(),
-                            attributes: HashMap::from([
+                            attributes: Attributes::from([
                                 (
                                     "class".to_string(),
                                     AttributeValue::String("MyTD".to_string())
@@ -113,11 +113,11 @@ fn test_colorful_table_example() {
                 }),
                 Element::Tag(Tag {
                     name: "tr".to_string(),
-                    attributes: HashMap::default(),
+                    attributes: Attributes::default(),
                     element_list: vec![
                         Element::Tag(Tag {
                             name: "td".to_string(),
-                            attributes: HashMap::from([
+                            attributes: Attributes::from([
                                 (
                                     "class".to_string(),
                                     AttributeValue::String("MyTD".to_string())
@@ -131,7 +131,7 @@ fn test_colorful_table_example() {
                         }),
                         Element::Tag(Tag {
                             name: "td".to_string(),
-                            attributes: HashMap::from([
+                            attributes: Attributes::from([
                                 (
                                     "class".to_string(),
                                     AttributeValue::String("MyTD".to_string())
@@ -147,11 +147,11 @@ fn test_colorful_table_example() {
                 }),
                 Element::Tag(Tag {
                     name: "tr".to_string(),
-                    attributes: HashMap::default(),
+                    attributes: Attributes::default(),
                     element_list: vec![
                         Element::Tag(Tag {
                             name: "td".to_string(),
-                            attributes: HashMap::from([
+                            attributes: Attributes::from([
                                 (
                                     "class".to_string(),
                                     AttributeValue::String("MyTD".to_string())
@@ -165,7 +165,7 @@ fn test_colorful_table_example() {
                         }),
                         Element::Tag(Tag {
                             name: "td".to_string(),
-                            attributes: HashMap::from([
+                            attributes: Attributes::from([
                                 (
                                     "class".to_string(),
                                     AttributeValue::String("MyTD".to_string())
@@ -179,7 +179,7 @@ fn test_colorful_table_example() {
                                 Element::String("\u{00A0}".to_string()),
                                 Element::Tag(Tag {
                                     name: "span".to_string(),
-                                    attributes: HashMap::from([(
+                                    attributes: Attributes::from([(
                                         "style".to_string(),
                                         AttributeValue::String(
                                             "background-color:maroon".to_string()
@@ -196,3 +196,57 @@ fn test_colorful_table_example() {
         })
     );
 }
+
+#[test]
+fn test_bullet_list_example_with_methods() {
+    let element: Element =
+        serde_json::from_str(include_str!("fixtures/bullet-list.json")).expect("deserialize JSON");
+
+    let mut first = Tag::new("li").unwrap();
+    first.set_attribute("style", "color:red").unwrap();
+    first.push("First Item");
+    let mut second = Tag::new("li").unwrap();
+    second.set_attribute("title", "Some hover text.").unwrap();
+    second.set_attribute("style", "color:green").unwrap();
+    second.push("Second Item");
+    let mut span = Tag::new("span").unwrap();
+    span.set_attribute("class", "code-example-third").unwrap();
+    span.push("Third");
+    let mut third = Tag::new("li").unwrap();
+    third.push(span);
+    third.push(" Item");
+    let mut list = Tag::new("ul").unwrap();
+    list.push(first);
+    list.push(second);
+    list.push(third);
+    assert_eq!(element, Element::Tag(list));
+}
+
+#[test]
+fn test_colorful_table_example_with_methods() {
+    let element: Element = serde_json::from_str(include_str!("fixtures/colorful-table.json"))
+        .expect("deserialize JSON");
+    let table = match element {
+        Element::Tag(tag) => tag,
+        Element::String(_) => panic!("expected a tag"),
+    };
+    assert_eq!(table.name(), "table");
+    assert_eq!(
+        table.attribute("class"),
+        Some(&AttributeValue::String("MyTable".to_string()))
+    );
+    assert_eq!(table.element_list().len(), 3);
+    for row in table.element_list() {
+        let row = match row {
+            Element::Tag(tag) => tag,
+            Element::String(_) => panic!("expected a tag"),
+        };
+        assert_eq!(row.name(), "tr");
+        assert!(row.attributes().is_empty());
+        assert_eq!(row.element_list().len(), 2);
+    }
+    let (name, attributes, element_list) = table.into_parts();
+    assert_eq!(name, "table");
+    assert_eq!(attributes.keys().collect::<Vec<_>>(), ["class", "style"]);
+    assert_eq!(element_list.len(), 3);
+}